@@ -1,5 +1,7 @@
+use crate::notebook::{Notebook, Rating};
 use gloo_net::http::Request;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone)]
@@ -10,11 +12,17 @@ pub enum ApiError {
     Unauthorized,
     #[error("Server error: {0}")]
     Server(String),
+    /// Any other non-2xx response, for callers that treat a status such as
+    /// 403 (feature turned off) or 404 as an answer rather than a failure.
+    #[error("Server error: status {0}")]
+    Status(u16),
 }
 
 #[derive(Deserialize, Clone)]
 pub struct MeResponse {
     pub username: String,
+    #[serde(default)]
+    pub is_admin: bool,
 }
 
 #[derive(Deserialize)]
@@ -35,10 +43,31 @@ pub struct ChatRequest {
     pub model: Option<String>,
     pub system_prompt: Option<String>,
     pub temperature: Option<f32>,
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
+    pub variables: HashMap<String, String>,
+    /// `system_prompt` is already rendered; the server sends it unchanged.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub rendered: bool,
+}
+
+/// Advanced sampling knobs sent alongside `temperature`. `None` (or an empty
+/// `stop` list) leaves the provider default in place.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SamplingParams {
     pub max_tokens: Option<usize>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub min_p: Option<f32>,
+    pub repeat_penalty: Option<f32>,
+    pub seed: Option<i64>,
+    pub num_ctx: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ChatMessage {
     pub role: String,
     pub content: String,
@@ -50,6 +79,16 @@ pub struct ChatChunk {
     pub done: bool,
 }
 
+/// Sent by `/api/chat` before the first chunk, with the digest of the
+/// answering model's weights when the provider reports one and the system
+/// prompt as rendered for this request.
+#[derive(Deserialize)]
+pub struct ChatMeta {
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+}
+
 pub struct ApiClient {
     base_url: String,
 }
@@ -71,7 +110,7 @@ impl ApiClient {
             return Err(ApiError::Unauthorized);
         }
         if !response.ok() {
-            return Err(ApiError::Server(format!("Status: {}", response.status())));
+            return Err(ApiError::Status(response.status()));
         }
         response
             .json::<MeResponse>()
@@ -85,7 +124,7 @@ impl ApiClient {
             .await
             .map_err(|e| ApiError::Network(e.to_string()))?;
         if !response.ok() {
-            return Err(ApiError::Server(format!("Status: {}", response.status())));
+            return Err(ApiError::Status(response.status()));
         }
         Ok(())
     }
@@ -100,7 +139,7 @@ impl ApiClient {
             return Err(ApiError::Unauthorized);
         }
         if !response.ok() {
-            return Err(ApiError::Server(format!("Status: {}", response.status())));
+            return Err(ApiError::Status(response.status()));
         }
         response
             .json::<ProvidersResponse>()
@@ -112,6 +151,308 @@ impl ApiClient {
         format!("{}/chat", self.base_url)
     }
 
+    pub async fn sandbox_status(&self) -> Result<SandboxStatus, ApiError> {
+        let response = Request::get(&format!("{}/sandbox", self.base_url))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<SandboxStatus>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn run_code(&self, req: &RunRequest) -> Result<RunResponse, ApiError> {
+        let response = Request::post(&format!("{}/sandbox/run", self.base_url))
+            .json(req)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<RunResponse>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn create_persona(&self, input: &PersonaInput) -> Result<SystemPrompt, ApiError> {
+        let response = Request::post(&format!("{}/prompts", self.base_url))
+            .json(input)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+        persona_response(response).await
+    }
+
+    pub async fn update_persona(
+        &self,
+        id: &str,
+        input: &PersonaInput,
+    ) -> Result<SystemPrompt, ApiError> {
+        let response = Request::put(&format!("{}/prompts/{}", self.base_url, id))
+            .json(input)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+        persona_response(response).await
+    }
+
+    pub async fn delete_persona(&self, id: &str) -> Result<(), ApiError> {
+        let response = Request::delete(&format!("{}/prompts/{}", self.base_url, id))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        Ok(())
+    }
+
+    /// The server's copy of one conversation; a 404 when it has none.
+    pub async fn get_conversation(&self, id: &str) -> Result<ConversationRecord, ApiError> {
+        let response = Request::get(&format!("{}/conversations/{}", self.base_url, id))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<ConversationRecord>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn sync_conversations(&self, req: &SyncRequest) -> Result<SyncResponse, ApiError> {
+        let response = Request::post(&format!("{}/conversations/sync", self.base_url))
+            .json(req)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<SyncResponse>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    /// Embed `texts` with the server's embedding model. A 403 means
+    /// semantic search is disabled on this server.
+    pub async fn embed(&self, texts: &[String]) -> Result<EmbedResponse, ApiError> {
+        let response = Request::post(&format!("{}/embed", self.base_url))
+            .json(&EmbedRequest { texts })
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<EmbedResponse>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    /// Ask `provider` to summarize `messages` for the context window. The
+    /// server may use a cheaper model than `model`.
+    pub async fn summarize(
+        &self,
+        provider: &str,
+        model: &str,
+        messages: &[ChatMessage],
+    ) -> Result<String, ApiError> {
+        let response = Request::post(&format!("{}/summarize", self.base_url))
+            .json(&SummarizeRequest {
+                provider,
+                model,
+                messages,
+            })
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<SummarizeResponse>()
+            .await
+            .map(|r| r.summary)
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    /// Count tokens in each of `texts` with `model`'s tokenizer. The server
+    /// answers 403 without tokenizers and 404 without one for `model`.
+    pub async fn tokenize(&self, model: &str, texts: &[String]) -> Result<Vec<usize>, ApiError> {
+        let response = Request::post(&format!("{}/tokenize", self.base_url))
+            .json(&TokenizeRequest { model, texts })
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<TokenizeResponse>()
+            .await
+            .map(|r| r.counts)
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    /// Append a rated reply to the server's feedback log. 403 when the
+    /// server doesn't keep one.
+    pub async fn post_feedback(&self, record: &FeedbackRecord) -> Result<(), ApiError> {
+        let response = Request::post(&format!("{}/feedback", self.base_url))
+            .json(record)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        Ok(())
+    }
+
+    pub async fn create_share(&self, req: &CreateShareRequest) -> Result<Share, ApiError> {
+        let response = Request::post(&format!("{}/shares", self.base_url))
+            .json(req)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<Share>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn list_shares(&self) -> Result<Vec<Share>, ApiError> {
+        let response = Request::get(&format!("{}/shares", self.base_url))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<Vec<Share>>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn delete_share(&self, token: &str) -> Result<(), ApiError> {
+        let response = Request::delete(&format!("{}/shares/{}", self.base_url, token))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        Ok(())
+    }
+
+    /// Works signed out; 401 only for links restricted to signed-in users.
+    pub async fn get_shared(&self, token: &str) -> Result<SharedConversation, ApiError> {
+        let response = Request::get(&format!("{}/shared/{}", self.base_url, token))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<SharedConversation>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn admin_health(&self) -> Result<AdminHealth, ApiError> {
+        let response = Request::get(&format!("{}/admin/health", self.base_url))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<AdminHealth>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
     pub async fn list_prompts(&self) -> Result<PromptsResponse, ApiError> {
         let response = Request::get(&format!("{}/prompts", self.base_url))
             .send()
@@ -122,13 +463,320 @@ impl ApiClient {
             return Err(ApiError::Unauthorized);
         }
         if !response.ok() {
-            return Err(ApiError::Server(format!("Status: {}", response.status())));
+            return Err(ApiError::Status(response.status()));
         }
         response
             .json::<PromptsResponse>()
             .await
             .map_err(|e| ApiError::Network(e.to_string()))
     }
+
+    /// 403 when the server runs no batch jobs.
+    pub async fn list_jobs(&self) -> Result<Vec<Job>, ApiError> {
+        let response = Request::get(&format!("{}/jobs", self.base_url))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<Vec<Job>>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    /// Problems with the upload, template or schedule come back as 400s
+    /// whose message is worth showing in the form.
+    pub async fn create_job(&self, req: &CreateJobRequest) -> Result<Job, ApiError> {
+        let response = Request::post(&format!("{}/jobs", self.base_url))
+            .json(req)
+            .map_err(|e| ApiError::Network(e.to_string()))?
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if response.status() == 400 || response.status() == 403 {
+            let msg = response
+                .json::<ErrorBody>()
+                .await
+                .map(|b| b.error)
+                .unwrap_or_else(|_| "Invalid job".to_string());
+            return Err(ApiError::Server(msg));
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<Job>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn cancel_job(&self, id: &str) -> Result<Job, ApiError> {
+        let response = Request::post(&format!("{}/jobs/{}/cancel", self.base_url, id))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<Job>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    pub async fn delete_job(&self, id: &str) -> Result<(), ApiError> {
+        let response = Request::delete(&format!("{}/jobs/{}", self.base_url, id))
+            .send()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        Ok(())
+    }
+
+    /// The next page of a job's results, those with `seq` above `after`.
+    pub async fn job_results(&self, id: &str, after: i64) -> Result<Vec<JobResult>, ApiError> {
+        let response = Request::get(&format!(
+            "{}/jobs/{}/results?after={}",
+            self.base_url, id, after
+        ))
+        .send()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))?;
+
+        if response.status() == 401 {
+            return Err(ApiError::Unauthorized);
+        }
+        if !response.ok() {
+            return Err(ApiError::Status(response.status()));
+        }
+        response
+            .json::<Vec<JobResult>>()
+            .await
+            .map_err(|e| ApiError::Network(e.to_string()))
+    }
+
+    /// Every result as a `csv` or `jsonl` attachment.
+    pub fn job_download_url(&self, id: &str, format: &str) -> String {
+        format!("{}/jobs/{}/results?format={}", self.base_url, id, format)
+    }
+
+    /// Server-sent `job`, `deleted` and `lagged` events for the user's jobs.
+    pub fn job_events_url(&self) -> String {
+        format!("{}/jobs/events", self.base_url)
+    }
+}
+
+/// Persona writes report validation problems (duplicate name, empty prompt)
+/// as 400s whose message is worth showing in the editor.
+async fn persona_response(response: gloo_net::http::Response) -> Result<SystemPrompt, ApiError> {
+    if response.status() == 401 {
+        return Err(ApiError::Unauthorized);
+    }
+    if response.status() == 400 {
+        let msg = response
+            .json::<ErrorBody>()
+            .await
+            .map(|b| b.error)
+            .unwrap_or_else(|_| "Invalid persona".to_string());
+        return Err(ApiError::Server(msg));
+    }
+    if !response.ok() {
+        return Err(ApiError::Status(response.status()));
+    }
+    response
+        .json::<SystemPrompt>()
+        .await
+        .map_err(|e| ApiError::Network(e.to_string()))
+}
+
+/// A conversation as exchanged with `/api/conversations/sync`. `data` is the
+/// serialized `StoredConversation`; tombstones carry none.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConversationRecord {
+    pub id: String,
+    /// `metadata.modified_at` in milliseconds; the newer copy wins conflicts.
+    pub modified_at: i64,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+pub struct SyncRequest {
+    pub since: i64,
+    pub changes: Vec<ConversationRecord>,
+}
+
+#[derive(Deserialize)]
+pub struct SyncResponse {
+    pub cursor: i64,
+    /// Ids of pushed changes the server now holds.
+    #[serde(default)]
+    pub acked: Vec<String>,
+    pub changes: Vec<ConversationRecord>,
+}
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    texts: &'a [String],
+}
+
+#[derive(Deserialize)]
+pub struct EmbedResponse {
+    pub model: String,
+    pub embeddings: Vec<Vec<f32>>,
+}
+
+/// A rated reply with the request that produced it. The server log keeps
+/// every change; the last record for a cell is its current rating.
+#[derive(Serialize)]
+pub struct FeedbackRecord {
+    pub conversation_id: String,
+    pub cell_id: usize,
+    pub rating: Option<Rating>,
+    pub comment: String,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub model_digest: Option<String>,
+    pub persona: Option<String>,
+    pub system_prompt: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    pub reply: String,
+}
+
+#[derive(Serialize)]
+struct TokenizeRequest<'a> {
+    model: &'a str,
+    texts: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct TokenizeResponse {
+    counts: Vec<usize>,
+}
+
+#[derive(Serialize)]
+struct SummarizeRequest<'a> {
+    provider: &'a str,
+    model: &'a str,
+    messages: &'a [ChatMessage],
+}
+
+#[derive(Deserialize)]
+struct SummarizeResponse {
+    summary: String,
+}
+
+/// What a shared link shows: the notebook and who answered, without the
+/// context state or template variables.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ShareSnapshot {
+    pub persona: String,
+    pub model: String,
+    pub notebook: Notebook,
+}
+
+#[derive(Serialize)]
+pub struct CreateShareRequest {
+    pub conversation_id: String,
+    pub title: String,
+    pub snapshot: ShareSnapshot,
+    pub require_auth: bool,
+    pub expires_in_secs: Option<i64>,
+}
+
+/// One of the user's links, as listed for revoking.
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct Share {
+    pub token: String,
+    pub conversation_id: String,
+    pub title: String,
+    pub require_auth: bool,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+impl Share {
+    pub fn url(&self) -> String {
+        let origin = web_sys::window()
+            .and_then(|w| w.location().origin().ok())
+            .unwrap_or_default();
+        format!("{}/s/{}", origin, self.token)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|at| at <= chrono::Utc::now().timestamp())
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct SharedConversation {
+    pub title: String,
+    pub owner: String,
+    pub snapshot: ShareSnapshot,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct AdminHealth {
+    pub providers: Vec<ProviderHealth>,
+    pub prompts: PromptStatus,
+    pub sandbox_enabled: bool,
+    #[serde(default)]
+    pub sandbox_unavailable: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ProviderHealth {
+    pub name: String,
+    pub available: bool,
+}
+
+/// Outcome of the server's last prompts.toml load.
+#[derive(Deserialize, Clone)]
+pub struct PromptStatus {
+    pub source: Option<String>,
+    pub loaded_at: i64,
+    pub count: usize,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+    pub warnings: Vec<String>,
+}
+
+/// Body for creating or updating one of the user's personas.
+#[derive(Serialize)]
+pub struct PersonaInput {
+    pub name: String,
+    pub prompt: String,
+    pub suggested_models: Vec<String>,
+    pub options: PromptOptions,
+    pub variables: Vec<PromptVariable>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
 }
 
 #[derive(Deserialize)]
@@ -138,7 +786,154 @@ pub struct PromptsResponse {
 
 #[derive(Deserialize, Clone)]
 pub struct SystemPrompt {
+    /// Present only on the user's own personas, which can be edited.
+    #[serde(default)]
+    pub id: Option<String>,
     pub name: String,
     pub prompt: String,
     pub suggested_models: Vec<String>,
+    #[serde(default)]
+    pub options: PromptOptions,
+    #[serde(default)]
+    pub variables: Vec<PromptVariable>,
+}
+
+/// A user-declared `{{name}}` placeholder in a persona prompt.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PromptVariable {
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+impl SystemPrompt {
+    /// Values to send for this persona's declared variables, falling back to
+    /// each variable's default when the user left it blank.
+    pub fn resolve_variables(&self, values: &HashMap<String, String>) -> HashMap<String, String> {
+        self.variables
+            .iter()
+            .filter_map(|var| {
+                values
+                    .get(&var.name)
+                    .filter(|v| !v.trim().is_empty())
+                    .cloned()
+                    .or_else(|| var.default.clone())
+                    .map(|value| (var.name.clone(), value))
+            })
+            .collect()
+    }
+}
+
+/// Per-persona defaults from `[prompts.options]`.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct PromptOptions {
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(flatten)]
+    pub sampling: SamplingParams,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct SandboxStatus {
+    pub languages: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct RunRequest {
+    pub language: String,
+    pub source: String,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RunResponse {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub truncated: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Scheduled,
+    Done,
+    Cancelled,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Scheduled => "scheduled",
+            Self::Done => "done",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+/// A batch or scheduled prompt job and the progress of its current run.
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct Job {
+    pub id: String,
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub persona: Option<String>,
+    pub template: String,
+    #[serde(default)]
+    pub columns: Vec<String>,
+    pub schedule: Option<String>,
+    pub status: JobStatus,
+    pub run: i64,
+    pub total: i64,
+    pub done: i64,
+    pub failed: i64,
+    /// Highest result `seq`; results above the last imported one are new.
+    pub last_seq: i64,
+    pub error: Option<String>,
+    pub next_run_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// One row's answer.
+#[derive(Deserialize, Clone)]
+pub struct JobResult {
+    pub seq: i64,
+    pub index: i64,
+    pub prompt: String,
+    pub reply: Option<String>,
+    pub error: Option<String>,
+    pub finished_at: i64,
+}
+
+#[derive(Serialize)]
+pub struct JobInput {
+    /// `csv` or `jsonl`.
+    pub format: String,
+    pub text: String,
+}
+
+#[derive(Serialize)]
+pub struct CreateJobRequest {
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    pub persona: Option<String>,
+    pub system_prompt: Option<String>,
+    pub variables: HashMap<String, String>,
+    pub template: String,
+    pub options: PromptOptions,
+    pub concurrency: Option<usize>,
+    pub schedule: Option<String>,
+    pub input: Option<JobInput>,
 }
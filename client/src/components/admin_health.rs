@@ -0,0 +1,106 @@
+use crate::api::{AdminHealth, ApiClient, ApiError};
+use crate::components::auth::redirect_to_login;
+use chrono::{Local, TimeZone};
+use leptos::*;
+
+fn format_time(secs: i64) -> String {
+    Local
+        .timestamp_opt(secs, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "—".to_string())
+}
+
+/// Server status for admins: provider reachability and the outcome of the
+/// last prompts.toml load, including why a reload was rejected.
+#[component]
+pub fn AdminHealthPage() -> impl IntoView {
+    let (health, set_health) = create_signal(None::<AdminHealth>);
+    let (error, set_error) = create_signal(None::<String>);
+
+    let refresh = move || {
+        spawn_local(async move {
+            match ApiClient::new().admin_health().await {
+                Ok(h) => {
+                    set_health.set(Some(h));
+                    set_error.set(None);
+                }
+                Err(ApiError::Unauthorized) => redirect_to_login(),
+                Err(ApiError::Status(403)) => {
+                    set_error.set(Some("Only admins can view server health.".to_string()));
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    };
+    refresh();
+
+    view! {
+        <div class="admin-page">
+            <div class="admin-head">
+                <h2>"Server health"</h2>
+                <button class="admin-refresh" on:click=move |_| refresh()>"Refresh"</button>
+                <a href="/">"Back to chat"</a>
+            </div>
+            {move || error.get().map(|e| view! { <div class="error-message">{e}</div> })}
+            {move || health.get().map(|h| {
+                let prompts = h.prompts;
+                view! {
+                    <section class="admin-section">
+                        <h3>"Providers"</h3>
+                        {h.providers.into_iter().map(|p| view! {
+                            <div class="admin-row">
+                                <span class="status-dot" class:offline=!p.available></span>
+                                <span>{p.name}</span>
+                                <span class="admin-meta">
+                                    {if p.available { "available" } else { "unreachable" }}
+                                </span>
+                            </div>
+                        }).collect_view()}
+                        <div class="admin-row">
+                            <span>"Code execution"</span>
+                            <span class="admin-meta">
+                                {match (h.sandbox_enabled, h.sandbox_unavailable) {
+                                    (true, _) => "enabled".to_string(),
+                                    (false, Some(reason)) => format!("unavailable: {}", reason),
+                                    (false, None) => "disabled".to_string(),
+                                }}
+                            </span>
+                        </div>
+                    </section>
+                    <section class="admin-section">
+                        <h3>"Prompts"</h3>
+                        <div class="admin-row">
+                            <span>"Source"</span>
+                            <span class="admin-meta">
+                                {prompts.source.unwrap_or_else(|| "built-in defaults".to_string())}
+                            </span>
+                        </div>
+                        <div class="admin-row">
+                            <span>"Loaded"</span>
+                            <span class="admin-meta">
+                                {format!("{} prompts at {}", prompts.count, format_time(prompts.loaded_at))}
+                            </span>
+                        </div>
+                        {prompts.last_error.map(|e| view! {
+                            <div class="error-message">
+                                <strong>
+                                    {format!(
+                                        "Last reload rejected ({}): ",
+                                        prompts.last_error_at.map(format_time).unwrap_or_default()
+                                    )}
+                                </strong>
+                                {e}
+                            </div>
+                        })}
+                        {(!prompts.warnings.is_empty()).then(|| view! {
+                            <ul class="admin-warnings">
+                                {prompts.warnings.into_iter().map(|w| view! { <li>{w}</li> }).collect_view()}
+                            </ul>
+                        })}
+                    </section>
+                }
+            })}
+        </div>
+    }
+}
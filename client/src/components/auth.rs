@@ -8,6 +8,16 @@ pub fn redirect_to_login() {
     }
 }
 
+/// Log in, then land back on `path` instead of the chat.
+pub fn redirect_to_login_then(path: &str) {
+    if let Some(win) = web_sys::window() {
+        let return_to = String::from(js_sys::encode_uri_component(path));
+        let _ = win
+            .location()
+            .assign(&format!("{LOGIN_PATH}?return_to={return_to}"));
+    }
+}
+
 #[component]
 pub fn LoginRedirect() -> impl IntoView {
     create_effect(|_| {
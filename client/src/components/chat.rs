@@ -1,22 +1,48 @@
-use crate::api::{ApiClient, ChatMessage, ChatRequest, ProviderInfo, SystemPrompt};
+use crate::api::{
+    ApiClient, ApiError, ChatMessage, ChatRequest, FeedbackRecord, PersonaInput, ProviderInfo,
+    RunRequest, SamplingParams, SandboxStatus, ShareSnapshot, SystemPrompt,
+};
+use crate::components::compare_picker::ComparePicker;
 use crate::components::composer::Composer;
-use crate::components::context_manager::ContextManager;
+use crate::components::context_inspector::ContextInspector;
+use crate::components::context_manager::{ContextManager, ContextStrategy, SummaryJob};
 use crate::components::empty_state::EmptyState;
+use crate::components::export_menu::ExportMenu;
+use crate::components::icons::{IconLayers, IconRedo, IconUndo};
 use crate::components::model_picker::ModelPicker;
 use crate::components::persona_picker::PersonaPicker;
+use crate::components::regenerate_menu::{Regenerate, RegenerateOptions};
+use crate::components::share_menu::ShareMenu;
 use crate::components::sidebar::Sidebar;
 use crate::components::sidebar_resize::{load_saved_width, SidebarResize};
-use crate::notebook::cell::{CellContext, CellView};
-use crate::notebook::{CellContent, CellId, Notebook};
-use crate::simple_storage::SimpleStorage;
-use crate::storage::{ConversationMetadata, StoredConversation};
+use crate::export;
+use crate::jobs;
+use crate::notebook::cell::{reveal_cell, CellContext, CellOp, CellView, RunSnippet};
+use crate::notebook::{
+    Cell, CellContent, CellId, CodeRun, ContextItem, Feedback, Notebook, Provenance,
+};
+use crate::search_index::{self, SearchHit};
+use crate::semantic_index::{self, SemanticHit};
+use crate::storage::{
+    check_quota, estimate_tokens, evicted_conversations, forget_evicted, is_quota_error,
+    restore_evicted, AppStorage, ConversationMetadata, ConversationRef, ConversationStore, JobLink,
+    QuotaCheck, StoredConversation, EVICT_USAGE,
+};
+use crate::sync;
+use crate::token_count;
 use chrono::Utc;
 use leptos::html::Div;
 use leptos::*;
+use std::collections::HashMap;
 use uuid::Uuid;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 
+/// Message snippets listed under the sidebar's search results.
+const MAX_MESSAGE_HITS: usize = 30;
+/// Cell edits that can be undone.
+const MAX_UNDO: usize = 50;
+
 fn read_local(key: &str) -> Option<String> {
     web_sys::window()
         .and_then(|w| w.local_storage().ok().flatten())
@@ -29,24 +55,86 @@ fn write_local(key: &str, value: &str) {
     }
 }
 
+fn remove_local(key: &str) {
+    if let Some(s) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        let _ = s.remove_item(key);
+    }
+}
+
+/// Move the old single `custom_prompt` localStorage slot into a server-side
+/// persona so it follows the user to other browsers. The slot is kept if the
+/// upload fails and retried on the next load.
+async fn migrate_custom_prompt(
+    client: &ApiClient,
+    prompts: RwSignal<Vec<SystemPrompt>>,
+    selected_name: RwSignal<String>,
+) {
+    let Some(text) = read_local("custom_prompt") else {
+        return;
+    };
+    let existing = prompts.get_untracked();
+    if text.trim().is_empty() || existing.iter().any(|p| p.prompt == text) {
+        remove_local("custom_prompt");
+        return;
+    }
+    let name = std::iter::once("Custom".to_string())
+        .chain((2..).map(|n| format!("Custom {n}")))
+        .find(|name| !existing.iter().any(|p| &p.name == name))
+        .unwrap_or_default();
+    let input = PersonaInput {
+        name,
+        prompt: text,
+        suggested_models: Vec::new(),
+        options: Default::default(),
+        variables: Vec::new(),
+    };
+    match client.create_persona(&input).await {
+        Ok(persona) => {
+            if selected_name.get_untracked() == "Custom" {
+                selected_name.set(persona.name.clone());
+            }
+            prompts.update(|list| list.push(persona));
+            remove_local("custom_prompt");
+        }
+        Err(e) => {
+            web_sys::console::error_1(&format!("custom prompt migration: {}", e).into());
+        }
+    }
+}
+
 #[component]
-pub fn Chat<F, G>(user_name: Signal<String>, on_auth_error: F, on_logout: G) -> impl IntoView
+pub fn Chat<F, G>(
+    user_name: Signal<String>,
+    is_admin: Signal<bool>,
+    on_auth_error: F,
+    on_logout: G,
+) -> impl IntoView
 where
     F: Fn() + Clone + 'static,
     G: Fn() + Clone + 'static,
 {
     let (notebook, set_notebook) = create_signal(Notebook::new());
+    // Snapshots for undoing cell edits. Anything else that changes the
+    // notebook (a new message, a code run, loading) starts a fresh history.
+    let undo_stack = create_rw_signal(Vec::<Notebook>::new());
+    let redo_stack = create_rw_signal(Vec::<Notebook>::new());
+    let clear_history = move || {
+        undo_stack.set(Vec::new());
+        redo_stack.set(Vec::new());
+    };
     let (auth_error_triggered, set_auth_error_triggered) = create_signal(false);
 
     let saved_provider = read_local("selected_provider").unwrap_or_default();
     let saved_model = read_local("selected_model").unwrap_or_default();
     let saved_prompt =
         read_local("selected_prompt").unwrap_or_else(|| "General Assistant".to_string());
-    let saved_custom = read_local("custom_prompt").unwrap_or_default();
     let saved_input = read_local("pending_input").unwrap_or_default();
     let saved_temp = read_local("temperature")
         .and_then(|s| s.parse::<f32>().ok())
         .unwrap_or(0.7);
+    let saved_sampling = read_local("sampling_params")
+        .and_then(|s| serde_json::from_str::<SamplingParams>(&s).ok())
+        .unwrap_or_default();
     let saved_theme = read_local("gc_theme").unwrap_or_else(|| "light".to_string());
 
     let providers = create_rw_signal(Vec::<ProviderInfo>::new());
@@ -54,8 +142,11 @@ where
     let selected_model = create_rw_signal(saved_model);
     let system_prompts = create_rw_signal(Vec::<SystemPrompt>::new());
     let selected_prompt_name = create_rw_signal(saved_prompt);
-    let custom_prompt = create_rw_signal(saved_custom);
+    let prompt_variables = create_rw_signal(HashMap::<String, String>::new());
     let temperature = create_rw_signal(saved_temp);
+    let sampling = create_rw_signal(saved_sampling);
+    // Models answering alongside the selected one.
+    let compare_targets = create_rw_signal(Vec::<(String, String)>::new());
     let input_value = create_rw_signal(saved_input.clone());
     let (is_streaming, set_is_streaming) = create_signal(false);
     let (should_submit, set_should_submit) = create_signal(false);
@@ -64,6 +155,7 @@ where
     let sidebar_width = create_rw_signal(load_saved_width());
     let theme = create_rw_signal(saved_theme);
     let search_query = create_rw_signal(String::new());
+    let sandbox = create_rw_signal(SandboxStatus::default());
 
     if !saved_input.is_empty() {
         if let Some(s) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
@@ -81,10 +173,59 @@ where
         }));
 
     let context_manager = ContextManager::new();
-    let simple_storage = SimpleStorage::new();
+    // Compression puts a heuristic summary in place at once and asks the
+    // model for a real one; if that fails the heuristic stays.
+    context_manager.set_summarizer({
+        let context_manager = context_manager.clone();
+        move |job: SummaryJob| {
+            let provider = selected_provider.get_untracked();
+            let model = selected_model.get_untracked();
+            let context_manager = context_manager.clone();
+            spawn_local(async move {
+                let summary = match ApiClient::new()
+                    .summarize(&provider, &model, &job.messages)
+                    .await
+                {
+                    Ok(summary) => Some(summary),
+                    Err(e) => {
+                        web_sys::console::warn_1(
+                            &format!("summarize: {}; keeping the heuristic summary", e).into(),
+                        );
+                        None
+                    }
+                };
+                context_manager.resolve_summary(&job, summary);
+            });
+        }
+    });
+    // Opened on mount; nothing is loaded or saved until it is ready.
+    let storage = create_rw_signal(None::<AppStorage>);
+    // The conversation whose stored copy is on screen. Saves wait for it, so
+    // a half-switched view is never written under the new id.
+    let loaded_id = create_rw_signal(None::<String>);
+    // Kept from the loaded copy so saves don't cut a job conversation loose
+    // from its job (see `jobs`).
+    let job_link = store_value(None::<JobLink>);
+    let storage_notice = create_rw_signal(None::<String>);
     let (created_at, set_created_at) = create_signal(Utc::now());
-    let (conversations, set_conversations) =
-        create_signal(Vec::<crate::storage::ConversationRef>::new());
+    let (conversations, set_conversations) = create_signal(Vec::<ConversationRef>::new());
+
+    let refresh_list = move || {
+        let Some(storage) = storage.get_untracked() else {
+            return;
+        };
+        spawn_local(async move {
+            if let Ok(mut list) = storage.list_conversations(50).await {
+                let evicted: Vec<ConversationRef> = evicted_conversations()
+                    .into_iter()
+                    .filter(|e| !list.iter().any(|c| c.id == e.id))
+                    .collect();
+                list.extend(evicted);
+                list.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+                set_conversations.set(list);
+            }
+        });
+    };
 
     create_effect(move |_| {
         if auth_error_triggered.get() {
@@ -92,31 +233,76 @@ where
         }
     });
 
+    // Bumped when a sync pull rewrites the open conversation.
+    let (reload_tick, set_reload_tick) = create_signal(0u32);
+
     create_effect({
         let context_manager = context_manager.clone();
-        let simple_storage = simple_storage.clone();
         move |_| {
             let current_id = conversation_id.get();
-            if let Ok(Some(stored)) = simple_storage.load_conversation(&current_id) {
-                context_manager.restore_state(stored.context_state);
-                set_notebook.update(|nb| *nb = stored.notebook);
-                set_created_at.set(stored.metadata.created_at);
-                if !stored.metadata.provider.is_empty() {
-                    selected_provider.set(stored.metadata.provider);
+            let _ = reload_tick.get();
+            let Some(storage) = storage.get() else {
+                return;
+            };
+            let context_manager = context_manager.clone();
+            spawn_local(async move {
+                let mut stored = match storage.load_conversation(&current_id).await {
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        web_sys::console::error_2(&"load conversation:".into(), &e);
+                        return;
+                    }
+                };
+                if stored.is_none() {
+                    match restore_evicted(&storage, &current_id).await {
+                        Ok(Some(conv)) => {
+                            stored = Some(conv);
+                            refresh_list();
+                        }
+                        Ok(None) => {}
+                        // Never marked loaded, so nothing typed here is saved
+                        // over the server's copy.
+                        Err(e) => {
+                            if matches!(e, ApiError::Unauthorized) {
+                                set_auth_error_triggered.set(true);
+                            }
+                            set_notebook.update(Notebook::clear);
+                            storage_notice.set(Some(format!(
+                                "This conversation is only on the server, and fetching it \
+                                 failed ({}). Select it again to retry.",
+                                e
+                            )));
+                            return;
+                        }
+                    }
                 }
-                if !stored.metadata.model.is_empty() {
-                    selected_model.set(stored.metadata.model);
+                // The user may have switched again while this was loading.
+                if conversation_id.get_untracked() != current_id {
+                    return;
                 }
-            }
+                job_link.set_value(stored.as_ref().and_then(|s| s.metadata.job.clone()));
+                if let Some(stored) = stored {
+                    context_manager.restore_state(stored.context_state);
+                    context_manager.set_pinned(pinned_messages(&stored.notebook));
+                    set_notebook.update(|nb| *nb = stored.notebook);
+                    set_created_at.set(stored.metadata.created_at);
+                    prompt_variables.set(stored.metadata.variables);
+                    if !stored.metadata.provider.is_empty() {
+                        selected_provider.set(stored.metadata.provider);
+                    }
+                    if !stored.metadata.model.is_empty() {
+                        selected_model.set(stored.metadata.model);
+                    }
+                }
+                clear_history();
+                loaded_id.set(Some(current_id));
+            });
         }
     });
 
-    create_effect({
-        let simple_storage = simple_storage.clone();
-        move |_| {
-            if let Ok(list) = simple_storage.list_conversations(50) {
-                set_conversations.set(list);
-            }
+    create_effect(move |_| {
+        if storage.with(Option::is_some) {
+            refresh_list();
         }
     });
 
@@ -149,12 +335,22 @@ where
                 }
             }
             match client.list_prompts().await {
-                Ok(resp) => system_prompts.set(resp.prompts),
+                Ok(resp) => {
+                    system_prompts.set(resp.prompts);
+                    migrate_custom_prompt(&client, system_prompts, selected_prompt_name).await;
+                }
                 Err(crate::api::ApiError::Unauthorized) => set_auth_error_triggered.set(true),
                 Err(e) => {
                     web_sys::console::error_1(&format!("prompts: {}", e).into());
                 }
             }
+            match client.sandbox_status().await {
+                Ok(status) => sandbox.set(status),
+                Err(crate::api::ApiError::Unauthorized) => set_auth_error_triggered.set(true),
+                Err(e) => {
+                    web_sys::console::error_1(&format!("sandbox: {}", e).into());
+                }
+            }
         });
     });
 
@@ -178,18 +374,64 @@ where
         }
     });
     create_effect(move |_| {
-        let v = custom_prompt.get();
+        let v = temperature.get();
         if initial_load_complete.get() {
-            write_local("custom_prompt", &v);
+            write_local("temperature", &v.to_string());
         }
     });
     create_effect(move |_| {
-        let v = temperature.get();
+        let v = sampling.get();
         if initial_load_complete.get() {
-            write_local("temperature", &v.to_string());
+            if let Ok(json) = serde_json::to_string(&v) {
+                write_local("sampling_params", &json);
+            }
         }
     });
 
+    // Exact counts come from the selected model's tokenizer.
+    create_effect(move |_| token_count::set_model(&selected_model.get()));
+    token_count::on_counted({
+        let context_manager = context_manager.clone();
+        move || context_manager.refresh_tokens()
+    });
+
+    // The persona's system prompt is sent with every request; count it
+    // against the window.
+    create_effect({
+        let context_manager = context_manager.clone();
+        move |_| {
+            let name = selected_prompt_name.get();
+            let _ = context_manager.token_epoch();
+            let tokens = system_prompts.with(|prompts| {
+                prompts
+                    .iter()
+                    .find(|p| p.name == name)
+                    .map(|p| estimate_tokens(&p.prompt))
+                    .unwrap_or(0)
+            });
+            untrack(|| context_manager.set_reserved_tokens(tokens));
+        }
+    });
+
+    // Switching persona applies its declared sampling defaults. The first run
+    // only records the restored selection so saved settings survive a reload.
+    create_effect(move |prev: Option<String>| {
+        let name = selected_prompt_name.get();
+        if prev.is_some_and(|p| p != name) {
+            if let Some(persona) = system_prompts
+                .get_untracked()
+                .iter()
+                .find(|p| p.name == name)
+            {
+                if let Some(t) = persona.options.temperature {
+                    temperature.set(t);
+                }
+                sampling.set(persona.options.sampling.clone());
+            }
+        }
+        name
+    });
+
     // When a new provider is selected, if current model isn't in its list, pick first
     create_effect(move |_| {
         let p = selected_provider.get();
@@ -220,16 +462,96 @@ where
         scroll_to_bottom();
     });
 
+    // Saves are queued and written one at a time, latest snapshot only, so a
+    // burst of streaming updates can't land out of order.
+    let pending_save = store_value(None::<StoredConversation>);
+    let saving = store_value(false);
+    let quota_warned = store_value(false);
+    let last_quota_check = store_value(0.0_f64);
+    let flush_saves = move || {
+        let Some(storage) = storage.get_untracked() else {
+            return;
+        };
+        if saving.get_value() {
+            return;
+        }
+        saving.set_value(true);
+        spawn_local(async move {
+            let mut saved = false;
+            while let Some(stored) = pending_save.try_update_value(Option::take).flatten() {
+                // Loading a conversation also lands here; don't bump modified_at
+                // (and win sync conflicts) when nothing actually changed.
+                let unchanged = storage
+                    .load_conversation(&stored.id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .is_some_and(|prev| stored.same_content(&prev));
+                if unchanged {
+                    continue;
+                }
+                match storage.save_conversation(&stored).await {
+                    Ok(()) => {
+                        sync::mark_dirty(&stored.id);
+                        saved = true;
+                    }
+                    Err(e) if is_quota_error(&e) => storage_notice.set(Some(
+                        "Couldn't save this conversation: browser storage is full. \
+                         Delete some old conversations to make room."
+                            .into(),
+                    )),
+                    Err(e) => web_sys::console::error_2(&"save conversation:".into(), &e),
+                }
+            }
+            saving.set_value(false);
+            if !saved {
+                return;
+            }
+            refresh_list();
+
+            // Estimates are coarse and lag behind writes; once a minute is plenty.
+            let now = js_sys::Date::now();
+            if now - last_quota_check.get_value() < 60_000.0 {
+                return;
+            }
+            last_quota_check.set_value(now);
+            let keep = conversation_id.get_untracked();
+            match check_quota(&storage, &keep, quota_warned.get_value()).await {
+                QuotaCheck::Fine => {}
+                QuotaCheck::Warn(usage) => {
+                    if !quota_warned.get_value() {
+                        quota_warned.set_value(true);
+                        storage_notice.set(Some(format!(
+                            "Browser storage is {:.0}% full. Past {:.0}%, the oldest \
+                             conversations already saved to the server will be removed \
+                             from this browser.",
+                            usage * 100.0,
+                            EVICT_USAGE * 100.0
+                        )));
+                    }
+                }
+                QuotaCheck::Evicted(title) => {
+                    storage_notice.set(Some(format!(
+                        "Browser storage is nearly full, so \"{}\" was removed from this \
+                         browser. It is still saved on the server and is fetched again \
+                         when you open it.",
+                        title
+                    )));
+                    refresh_list();
+                }
+            }
+        });
+    };
+
     // Save conversation when notebook changes
     create_effect({
         let context_manager = context_manager.clone();
-        let simple_storage = simple_storage.clone();
         move |_| {
             let nb = notebook.get();
             let _ = context_manager.get_total_tokens();
             let title = nb
-                .cells
-                .iter()
+                .active_path()
+                .into_iter()
                 .find_map(|c| match &c.content {
                     CellContent::UserInput { text } => Some(text.clone()),
                     _ => None,
@@ -251,23 +573,174 @@ where
                 title,
                 model: selected_model.get(),
                 provider: selected_provider.get(),
+                persona: selected_prompt_name.get(),
+                variables: prompt_variables.get(),
+                job: job_link.get_value(),
             };
-            let stored = StoredConversation {
-                id: conversation_id.get(),
+            let id = conversation_id.get();
+            if !loaded_id.with(|l| l.as_ref() == Some(&id)) {
+                return;
+            }
+            pending_save.set_value(Some(StoredConversation {
+                id,
                 notebook: nb,
                 context_state: context_manager.to_state(),
                 metadata,
+            }));
+            flush_saves();
+        }
+    });
+
+    // Stream a reply to `message`, the last user cell on the active branch.
+    let start_reply = {
+        let context_manager = context_manager.clone();
+        move |message: String, options: ReplyOptions| {
+            clear_history();
+            // A replay needs every cell it was sent; check before adding
+            // the new version.
+            let replayed = match &options.replay {
+                Some(recorded) => match notebook.with_untracked(|nb| recorded.messages(nb)) {
+                    Ok(messages) => Some(messages),
+                    Err(_) => {
+                        set_notebook.update(|nb| {
+                            nb.add_cell(CellContent::Error {
+                                message: "Can't replay this reply".into(),
+                                details: Some(
+                                    "A message it was sent has since been deleted.".into(),
+                                ),
+                            });
+                        });
+                        return;
+                    }
+                },
+                None => None,
+            };
+            let mut provenance = match options.replay {
+                Some(recorded) => Provenance {
+                    model_digest: None,
+                    replay_of: options.version_of,
+                    ..recorded
+                },
+                None => {
+                    let prompt_name = selected_prompt_name.get_untracked();
+                    let prompts_snapshot = system_prompts.get_untracked();
+                    let persona = prompts_snapshot.iter().find(|p| p.name == prompt_name);
+                    let system_prompt = persona.map(|p| p.prompt.clone());
+                    Provenance {
+                        provider: selected_provider.get_untracked(),
+                        model: options
+                            .model
+                            .unwrap_or_else(|| selected_model.get_untracked()),
+                        model_digest: None,
+                        temperature: options
+                            .temperature
+                            .unwrap_or_else(|| temperature.get_untracked()),
+                        sampling: sampling.get_untracked(),
+                        persona: persona.map(|p| p.name.clone()),
+                        prompt_hash: system_prompt.as_deref().map(prompt_hash),
+                        system_prompt,
+                        variables: persona
+                            .map(|p| p.resolve_variables(&prompt_variables.get_untracked()))
+                            .unwrap_or_default(),
+                        rendered_system_prompt: None,
+                        context: Vec::new(),
+                        replay_of: None,
+                    }
+                }
             };
-            if simple_storage.save_conversation(&stored).is_ok() {
-                if let Ok(list) = simple_storage.list_conversations(50) {
-                    set_conversations.set(list);
+            // Compared models each answer as a version of the same reply;
+            // the first one stays on the active branch.
+            let mut targets = vec![(provenance.provider.clone(), provenance.model.clone())];
+            for target in options.compare {
+                if !targets.contains(&target) {
+                    targets.push(target);
                 }
             }
+            let responses = {
+                let mut ids = Vec::new();
+                set_notebook.update(|nb| {
+                    for (provider, model) in &targets {
+                        let content = CellContent::TextResponse {
+                            text: String::new(),
+                            streaming: true,
+                        };
+                        let cell = match ids.first().copied().or(options.version_of) {
+                            Some(previous) => nb.fork(previous, content),
+                            None => nb.add_cell(content),
+                        };
+                        let group = ids.first().copied().unwrap_or(cell);
+                        if let Some(cell) = nb.get_cell_mut(cell) {
+                            cell.metadata.provider = Some(provider.clone());
+                            cell.metadata.model = Some(model.clone());
+                            if targets.len() > 1 {
+                                cell.metadata.compare_group = Some(group);
+                            }
+                        }
+                        ids.push(cell);
+                    }
+                    nb.switch_to(ids[0]);
+                });
+                ids
+            };
+            if options.version_of.is_some() {
+                // The old version is off the active branch now.
+                notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+            }
+
+            let messages = match replayed {
+                Some(messages) => messages,
+                None => {
+                    let messages = context_manager.get_context_for_request();
+                    provenance.context = notebook.with_untracked(|nb| context_items(nb, &messages));
+                    messages
+                }
+            };
+            let mut jobs = Vec::new();
+            set_notebook.update(|nb| {
+                for (id, (provider, model)) in responses.iter().zip(targets) {
+                    let provenance = Provenance {
+                        provider,
+                        model,
+                        ..provenance.clone()
+                    };
+                    jobs.push((*id, chat_request(&provenance, messages.clone())));
+                    if let Some(cell) = nb.get_cell_mut(*id) {
+                        cell.metadata.provenance = Some(provenance);
+                    }
+                }
+            });
+
+            set_is_streaming.set(true);
+            let context_manager = context_manager.clone();
+            let compared = jobs.len() > 1;
+            spawn_local(async move {
+                let replies = futures::future::join_all(jobs.into_iter().map(|(id, req)| {
+                    stream_response(
+                        req,
+                        set_notebook,
+                        id,
+                        set_auth_error_triggered,
+                        message.clone(),
+                    )
+                }))
+                .await;
+                if compared {
+                    // Only the reply on the active branch joins the context.
+                    notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+                } else if let Some(Some(text)) = replies.into_iter().next() {
+                    context_manager.add_message(ChatMessage {
+                        role: "assistant".into(),
+                        content: text,
+                    });
+                }
+                set_is_streaming.set(false);
+            });
         }
-    });
+    };
 
     create_effect({
         let context_manager = context_manager.clone();
+        let start_reply = start_reply.clone();
         move |_| {
             if !should_submit.get() {
                 return;
@@ -287,53 +760,234 @@ where
                 content: message.clone(),
             });
             input_value.set(String::new());
+            start_reply(
+                message,
+                ReplyOptions {
+                    compare: compare_targets.get_untracked(),
+                    ..Default::default()
+                },
+            );
+        }
+    });
 
-            let response_id = {
-                let mut id = None;
-                set_notebook.update(|nb| {
-                    id = Some(nb.add_cell(CellContent::TextResponse {
-                        text: String::new(),
-                        streaming: true,
-                    }));
-                });
-                id.unwrap()
-            };
+    // Editing an earlier message resends it as a new branch beside the
+    // original; the model only sees the branch being continued.
+    let on_edit = Callback::new({
+        let context_manager = context_manager.clone();
+        let start_reply = start_reply.clone();
+        move |(cell, text): (CellId, String)| {
+            if text.trim().is_empty() || is_streaming.get_untracked() {
+                return;
+            }
+            set_notebook.update(|nb| {
+                nb.fork(cell, CellContent::UserInput { text: text.clone() });
+            });
+            notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+            start_reply(
+                text,
+                ReplyOptions {
+                    compare: compare_targets.get_untracked(),
+                    ..Default::default()
+                },
+            );
+        }
+    });
+    let on_regenerate = Callback::new({
+        let start_reply = start_reply.clone();
+        move |req: Regenerate| {
+            if is_streaming.get_untracked() {
+                return;
+            }
+            start_reply(
+                // Nothing to restore into the composer if the session expired.
+                String::new(),
+                ReplyOptions {
+                    version_of: Some(req.cell),
+                    model: req.model,
+                    temperature: req.temperature,
+                    ..Default::default()
+                },
+            );
+        }
+    });
+    // Send a reply's recorded request again, as a new version beside it.
+    let on_replay = Callback::new(move |cell: CellId| {
+        if is_streaming.get_untracked() {
+            return;
+        }
+        let Some(recorded) = notebook.with_untracked(|nb| {
+            nb.get_cell(cell)
+                .and_then(|c| c.metadata.provenance.clone())
+        }) else {
+            return;
+        };
+        start_reply(
+            String::new(),
+            ReplyOptions {
+                version_of: Some(cell),
+                replay: Some(recorded),
+                ..Default::default()
+            },
+        );
+    });
+    // Ratings are kept with the conversation, and posted to the server's
+    // feedback log until it answers that it doesn't keep one.
+    let feedback_log = store_value(true);
+    let on_feedback = Callback::new(move |(cell, feedback): (CellId, Option<Feedback>)| {
+        set_notebook.update(|nb| nb.set_feedback(cell, feedback.clone()));
+        if !feedback_log.get_value() {
+            return;
+        }
+        let persona = selected_prompt_name.get_untracked();
+        let fallback_prompt = system_prompts.with_untracked(|all| {
+            all.iter()
+                .find(|p| p.name == persona)
+                .map(|p| p.prompt.clone())
+        });
+        let Some(record) = notebook.with_untracked(|nb| {
+            let reply = nb.get_cell(cell)?;
+            let provenance = reply.metadata.provenance.as_ref();
+            Some(FeedbackRecord {
+                conversation_id: conversation_id.get_untracked(),
+                cell_id: cell.0,
+                rating: feedback.as_ref().and_then(|f| f.rating),
+                comment: feedback.map(|f| f.comment).unwrap_or_default(),
+                provider: reply.metadata.provider.clone(),
+                model: reply.metadata.model.clone(),
+                model_digest: provenance.and_then(|p| p.model_digest.clone()),
+                persona: provenance
+                    .map(|p| p.persona.clone())
+                    .unwrap_or_else(|| Some(persona.clone())),
+                system_prompt: provenance
+                    .map(|p| export::sent_system_prompt(p, reply.timestamp))
+                    .unwrap_or(fallback_prompt),
+                messages: nb.sent_messages(cell),
+                reply: reply.chat_message()?.content,
+            })
+        }) else {
+            return;
+        };
+        spawn_local(async move {
+            match ApiClient::new().post_feedback(&record).await {
+                Ok(()) => {}
+                Err(ApiError::Status(403)) => feedback_log.set_value(false),
+                Err(e) => web_sys::console::warn_1(&format!("feedback: {}", e).into()),
+            }
+        });
+    });
+    let regenerate_options = RegenerateOptions {
+        models: Signal::derive(move || {
+            let provider = selected_provider.get();
+            providers.with(|all| {
+                all.iter()
+                    .find(|p| p.name == provider)
+                    .map(|p| p.models.clone())
+                    .unwrap_or_default()
+            })
+        }),
+        model: selected_model.into(),
+        temperature: temperature.into(),
+        on_regenerate,
+    };
+    let on_switch_branch = Callback::new({
+        let context_manager = context_manager.clone();
+        move |cell: CellId| {
+            if is_streaming.get_untracked() {
+                return;
+            }
+            set_notebook.update(|nb| nb.switch_to(cell));
+            notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+        }
+    });
 
-            set_is_streaming.set(true);
-            let provider = selected_provider.get_untracked();
-            let model = selected_model.get_untracked();
-            let prompt_name = selected_prompt_name.get_untracked();
-            let custom = custom_prompt.get_untracked();
-            let prompts_snapshot = system_prompts.get_untracked();
-            let system_prompt = if prompt_name == "Custom" {
-                Some(custom)
-            } else {
-                prompts_snapshot
-                    .iter()
-                    .find(|p| p.name == prompt_name)
-                    .map(|p| p.prompt.clone())
-            };
-            let cm_clone = context_manager.clone();
-            spawn_local(async move {
-                stream_response(
-                    provider,
-                    model,
-                    system_prompt,
-                    temperature.get_untracked(),
-                    cm_clone.clone(),
-                    set_notebook,
-                    response_id,
-                    set_is_streaming,
-                    set_auth_error_triggered,
-                    message,
-                )
-                .await;
+    let on_cell_op = Callback::new({
+        let context_manager = context_manager.clone();
+        move |(cell, op): (CellId, CellOp)| {
+            if is_streaming.get_untracked() {
+                return;
+            }
+            let before = notebook.get_untracked();
+            set_notebook.update(|nb| match op {
+                CellOp::Delete => nb.delete_cell(cell),
+                CellOp::MoveUp => nb.move_up(cell),
+                CellOp::MoveDown => nb.move_down(cell),
+                CellOp::ToggleCollapsed => nb.toggle_collapsed(cell),
+                CellOp::TogglePinned => nb.toggle_pinned(cell),
+                CellOp::ToggleHidden => nb.toggle_hidden(cell),
+            });
+            if notebook.with_untracked(|nb| *nb == before) {
+                return;
+            }
+            let rebuild = notebook.with_untracked(|nb| context_changed(nb, &before));
+            undo_stack.update(|s| {
+                s.push(before);
+                if s.len() > MAX_UNDO {
+                    s.remove(0);
+                }
             });
+            redo_stack.set(Vec::new());
+            if rebuild {
+                notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+            }
+        }
+    });
+
+    // Undo pops `from` and pushes the current notebook onto `to`; redo the
+    // other way round.
+    let restore = {
+        let context_manager = context_manager.clone();
+        move |from: RwSignal<Vec<Notebook>>, to: RwSignal<Vec<Notebook>>| {
+            if is_streaming.get_untracked() {
+                return;
+            }
+            let Some(snapshot) = from.try_update(Vec::pop).flatten() else {
+                return;
+            };
+            let current = notebook.get_untracked();
+            let rebuild = context_changed(&current, &snapshot);
+            to.update(|s| s.push(current));
+            set_notebook.set(snapshot);
+            if rebuild {
+                notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+            }
+        }
+    };
+    let on_undo = Callback::new({
+        let restore = restore.clone();
+        move |_: ()| restore(undo_stack, redo_stack)
+    });
+    let on_redo = Callback::new(move |_: ()| restore(redo_stack, undo_stack));
+    let _undo_keys = window_event_listener(ev::keydown, move |e| {
+        if !(e.ctrl_key() || e.meta_key()) {
+            return;
+        }
+        // Text fields keep their own undo.
+        let in_field = e
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+            .is_some_and(|el| matches!(el.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"));
+        if in_field {
+            return;
+        }
+        match e.key().to_lowercase().as_str() {
+            "z" if e.shift_key() => {
+                e.prevent_default();
+                on_redo.call(());
+            }
+            "z" => {
+                e.prevent_default();
+                on_undo.call(());
+            }
+            "y" => {
+                e.prevent_default();
+                on_redo.call(());
+            }
+            _ => {}
         }
     });
 
     let has_messages = create_memo(move |_| {
-        notebook.get().cells.iter().any(|c| {
+        notebook.get().active_path().into_iter().any(|c| {
             matches!(
                 &c.content,
                 CellContent::UserInput { .. } | CellContent::TextResponse { .. }
@@ -344,64 +998,186 @@ where
     let provider_online = Signal::derive(move || !providers.get().is_empty());
     let user_signal = user_name;
 
-    let simple_storage_new = simple_storage.clone();
     let cm_for_new = context_manager.clone();
     let on_new_chat = Callback::new(move |_| {
         let new_id = Uuid::new_v4().to_string();
         write_local("current_conversation_id", &new_id);
+        loaded_id.set(None);
         set_conversation_id.set(new_id);
-        set_notebook.update(|nb| {
-            nb.cells.clear();
-            nb.cursor_position = CellId(0);
-        });
+        set_notebook.update(Notebook::clear);
         cm_for_new.clear_context();
+        cm_for_new.set_strategy(ContextStrategy::default());
         set_created_at.set(Utc::now());
-        if let Ok(list) = simple_storage_new.list_conversations(50) {
-            set_conversations.set(list);
-        }
+        prompt_variables.set(HashMap::new());
+        refresh_list();
     });
 
-    let simple_storage_sel = simple_storage.clone();
-    let cm_for_sel = context_manager.clone();
+    // The load effect above restores the stored copy.
     let on_select = Callback::new(move |id: String| {
         if id == conversation_id.get_untracked() {
+            // Retry a server-only conversation whose fetch failed.
+            if loaded_id.with_untracked(Option::is_none) {
+                set_reload_tick.update(|t| *t += 1);
+            }
             return;
         }
         write_local("current_conversation_id", &id);
-        set_conversation_id.set(id.clone());
-        if let Ok(Some(stored)) = simple_storage_sel.load_conversation(&id) {
-            cm_for_sel.restore_state(stored.context_state);
-            set_notebook.update(|nb| *nb = stored.notebook);
-            set_created_at.set(stored.metadata.created_at);
-            if !stored.metadata.provider.is_empty() {
-                selected_provider.set(stored.metadata.provider);
-            }
-            if !stored.metadata.model.is_empty() {
-                selected_model.set(stored.metadata.model);
+        loaded_id.set(None);
+        set_conversation_id.set(id);
+    });
+
+    // Full-text hits for the sidebar, refreshed as the query or the stored
+    // conversations change.
+    let message_hits = create_rw_signal(Vec::<SearchHit>::new());
+    let semantic_mode = create_rw_signal(false);
+    create_effect(move |_| {
+        let query = search_query.get();
+        conversations.with(|_| ());
+        let Some(storage) = storage.get() else {
+            return;
+        };
+        if semantic_mode.get() || search_index::tokenize(&query).is_empty() {
+            message_hits.set(Vec::new());
+            return;
+        }
+        spawn_local(async move {
+            search_index::ensure_built(&storage).await;
+            // The query may have moved on while the index was being built.
+            let query = search_query.get_untracked();
+            message_hits.set(search_index::search(&query, MAX_MESSAGE_HITS));
+        });
+    });
+
+    // A search hit to scroll to once its conversation is on screen.
+    let jump_to = create_rw_signal(None::<(String, CellId, Vec<String>)>);
+    let on_open_hit = Callback::new(move |hit: SearchHit| {
+        let terms = search_index::tokenize(&search_query.get_untracked());
+        jump_to.set(Some((hit.conversation_id.clone(), hit.cell, terms)));
+        on_select.call(hit.conversation_id);
+    });
+    // Search by meaning runs on Enter rather than per keystroke, since each
+    // query is an embedding request.
+    let semantic_hits = create_rw_signal(Vec::<SemanticHit>::new());
+    let semantic_status = create_rw_signal(None::<String>);
+    let on_semantic_search = Callback::new(move |_: ()| {
+        let query = search_query.get_untracked().trim().to_string();
+        let Some(storage) = storage.get_untracked() else {
+            return;
+        };
+        if query.is_empty() {
+            semantic_hits.set(Vec::new());
+            semantic_status.set(None);
+            return;
+        }
+        semantic_status.set(Some("Searching…".to_string()));
+        spawn_local(async move {
+            match semantic_index::search(&storage, &query).await {
+                Ok(hits) => {
+                    semantic_status.set(hits.is_empty().then(|| "No close matches".to_string()));
+                    semantic_hits.set(hits);
+                }
+                Err(e) => {
+                    semantic_status.set(Some(e));
+                    semantic_hits.set(Vec::new());
+                }
             }
+        });
+    });
+    let on_open_semantic = Callback::new(move |(id, cell): (String, CellId)| {
+        jump_to.set(Some((id.clone(), cell, Vec::new())));
+        on_select.call(id);
+    });
+    create_effect(move |_| {
+        let Some((id, cell, terms)) = jump_to.get() else {
+            return;
+        };
+        if loaded_id.get().as_deref() != Some(id.as_str()) {
+            return;
         }
+        jump_to.set(None);
+        // After the thread has rendered the loaded cells and auto-scrolled.
+        gloo_timers::callback::Timeout::new(100, move || reveal_cell(cell, &terms)).forget();
     });
 
-    let simple_storage_del = simple_storage.clone();
-    let cm_for_del = context_manager.clone();
     let on_delete = Callback::new(move |id: String| {
-        let _ = simple_storage_del.delete_conversation(&id);
-        if let Ok(list) = simple_storage_del.list_conversations(50) {
-            set_conversations.set(list);
+        sync::mark_deleted(&id);
+        forget_evicted(&id);
+        jobs::note_deleted(&id);
+        // A queued save must not bring it back.
+        pending_save.update_value(|p| {
+            if p.as_ref().is_some_and(|s| s.id == id) {
+                *p = None;
+            }
+        });
+        if let Some(storage) = storage.get_untracked() {
+            let id = id.clone();
+            spawn_local(async move {
+                let _ = storage.delete_conversation(&id).await;
+                refresh_list();
+            });
         }
         if id == conversation_id.get_untracked() {
-            let new_id = Uuid::new_v4().to_string();
-            write_local("current_conversation_id", &new_id);
-            set_conversation_id.set(new_id);
-            set_notebook.update(|nb| {
-                nb.cells.clear();
-                nb.cursor_position = CellId(0);
-            });
-            cm_for_del.clear_context();
-            set_created_at.set(Utc::now());
+            on_new_chat.call(());
         }
     });
 
+    // Sync with the server on mount and then periodically. Offline or failed
+    // rounds are retried on the next tick; local storage keeps working.
+    let (syncing, set_syncing) = create_signal(false);
+    // Off after a 403: the server runs no batch jobs.
+    let job_import = store_value(true);
+    let run_sync = move || {
+        let Some(storage) = storage.get_untracked() else {
+            return;
+        };
+        if syncing.get_untracked() {
+            return;
+        }
+        set_syncing.set(true);
+        spawn_local(async move {
+            match sync::sync(&storage).await {
+                Ok(mut outcome) => {
+                    // Before importing, or a job conversation deleted on
+                    // another device would be recreated here.
+                    outcome
+                        .tombstones
+                        .iter()
+                        .for_each(|id| jobs::note_deleted(id));
+                    if job_import.get_value() {
+                        match jobs::import_results(&storage).await {
+                            Ok(ids) => outcome.updated.extend(ids),
+                            Err(ApiError::Status(403)) => job_import.set_value(false),
+                            Err(e) => {
+                                web_sys::console::warn_1(&format!("jobs: {}", e).into());
+                            }
+                        }
+                    }
+                    if !outcome.updated.is_empty() || !outcome.deleted.is_empty() {
+                        refresh_list();
+                    }
+                    let current = conversation_id.get_untracked();
+                    if outcome.deleted.contains(&current) {
+                        on_new_chat.call(());
+                    } else if outcome.updated.contains(&current) && !is_streaming.get_untracked() {
+                        set_reload_tick.update(|t| *t += 1);
+                    }
+                }
+                Err(ApiError::Unauthorized) => set_auth_error_triggered.set(true),
+                Err(e) => {
+                    web_sys::console::warn_1(&format!("sync: {}", e).into());
+                }
+            }
+            set_syncing.set(false);
+        });
+    };
+    spawn_local(async move {
+        storage.set(Some(AppStorage::open().await));
+        run_sync();
+    });
+    if let Ok(handle) = set_interval_with_handle(run_sync, std::time::Duration::from_secs(30)) {
+        on_cleanup(move || handle.clear());
+    }
+
     let logout_cb = on_logout.clone();
     let on_logout_cb = Callback::new(move |_| logout_cb());
 
@@ -420,7 +1196,123 @@ where
             .unwrap_or_else(|| "U".into())
     });
 
+    let on_run_code = Callback::new(move |snippet: RunSnippet| {
+        clear_history();
+        let mut output_id = None;
+        set_notebook.update(|nb| {
+            output_id = Some(nb.add_cell(CellContent::CodeOutput {
+                source_cell: snippet.cell,
+                language: snippet.language.clone(),
+                result: None,
+            }));
+        });
+        let Some(output_id) = output_id else {
+            return;
+        };
+        spawn_local(async move {
+            let req = RunRequest {
+                language: snippet.language,
+                source: snippet.source,
+            };
+            let run = match ApiClient::new().run_code(&req).await {
+                Ok(resp) => CodeRun {
+                    stdout: resp.stdout,
+                    stderr: resp.stderr,
+                    exit_code: resp.exit_code,
+                    timed_out: resp.timed_out,
+                    truncated: resp.truncated,
+                },
+                Err(ApiError::Unauthorized) => {
+                    set_auth_error_triggered.set(true);
+                    return;
+                }
+                Err(e) => CodeRun {
+                    stdout: String::new(),
+                    stderr: e.to_string(),
+                    exit_code: None,
+                    timed_out: false,
+                    truncated: false,
+                },
+            };
+            set_notebook.update(|nb| nb.complete_code_output(output_id, run));
+        });
+    });
+    let run_languages = Signal::derive(move || sandbox.get().languages);
+
     let cm_for_composer = context_manager.clone();
+    let inspector_open = create_rw_signal(false);
+    let cm_for_inspector = context_manager.clone();
+    let inspector_prompt = Signal::derive(move || {
+        let name = selected_prompt_name.get();
+        system_prompts.with(|prompts| {
+            prompts
+                .iter()
+                .find(|p| p.name == name)
+                .map(|p| (p.name.clone(), p.prompt.clone()))
+        })
+    });
+    // The inspector's entries are context messages; act on the cell behind
+    // one when there is a cell, so the change shows in the thread and can
+    // be undone.
+    let context_cell = move |index: usize, context_manager: &ContextManager| {
+        let message = context_manager.message(index)?;
+        notebook.with_untracked(|nb| {
+            nb.active_path()
+                .into_iter()
+                .rev()
+                .find(|c| context_message(c).as_ref() == Some(&message))
+                .map(|c| c.id)
+        })
+    };
+    let on_inspector_remove = Callback::new({
+        let context_manager = context_manager.clone();
+        move |index: usize| match context_cell(index, &context_manager) {
+            Some(cell) => on_cell_op.call((cell, CellOp::ToggleHidden)),
+            None => context_manager.remove_message(index),
+        }
+    });
+    let on_inspector_pin = Callback::new({
+        let context_manager = context_manager.clone();
+        move |index: usize| match context_cell(index, &context_manager) {
+            Some(cell) => on_cell_op.call((cell, CellOp::TogglePinned)),
+            None => context_manager.toggle_pinned_message(index),
+        }
+    });
+    // Summaries only exist under summarize-oldest, so every switch starts
+    // over from the active branch.
+    let on_strategy = Callback::new({
+        let context_manager = context_manager.clone();
+        move |strategy: ContextStrategy| {
+            context_manager.set_strategy(strategy);
+            notebook.with_untracked(|nb| rebuild_context(&context_manager, nb));
+        }
+    });
+
+    let chat_title = Signal::derive(move || {
+        let id = conversation_id.get();
+        conversations
+            .get()
+            .iter()
+            .find(|c| c.id == id)
+            .map(|c| c.title.clone())
+            .unwrap_or_else(|| "New chat".into())
+    });
+    let share_snapshot = Callback::new(move |_: ()| {
+        // Only the branch on screen: edited-away messages and other
+        // versions stay private. Provenance carries the persona's prompt and
+        // feedback the owner's private ratings; readers get neither.
+        let mut notebook = notebook.with_untracked(Notebook::active_branch);
+        for cell in &mut notebook.cells {
+            cell.metadata.provenance = None;
+            cell.metadata.feedback = None;
+        }
+        ShareSnapshot {
+            persona: selected_prompt_name.get_untracked(),
+            model: selected_model.get_untracked(),
+            notebook,
+        }
+    });
+    let share_disabled = Signal::derive(move || is_streaming.get() || !has_messages.get());
 
     view! {
         <div class="app" style:grid-template-columns=move || format!("{}px 1fr", sidebar_width.get())>
@@ -428,9 +1320,17 @@ where
                 conversations=conversations.into()
                 current_id=conversation_id
                 search=search_query
+                message_hits=message_hits.into()
+                on_open_hit=on_open_hit
+                semantic=semantic_mode
+                semantic_hits=semantic_hits.into()
+                semantic_status=semantic_status.into()
+                on_semantic_search=on_semantic_search
+                on_open_semantic=on_open_semantic
                 theme=theme
                 provider_online=provider_online
                 user_name=user_signal
+                is_admin=is_admin
                 on_new=on_new_chat
                 on_select=on_select
                 on_delete=on_delete
@@ -440,15 +1340,7 @@ where
             <main class="main">
                 <div class="chat-header">
                     <div class="chat-title">
-                        <span class="chat-title-text">
-                            {move || {
-                                let id = conversation_id.get();
-                                conversations.get().iter()
-                                    .find(|c| c.id == id)
-                                    .map(|c| c.title.clone())
-                                    .unwrap_or_else(|| "New chat".into())
-                            }}
-                        </span>
+                        <span class="chat-title-text">{chat_title}</span>
                     </div>
                     {move || if providers_loaded.get() {
                         view! {
@@ -459,18 +1351,74 @@ where
                                     selected_model=selected_model
                                     disabled=is_streaming.into()
                                 />
+                                <ComparePicker
+                                    providers=providers.read_only()
+                                    selected_provider=selected_provider
+                                    selected_model=selected_model
+                                    targets=compare_targets
+                                    disabled=is_streaming.into()
+                                />
                                 <PersonaPicker
-                                    prompts=system_prompts.read_only()
+                                    prompts=system_prompts
                                     selected_name=selected_prompt_name
-                                    custom_prompt=custom_prompt
+                                    variables=prompt_variables
                                 />
                             </>
                         }.into_view()
                     } else {
                         view! { <span class="chat-title-meta">"Loading…"</span> }.into_view()
                     }}
+                    {move || {
+                        let can_undo = !undo_stack.with(Vec::is_empty);
+                        let can_redo = !redo_stack.with(Vec::is_empty);
+                        (can_undo || can_redo).then(|| view! {
+                            <button
+                                class="icon-btn"
+                                title="Undo cell edit (⌘Z)"
+                                disabled=move || !can_undo || is_streaming.get()
+                                on:click=move |_| on_undo.call(())
+                            >
+                                <IconUndo/>
+                            </button>
+                            <button
+                                class="icon-btn"
+                                title="Redo (⇧⌘Z)"
+                                disabled=move || !can_redo || is_streaming.get()
+                                on:click=move |_| on_redo.call(())
+                            >
+                                <IconRedo/>
+                            </button>
+                        })
+                    }}
+                    <button
+                        class="icon-btn"
+                        class:active=move || inspector_open.get()
+                        title="Inspect the context for the next request"
+                        on:click=move |_| inspector_open.update(|o| *o = !*o)
+                    >
+                        <IconLayers/>
+                    </button>
+                    <ShareMenu
+                        conversation_id=conversation_id
+                        title=chat_title
+                        snapshot=share_snapshot
+                        disabled=share_disabled
+                    />
+                    <ExportMenu
+                        storage=storage
+                        prompts=system_prompts
+                        conversation_id=conversation_id
+                        title=chat_title
+                        disabled=share_disabled
+                    />
                 </div>
 
+                {move || storage_notice.get().map(|msg| view! {
+                    <div class="storage-notice">
+                        <span>{msg}</span>
+                        <button on:click=move |_| storage_notice.set(None)>"Dismiss"</button>
+                    </div>
+                })}
                 <div class="thread-wrap" node_ref=thread_ref>
                     {move || if !has_messages.get() {
                         view! {
@@ -483,12 +1431,23 @@ where
                         view! {
                             <div class="thread">
                                 <For
-                                    each=move || notebook.get().cells
+                                    each=move || notebook.with(|nb| {
+                                        nb.active_path().into_iter().cloned().collect::<Vec<_>>()
+                                    })
                                     key=|c| c.id.0
                                     children=move |cell| {
                                         let ctx = CellContext {
                                             user_initial: user_initial.get_untracked(),
                                             persona_name: selected_prompt_name.get_untracked(),
+                                            run_languages,
+                                            on_run: Some(on_run_code),
+                                            on_edit: Some(on_edit),
+                                            on_switch: Some(on_switch_branch),
+                                            regenerate: Some(regenerate_options),
+                                            on_op: Some(on_cell_op),
+                                            on_replay: Some(on_replay),
+                                            on_feedback: Some(on_feedback),
+                                            busy: is_streaming.into(),
                                         };
                                         view! { <CellView cell=cell ctx=ctx notebook=notebook/> }
                                     }
@@ -497,31 +1456,152 @@ where
                         }.into_view()
                     }}
                 </div>
+                {move || inspector_open.get().then(|| view! {
+                    <ContextInspector
+                        context_manager=cm_for_inspector.clone()
+                        system_prompt=inspector_prompt
+                        on_remove=on_inspector_remove
+                        on_pin=on_inspector_pin
+                        busy=is_streaming.into()
+                        on_close=Callback::new(move |_| inspector_open.set(false))
+                    />
+                })}
                 <Composer
                     input_value=input_value
                     is_streaming=is_streaming
                     temperature=temperature
+                    sampling=sampling
                     context_manager=cm_for_composer
                     on_submit=on_submit
+                    on_strategy=on_strategy
                 />
             </main>
         </div>
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Where a reply goes and what it runs with; `None` uses the chat's
+/// current model and temperature.
+#[derive(Default)]
+struct ReplyOptions {
+    /// Add the reply as another version of this one instead of after the
+    /// active branch.
+    version_of: Option<CellId>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    /// Send this recorded request again instead of building one from the
+    /// chat's current settings and context.
+    replay: Option<Provenance>,
+    /// Other (provider, model) pairs to answer alongside, side by side.
+    compare: Vec<(String, String)>,
+}
+
+/// What a cell contributes to the model's context: user messages and
+/// finished replies, unless hidden.
+fn context_message(cell: &Cell) -> Option<ChatMessage> {
+    if cell.metadata.hidden {
+        return None;
+    }
+    cell.chat_message()
+}
+
+/// Record `messages` against the active branch: each one sent exactly as
+/// a cell on it wrote it refers to that cell, anything else is kept as
+/// text.
+fn context_items(nb: &Notebook, messages: &[ChatMessage]) -> Vec<ContextItem> {
+    let path = nb.active_path();
+    let mut used = vec![false; path.len()];
+    messages
+        .iter()
+        .map(|message| {
+            let found = path
+                .iter()
+                .enumerate()
+                .position(|(i, cell)| !used[i] && cell.chat_message().as_ref() == Some(message));
+            match found {
+                Some(i) => {
+                    used[i] = true;
+                    ContextItem::Cell(path[i].id)
+                }
+                None => ContextItem::Text(message.clone()),
+            }
+        })
+        .collect()
+}
+
+/// The request a provenance record describes, carrying `messages`.
+/// A replay resends the prompt exactly as rendered the first time, so
+/// `{{date}}` and the like keep their original values.
+fn chat_request(provenance: &Provenance, messages: Vec<ChatMessage>) -> ChatRequest {
+    let (system_prompt, rendered) = match &provenance.rendered_system_prompt {
+        Some(prompt) => (Some(prompt.clone()), true),
+        None => (provenance.system_prompt.clone(), false),
+    };
+    ChatRequest {
+        provider: provenance.provider.clone(),
+        messages,
+        model: Some(provenance.model.clone()),
+        system_prompt,
+        temperature: Some(provenance.temperature),
+        sampling: provenance.sampling.clone(),
+        variables: provenance.variables.clone(),
+        rendered,
+    }
+}
+
+/// Identifies a persona prompt in a provenance record.
+fn prompt_hash(prompt: &str) -> String {
+    format!("{:016x}", semantic_index::hash(prompt))
+}
+
+/// The model's view of the active branch.
+fn path_messages(nb: &Notebook) -> Vec<ChatMessage> {
+    nb.active_path()
+        .into_iter()
+        .filter_map(context_message)
+        .collect()
+}
+
+/// Messages of exchanges (a user message and the replies to it) on the
+/// active branch with any cell pinned.
+fn pinned_messages(nb: &Notebook) -> Vec<ChatMessage> {
+    let mut pinned = Vec::new();
+    let mut exchange = Vec::new();
+    let mut exchange_pinned = false;
+    for cell in nb.active_path() {
+        if matches!(cell.content, CellContent::UserInput { .. }) {
+            if exchange_pinned {
+                pinned.append(&mut exchange);
+            }
+            exchange.clear();
+            exchange_pinned = false;
+        }
+        exchange_pinned |= cell.metadata.pinned;
+        exchange.extend(context_message(cell));
+    }
+    if exchange_pinned {
+        pinned.append(&mut exchange);
+    }
+    pinned
+}
+
+fn context_changed(a: &Notebook, b: &Notebook) -> bool {
+    path_messages(a) != path_messages(b) || pinned_messages(a) != pinned_messages(b)
+}
+
+fn rebuild_context(context_manager: &ContextManager, nb: &Notebook) {
+    context_manager.rebuild(path_messages(nb), pinned_messages(nb));
+}
+
+/// Stream the reply to `req` into `response_id`, returning its text once
+/// complete. Failures replace the reply with an error.
 async fn stream_response(
-    provider: String,
-    model: String,
-    system_prompt: Option<String>,
-    temperature: f32,
-    context_manager: ContextManager,
+    req: ChatRequest,
     set_notebook: WriteSignal<Notebook>,
     response_id: CellId,
-    set_is_streaming: WriteSignal<bool>,
     set_auth_error: WriteSignal<bool>,
     pending_message: String,
-) {
+) -> Option<String> {
     use futures::FutureExt;
     use futures::StreamExt;
     use gloo_timers::future::TimeoutFuture;
@@ -530,27 +1610,16 @@ async fn stream_response(
     use web_sys::{Headers, Request, RequestInit, Response};
 
     let client = ApiClient::new();
-    let context_messages = context_manager.get_context_for_request();
-    let req = ChatRequest {
-        provider,
-        messages: context_messages,
-        model: Some(model),
-        system_prompt,
-        temperature: Some(temperature),
-        max_tokens: None,
-    };
 
     let push_error = move |msg: &str, details: Option<String>| {
         set_notebook.update(|nb| {
-            nb.cells.push(crate::notebook::Cell {
-                id: CellId(nb.cells.len()),
-                content: CellContent::Error {
+            if let Some(cell) = nb.get_cell_mut(response_id) {
+                cell.content = CellContent::Error {
                     message: msg.to_string(),
                     details,
-                },
-                timestamp: chrono::Utc::now(),
-                metadata: Default::default(),
-            });
+                };
+                cell.timestamp = chrono::Utc::now();
+            }
         });
     };
 
@@ -560,8 +1629,7 @@ async fn stream_response(
             Some(w) => w,
             None => {
                 push_error("No window context", None);
-                set_is_streaming.set(false);
-                return;
+                return None;
             }
         };
 
@@ -577,8 +1645,7 @@ async fn stream_response(
             Ok(r) => r,
             Err(_) => {
                 push_error("Failed to create request", None);
-                set_is_streaming.set(false);
-                return;
+                return None;
             }
         };
 
@@ -586,8 +1653,7 @@ async fn stream_response(
             Ok(v) => v.dyn_into().unwrap(),
             Err(_) => {
                 push_error("Network error", None);
-                set_is_streaming.set(false);
-                return;
+                return None;
             }
         };
 
@@ -598,24 +1664,21 @@ async fn stream_response(
                     "Authentication expired. Please log in again.",
                     Some("Your message has been saved and will be restored after login.".into()),
                 );
-                set_is_streaming.set(false);
                 spawn_local(async move {
                     gloo_timers::future::sleep(std::time::Duration::from_secs(2)).await;
                     set_auth_error.set(true);
                 });
-                return;
+                return None;
             }
             push_error(&format!("Server error: {}", resp.status()), None);
-            set_is_streaming.set(false);
-            return;
+            return None;
         }
 
         let body = match resp.body() {
             Some(b) => b,
             None => {
                 push_error("Empty response", None);
-                set_is_streaming.set(false);
-                return;
+                return None;
             }
         };
 
@@ -639,6 +1702,22 @@ async fn stream_response(
                         else {
                             continue;
                         };
+                        if event.lines().any(|l| l == "event: meta") {
+                            if let Ok(meta) =
+                                serde_json::from_str::<crate::api::ChatMeta>(&data_line[6..])
+                            {
+                                set_notebook.update(|nb| {
+                                    if let Some(provenance) = nb
+                                        .get_cell_mut(response_id)
+                                        .and_then(|c| c.metadata.provenance.as_mut())
+                                    {
+                                        provenance.model_digest = meta.digest;
+                                        provenance.rendered_system_prompt = meta.system_prompt;
+                                    }
+                                });
+                            }
+                            continue;
+                        }
                         let Ok(chunk) =
                             serde_json::from_str::<crate::api::ChatChunk>(&data_line[6..])
                         else {
@@ -652,42 +1731,29 @@ async fn stream_response(
                             }
                         });
                         if chunk.done {
-                            context_manager.add_message(ChatMessage {
-                                role: "assistant".into(),
-                                content: full.clone(),
-                            });
-                            set_is_streaming.set(false);
-                            return;
+                            return Some(full);
                         }
                     }
                 }
                 Err(_) => {
                     push_error("Stream read error", None);
-                    set_is_streaming.set(false);
-                    return;
+                    return None;
                 }
             }
         }
+        // Closed without a final chunk; keep what arrived.
+        set_notebook.update(|nb| nb.finalize_streaming_response(response_id));
+        None
     };
 
     futures::select! {
-        _ = request_future.fuse() => {}
+        reply = request_future.fuse() => reply,
         _ = timeout.fuse() => {
-            set_is_streaming.set(false);
-            set_notebook.update(|nb| {
-                if let Some(pos) = nb.cells.iter().position(|c| c.id == response_id) {
-                    nb.cells.remove(pos);
-                }
-                nb.cells.push(crate::notebook::Cell {
-                    id: CellId(nb.cells.len()),
-                    content: CellContent::Error {
-                        message: "Request timed out".into(),
-                        details: Some("The model didn't respond within 2 minutes.".into()),
-                    },
-                    timestamp: chrono::Utc::now(),
-                    metadata: Default::default(),
-                });
-            });
+            push_error(
+                "Request timed out",
+                Some("The model didn't respond within 2 minutes.".into()),
+            );
+            None
         }
     }
 }
@@ -0,0 +1,167 @@
+use crate::api::ProviderInfo;
+use crate::components::icons::*;
+use leptos::ev::MouseEvent;
+use leptos::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+/// Models to ask alongside the selected one. With any checked, each
+/// message goes to all of them and the answers stream side by side.
+#[component]
+pub fn ComparePicker(
+    providers: ReadSignal<Vec<ProviderInfo>>,
+    selected_provider: RwSignal<String>,
+    selected_model: RwSignal<String>,
+    /// Checked (provider, model) pairs, not including the selected model.
+    targets: RwSignal<Vec<(String, String)>>,
+    disabled: Signal<bool>,
+) -> impl IntoView {
+    let (open, set_open) = create_signal(false);
+
+    let toggle = move |e: MouseEvent| {
+        e.stop_propagation();
+        if disabled.get_untracked() {
+            return;
+        }
+        set_open.update(|o| *o = !*o);
+    };
+
+    create_effect(move |_| {
+        if !open.get() {
+            return;
+        }
+        let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+            if let Some(target) = e.target() {
+                if let Ok(el) = target.dyn_into::<web_sys::Element>() {
+                    if el
+                        .closest(".compare-popover-anchor")
+                        .ok()
+                        .flatten()
+                        .is_none()
+                    {
+                        set_open.set(false);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+            let _ =
+                doc.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    });
+
+    // Picking the model that's already selected would only ask it twice.
+    create_effect(move |_| {
+        let provider = selected_provider.get();
+        let model = selected_model.get();
+        if targets.with_untracked(|t| t.iter().any(|(p, m)| *p == provider && *m == model)) {
+            targets.update(|t| t.retain(|(p, m)| !(*p == provider && *m == model)));
+        }
+    });
+
+    let flip = move |provider: String, model: String| {
+        targets.update(
+            |t| match t.iter().position(|(p, m)| *p == provider && *m == model) {
+                Some(i) => {
+                    t.remove(i);
+                }
+                None => t.push((provider, model)),
+            },
+        );
+    };
+
+    view! {
+        <div class="popover-anchor compare-popover-anchor">
+            <button
+                class="pill"
+                class:active=move || targets.with(|t| !t.is_empty())
+                title="Send each message to several models and compare the answers"
+                disabled=move || disabled.get()
+                on:click=toggle
+            >
+                <IconColumns/>
+                <span class="model-text">
+                    {move || match targets.with(Vec::len) {
+                        0 => "Compare".to_string(),
+                        n => format!("vs {}", n),
+                    }}
+                </span>
+            </button>
+            {move || open.get().then(|| {
+                let current = (selected_provider.get(), selected_model.get());
+                let groups: Vec<_> = providers
+                    .get()
+                    .into_iter()
+                    .map(|p| {
+                        let models: Vec<String> = p
+                            .models
+                            .iter()
+                            .filter(|m| !(p.name == current.0 && **m == current.1))
+                            .cloned()
+                            .collect();
+                        (p.name, models)
+                    })
+                    .filter(|(_, m)| !m.is_empty())
+                    .collect();
+                view! {
+                    <div class="popover model-popover" on:click=|e| e.stop_propagation()>
+                        <div class="summary-note compare-note">
+                            {format!("Compare {} with:", current.1)}
+                        </div>
+                        <div class="popover-body">
+                            {groups.into_iter().map(|(provider_name, models)| {
+                                let pn = provider_name.clone();
+                                view! {
+                                    <div class="provider-group">
+                                        <div class="provider-label">
+                                            <span>{pn.clone()}</span>
+                                        </div>
+                                        {models.into_iter().map(|m| {
+                                            let pn_flip = pn.clone();
+                                            let m_flip = m.clone();
+                                            let pn_cmp = pn.clone();
+                                            let m_cmp = m.clone();
+                                            let checked = create_memo(move |_| {
+                                                targets.with(|t| {
+                                                    t.iter().any(|(p, model)| {
+                                                        *p == pn_cmp && *model == m_cmp
+                                                    })
+                                                })
+                                            });
+                                            view! {
+                                                <div
+                                                    class="model-row"
+                                                    class:selected=move || checked.get()
+                                                    on:click=move |_| {
+                                                        flip(pn_flip.clone(), m_flip.clone())
+                                                    }
+                                                >
+                                                    <div class="model-info">
+                                                        <div class="model-name">{m}</div>
+                                                    </div>
+                                                    {move || checked.get().then(|| view! {
+                                                        <span class="model-check"><IconCheck/></span>
+                                                    })}
+                                                </div>
+                                            }
+                                        }).collect_view()}
+                                    </div>
+                                }
+                            }).collect_view()}
+                        </div>
+                        {move || targets.with(|t| !t.is_empty()).then(|| view! {
+                            <button
+                                class="compare-clear"
+                                on:click=move |_| targets.set(Vec::new())
+                            >
+                                "Stop comparing"
+                            </button>
+                        })}
+                    </div>
+                }
+            })}
+        </div>
+    }
+}
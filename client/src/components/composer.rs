@@ -1,5 +1,8 @@
-use crate::components::context_manager::ContextManager;
+use crate::api::SamplingParams;
+use crate::components::context_manager::{ContextManager, ContextStrategy};
 use crate::components::icons::*;
+use crate::components::sampling_popover::SamplingPopover;
+use crate::components::summary_popover::SummaryPopover;
 use leptos::ev::KeyboardEvent;
 use leptos::*;
 
@@ -10,8 +13,10 @@ pub fn Composer(
     input_value: RwSignal<String>,
     is_streaming: ReadSignal<bool>,
     temperature: RwSignal<f32>,
+    sampling: RwSignal<SamplingParams>,
     context_manager: ContextManager,
     on_submit: Callback<()>,
+    on_strategy: Callback<ContextStrategy>,
 ) -> impl IntoView {
     let handle_keydown = move |e: KeyboardEvent| {
         if e.key() == "Enter" && !e.shift_key() {
@@ -22,6 +27,11 @@ pub fn Composer(
 
     let can_send = move || !is_streaming.get() && !input_value.get().trim().is_empty();
 
+    let cm_summaries = context_manager.clone();
+    let cm_strategy = context_manager.clone();
+    let cm_omitted = context_manager.clone();
+    let strategy = create_memo(move |_| cm_strategy.strategy());
+    let omitted = create_memo(move |_| cm_omitted.omitted_messages());
     let cm_tokens = context_manager.clone();
     let cm_pct = context_manager.clone();
     let pct = create_memo(move |_| cm_pct.get_usage_percentage());
@@ -69,6 +79,7 @@ pub fn Composer(
                         />
                         <span class="temp-value">{move || format!("{:.1}", temperature.get())}</span>
                     </div>
+                    <SamplingPopover sampling=sampling/>
                     <div class="toolbar-spacer"></div>
                     <button
                         class="send-btn"
@@ -90,8 +101,8 @@ pub fn Composer(
                 </div>
             </div>
             <div class="composer-footnote">
-                <div class="ctx-gauge" title="Context window usage">
-                    <div class="ctx-bar">
+                <div class="ctx-gauge">
+                    <div class="ctx-bar" title="Context window usage">
                         <div
                             class="ctx-fill"
                             class:warn=move || warn_cls.get()
@@ -100,6 +111,27 @@ pub fn Composer(
                         ></div>
                     </div>
                     <span class="ctx-text">{ctx_text}</span>
+                    {move || (omitted.get() > 0).then(|| view! {
+                        <span class="ctx-omitted" title="Left out of the next request">
+                            {format!("{} left out", omitted.get())}
+                        </span>
+                    })}
+                    <select
+                        class="ctx-strategy"
+                        title="What to do when the conversation outgrows the context window"
+                        on:change=move |ev| {
+                            if let Some(s) = ContextStrategy::from_key(&event_target_value(&ev)) {
+                                on_strategy.call(s);
+                            }
+                        }
+                    >
+                        {ContextStrategy::ALL.into_iter().map(|s| view! {
+                            <option value=s.key() prop:selected=move || strategy.get() == s>
+                                {s.label()}
+                            </option>
+                        }).collect_view()}
+                    </select>
+                    <SummaryPopover context_manager=cm_summaries/>
                 </div>
                 <div>
                     <span class="kbd">"↵"</span>" send · "
@@ -0,0 +1,178 @@
+use crate::components::context_manager::{ContextEntry, ContextManager, EntrySource};
+use crate::components::icons::*;
+use crate::storage::estimate_tokens;
+use leptos::*;
+
+/// What the next request will carry: the system prompt, then each summary
+/// and message with its token estimate. Entries can be edited, pinned or
+/// removed before sending.
+#[component]
+pub fn ContextInspector(
+    context_manager: ContextManager,
+    /// Persona name and prompt template.
+    system_prompt: Signal<Option<(String, String)>>,
+    /// Hide the message at this index in the active messages.
+    on_remove: Callback<usize>,
+    on_pin: Callback<usize>,
+    busy: Signal<bool>,
+    on_close: Callback<()>,
+) -> impl IntoView {
+    let editing = create_rw_signal(None::<EntrySource>);
+    let draft = create_rw_signal(String::new());
+
+    let cm_entries = context_manager.clone();
+    let entries = create_memo(move |_| cm_entries.inspect());
+    let cm_total = context_manager.clone();
+    let total = create_memo(move |_| cm_total.get_total_tokens());
+
+    let cm_save = context_manager.clone();
+    let save = move |source: EntrySource| {
+        let text = draft.get_untracked();
+        match source {
+            EntrySource::Summary(i) => cm_save.set_summary(i, text),
+            EntrySource::Message(i) | EntrySource::Omitted(i) => cm_save.set_message(i, text),
+            EntrySource::Note => {}
+        }
+        editing.set(None);
+    };
+    let cm_remove = context_manager.clone();
+    let remove = move |source: EntrySource| match source {
+        EntrySource::Summary(i) => cm_remove.remove_summary(i),
+        EntrySource::Message(i) | EntrySource::Omitted(i) => on_remove.call(i),
+        EntrySource::Note => {}
+    };
+
+    let entry_view = move |entry: ContextEntry| {
+        let source = entry.source;
+        let (label, sent) = match source {
+            EntrySource::Summary(i) => (format!("summary {}", i + 1), true),
+            EntrySource::Message(_) => (entry.message.role.clone(), true),
+            EntrySource::Note => ("note".to_string(), true),
+            EntrySource::Omitted(_) => (format!("{} · left out", entry.message.role), false),
+        };
+        let index = match source {
+            EntrySource::Message(i) | EntrySource::Omitted(i) => Some(i),
+            _ => None,
+        };
+        // Summaries are edited without the prefix they're sent with.
+        let raw = match source {
+            EntrySource::Summary(_) => entry
+                .message
+                .content
+                .trim_start_matches("Previous conversation summary: ")
+                .to_string(),
+            _ => entry.message.content.clone(),
+        };
+        let save = save.clone();
+        let remove = remove.clone();
+        let is_editing = move || editing.get() == Some(source);
+        view! {
+            <div class="inspector-entry" class:omitted=!sent class:pinned=entry.pinned>
+                <div class="inspector-entry-head">
+                    <span class="inspector-role">{label}</span>
+                    {entry.pinned.then(|| view! { <span class="inspector-pin"><IconPin/></span> })}
+                    <span class="inspector-tokens">{format!("~{} tok", entry.tokens)}</span>
+                    {(source != EntrySource::Note).then(|| view! {
+                        <span class="inspector-actions">
+                            <button
+                                class="code-copy"
+                                title="Edit what the model sees"
+                                disabled=move || busy.get()
+                                on:click={
+                                    let raw = raw.clone();
+                                    move |_| {
+                                        draft.set(raw.clone());
+                                        editing.set(Some(source));
+                                    }
+                                }
+                            >
+                                <IconEdit/>
+                            </button>
+                            {index.map(|i| view! {
+                                <button
+                                    class="code-copy"
+                                    class:active=entry.pinned
+                                    title=if entry.pinned { "Unpin" } else { "Pin" }
+                                    disabled=move || busy.get()
+                                    on:click=move |_| on_pin.call(i)
+                                >
+                                    <IconPin/>
+                                </button>
+                            })}
+                            <button
+                                class="code-copy cell-delete"
+                                title="Remove from the context"
+                                disabled=move || busy.get()
+                                on:click=move |_| remove(source)
+                            >
+                                <IconTrash/>
+                            </button>
+                        </span>
+                    })}
+                </div>
+                {move || if is_editing() {
+                    let save = save.clone();
+                    view! {
+                        <div class="msg-edit">
+                            <textarea
+                                class="msg-edit-input"
+                                rows="5"
+                                prop:value=move || draft.get()
+                                on:input=move |ev| draft.set(event_target_value(&ev))
+                            />
+                            <div class="msg-edit-actions">
+                                <button on:click=move |_| editing.set(None)>"Cancel"</button>
+                                <button class="msg-edit-send" on:click=move |_| save(source)>
+                                    "Save"
+                                </button>
+                            </div>
+                        </div>
+                    }
+                    .into_view()
+                } else {
+                    view! { <div class="inspector-text">{entry.message.content.clone()}</div> }
+                        .into_view()
+                }}
+            </div>
+        }
+    };
+
+    view! {
+        <aside class="inspector">
+            <div class="inspector-head">
+                <span class="inspector-title">"Next request"</span>
+                <span class="inspector-total">
+                    {move || format!("~{} tok", total.get())}
+                </span>
+                <button class="icon-btn" title="Close" on:click=move |_| on_close.call(())>
+                    "✕"
+                </button>
+            </div>
+            <div class="inspector-body">
+                {move || system_prompt.get().map(|(persona, prompt)| view! {
+                    <div class="inspector-entry">
+                        <div class="inspector-entry-head">
+                            <span class="inspector-role">{format!("system · {}", persona)}</span>
+                            <span class="inspector-tokens">
+                                {format!("~{} tok", estimate_tokens(&prompt))}
+                            </span>
+                        </div>
+                        <div class="inspector-text">{prompt}</div>
+                    </div>
+                })}
+                {move || {
+                    let entries = entries.get();
+                    if entries.is_empty() {
+                        view! { <div class="inspector-empty">"No messages yet."</div> }.into_view()
+                    } else {
+                        entries.into_iter().map(entry_view.clone()).collect_view()
+                    }
+                }}
+            </div>
+            <div class="inspector-foot">
+                "Edits change what the model sees, not the thread, and are reset when the \
+                 branch changes. Removing a message hides its cell."
+            </div>
+        </aside>
+    }
+}
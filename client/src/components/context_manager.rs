@@ -1,16 +1,327 @@
 use crate::api::ChatMessage;
-use crate::storage::{estimate_context_tokens, ContextState};
+use crate::storage::{estimate_context_tokens, estimate_message_tokens, ContextState};
 use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
 
 const MAX_CONTEXT_TOKENS: usize = 4096; // Adjust based on your models
 const AUTO_COMPRESS_THRESHOLD: f32 = 0.85; // Compress at 85% full (leaves room for response)
 
+/// How a conversation that outgrows the window is cut down to fit. Chosen
+/// per conversation and stored with its context state.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Replace the oldest turns with a summary.
+    #[default]
+    SummarizeOldest,
+    /// Send the newest messages that fit; pinned ones stay in place.
+    SlidingWindow,
+    /// Pinned exchanges go first, then the newest messages that fit.
+    PinnedFirst,
+    /// Keep the opening exchange and the newest messages, with a note where
+    /// the middle was left out.
+    DropMiddle,
+    /// Send the newest text that fits, cutting the oldest message it reaches
+    /// mid-way. Ignores pins.
+    Truncate,
+}
+
+impl ContextStrategy {
+    pub const ALL: [ContextStrategy; 5] = [
+        ContextStrategy::SummarizeOldest,
+        ContextStrategy::SlidingWindow,
+        ContextStrategy::PinnedFirst,
+        ContextStrategy::DropMiddle,
+        ContextStrategy::Truncate,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ContextStrategy::SummarizeOldest => "Summarize oldest",
+            ContextStrategy::SlidingWindow => "Sliding window",
+            ContextStrategy::PinnedFirst => "Pinned first",
+            ContextStrategy::DropMiddle => "Drop middle",
+            ContextStrategy::Truncate => "Truncate",
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            ContextStrategy::SummarizeOldest => "summarize_oldest",
+            ContextStrategy::SlidingWindow => "sliding_window",
+            ContextStrategy::PinnedFirst => "pinned_first",
+            ContextStrategy::DropMiddle => "drop_middle",
+            ContextStrategy::Truncate => "truncate",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.key() == key)
+    }
+
+    /// What to send out of `messages`, within `budget` tokens.
+    /// Summarizing happens up front in `ContextManager::compress_context`,
+    /// so that strategy sends everything that is left.
+    pub fn select(
+        self,
+        messages: &[ChatMessage],
+        pinned: &[ChatMessage],
+        budget: usize,
+    ) -> Vec<Pick> {
+        let all = || (0..messages.len()).map(Pick::Message).collect();
+        if estimate_context_tokens(messages) <= budget {
+            return all();
+        }
+        let is_pinned: Vec<bool> = messages.iter().map(|m| pinned.contains(m)).collect();
+        match self {
+            ContextStrategy::SummarizeOldest => all(),
+            ContextStrategy::SlidingWindow => {
+                let mut keep = is_pinned;
+                keep_newest(messages, &mut keep, budget);
+                kept(&keep).collect()
+            }
+            ContextStrategy::PinnedFirst => {
+                let mut keep = is_pinned.clone();
+                keep_newest(messages, &mut keep, budget);
+                let recent: Vec<bool> =
+                    keep.iter().zip(&is_pinned).map(|(k, p)| *k && !p).collect();
+                kept(&is_pinned).chain(kept(&recent)).collect()
+            }
+            ContextStrategy::DropMiddle => {
+                let mut keep = is_pinned;
+                // The opening exchange usually states the task.
+                for k in keep.iter_mut().take(2) {
+                    *k = true;
+                }
+                keep_newest(messages, &mut keep, budget);
+                let mut picks = Vec::new();
+                let mut omitted = 0;
+                for (i, keep) in keep.iter().enumerate() {
+                    if !keep {
+                        omitted += 1;
+                        continue;
+                    }
+                    if omitted > 0 {
+                        picks.push(Pick::Note(format!(
+                            "[{} earlier messages omitted]",
+                            omitted
+                        )));
+                        omitted = 0;
+                    }
+                    picks.push(Pick::Message(i));
+                }
+                picks
+            }
+            ContextStrategy::Truncate => {
+                let mut picks = Vec::new();
+                let mut left = budget;
+                for (i, message) in messages.iter().enumerate().rev() {
+                    let tokens = estimate_message_tokens(message);
+                    if tokens <= left {
+                        left -= tokens;
+                        picks.push(Pick::Message(i));
+                        continue;
+                    }
+                    // Keep the end of this one, as much as still fits.
+                    let chars = left.saturating_sub(10) * 4;
+                    if chars > 0 {
+                        let skip = message.content.chars().count().saturating_sub(chars);
+                        picks.push(Pick::Tail {
+                            index: i,
+                            text: format!(
+                                "[…]{}",
+                                message.content.chars().skip(skip).collect::<String>()
+                            ),
+                        });
+                    }
+                    break;
+                }
+                picks.reverse();
+                picks
+            }
+        }
+    }
+}
+
+/// One item a strategy sends, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pick {
+    /// `messages[index]` as it is.
+    Message(usize),
+    /// The end of `messages[index]`, cut to fit.
+    Tail { index: usize, text: String },
+    /// A system note standing in for left-out messages.
+    Note(String),
+}
+
+impl Pick {
+    /// The message in `messages` this stands for, if any.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            Pick::Message(index) | Pick::Tail { index, .. } => Some(*index),
+            Pick::Note(_) => None,
+        }
+    }
+
+    pub fn to_message(&self, messages: &[ChatMessage]) -> ChatMessage {
+        match self {
+            Pick::Message(index) => messages[*index].clone(),
+            Pick::Tail { index, text } => ChatMessage {
+                role: messages[*index].role.clone(),
+                content: text.clone(),
+            },
+            Pick::Note(text) => ChatMessage {
+                role: "system".into(),
+                content: text.clone(),
+            },
+        }
+    }
+}
+
+/// Mark the newest unmarked messages to keep while the kept total stays
+/// within `budget`, stopping at the first that doesn't fit.
+fn keep_newest(messages: &[ChatMessage], keep: &mut [bool], budget: usize) {
+    let mut used: usize = messages
+        .iter()
+        .zip(keep.iter())
+        .filter(|(_, k)| **k)
+        .map(|(m, _)| estimate_message_tokens(m))
+        .sum();
+    for (message, keep) in messages.iter().zip(keep.iter_mut()).rev() {
+        if *keep {
+            continue;
+        }
+        let tokens = estimate_message_tokens(message);
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        *keep = true;
+    }
+}
+
+/// `messages` less `span`, if `span` is what compressing them would take:
+/// their oldest unpinned messages, in order.
+fn without_span(
+    messages: &[ChatMessage],
+    span: &[ChatMessage],
+    pinned: &[ChatMessage],
+) -> Option<Vec<ChatMessage>> {
+    if span.is_empty() {
+        return None;
+    }
+    let mut rest = Vec::new();
+    let mut taken = 0;
+    for message in messages {
+        if taken < span.len() && !pinned.contains(message) {
+            if *message != span[taken] {
+                return None;
+            }
+            taken += 1;
+        } else {
+            rest.push(message.clone());
+        }
+    }
+    (taken == span.len()).then_some(rest)
+}
+
+/// A user message cut to about 150 characters, at the last sentence break
+/// when there is one. Cuts fall on character boundaries.
+fn clip_request(content: &str) -> String {
+    let Some((cut, _)) = content.char_indices().nth(150) else {
+        return content.to_string();
+    };
+    let head = &content[..cut];
+    if let Some(pos) = head.rfind(". ") {
+        format!("{}.", &head[..pos])
+    } else if let Some(pos) = head.rfind("? ") {
+        format!("{}?", &head[..pos])
+    } else {
+        let end = content.char_indices().nth(147).map_or(cut, |(i, _)| i);
+        format!("{}...", &content[..end])
+    }
+}
+
+fn kept(keep: &[bool]) -> impl Iterator<Item = Pick> + '_ {
+    keep.iter()
+        .enumerate()
+        .filter(|(_, k)| **k)
+        .map(|(i, _)| Pick::Message(i))
+}
+
+/// Where an inspector entry comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntrySource {
+    Summary(usize),
+    /// Sent; the index is into the active messages.
+    Message(usize),
+    /// A note the strategy added.
+    Note,
+    /// Left out by the strategy.
+    Omitted(usize),
+}
+
+/// One line of the context inspector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextEntry {
+    pub source: EntrySource,
+    pub message: ChatMessage,
+    pub tokens: usize,
+    pub pinned: bool,
+}
+
+impl ContextEntry {
+    fn new(source: EntrySource, message: ChatMessage, pinned: bool) -> Self {
+        Self {
+            tokens: estimate_message_tokens(&message),
+            source,
+            message,
+            pinned,
+        }
+    }
+}
+
+/// A span that was just compressed. Its summary starts out as the
+/// `placeholder` built here and is swapped for the model's summary once
+/// that arrives.
+#[derive(Clone)]
+pub struct SummaryJob {
+    pub index: usize,
+    pub placeholder: String,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Asks a model for a better summary of a compressed span.
+type Summarizer = Rc<dyn Fn(SummaryJob)>;
+
+/// An inspector edit: a message from the thread and what the model sees in
+/// its place, or `None` when it was left out.
+pub type Override = (ChatMessage, Option<ChatMessage>);
+
 #[derive(Clone)]
 pub struct ContextManager {
     messages: RwSignal<Vec<ChatMessage>>,
     compressed_summaries: RwSignal<Vec<String>>,
+    /// The messages each summary stands for, so a rebuild can keep the
+    /// summary while they are unchanged.
+    compressed_spans: RwSignal<Vec<Vec<ChatMessage>>>,
+    /// Inspector edits, applied again on every rebuild.
+    overrides: RwSignal<Vec<Override>>,
     total_tokens: RwSignal<usize>,
     compression_count: RwSignal<u32>,
+    /// Messages of pinned exchanges; compression leaves them in place.
+    pinned: RwSignal<Vec<ChatMessage>>,
+    summarizer: StoredValue<Option<Summarizer>>,
+    pending_summaries: RwSignal<usize>,
+    strategy: RwSignal<ContextStrategy>,
+    /// Tokens sent besides the messages: the system prompt and anything
+    /// attached to the request.
+    reserved_tokens: RwSignal<usize>,
+    /// Messages the strategy left out of the last estimate.
+    omitted: RwSignal<usize>,
+    /// Bumped when exact token counts arrive for text already counted.
+    token_epoch: RwSignal<u32>,
 }
 
 impl ContextManager {
@@ -18,24 +329,128 @@ impl ContextManager {
         Self {
             messages: create_rw_signal(Vec::new()),
             compressed_summaries: create_rw_signal(Vec::new()),
+            compressed_spans: create_rw_signal(Vec::new()),
+            overrides: create_rw_signal(Vec::new()),
             total_tokens: create_rw_signal(0),
             compression_count: create_rw_signal(0),
+            pinned: create_rw_signal(Vec::new()),
+            summarizer: store_value(None),
+            pending_summaries: create_rw_signal(0),
+            strategy: create_rw_signal(ContextStrategy::default()),
+            reserved_tokens: create_rw_signal(0),
+            omitted: create_rw_signal(0),
+            token_epoch: create_rw_signal(0),
         }
     }
 
     pub fn restore_state(&self, state: ContextState) {
         self.messages.set(state.active_messages);
         self.compressed_summaries.set(state.compressed_summaries);
+        self.compressed_spans.set(state.compressed_spans);
+        self.overrides.set(state.overrides);
         self.total_tokens.set(state.total_tokens);
         self.compression_count.set(state.compression_count);
+        self.strategy.set(state.strategy);
+        self.update_token_count();
+    }
+
+    pub fn strategy(&self) -> ContextStrategy {
+        self.strategy.get()
+    }
+
+    /// Callers rebuild afterwards: summaries only exist under
+    /// `SummarizeOldest`, and the other strategies need the full branch.
+    pub fn set_strategy(&self, strategy: ContextStrategy) {
+        self.strategy.set(strategy);
+    }
+
+    pub fn set_reserved_tokens(&self, tokens: usize) {
+        if self.reserved_tokens.get_untracked() != tokens {
+            self.reserved_tokens.set(tokens);
+            self.update_token_count();
+        }
+    }
+
+    /// Messages the strategy currently leaves out of the request.
+    pub fn omitted_messages(&self) -> usize {
+        self.omitted.get()
+    }
+
+    /// Without a summarizer (or when it fails) the heuristic summary stays.
+    pub fn set_summarizer(&self, summarizer: impl Fn(SummaryJob) + 'static) {
+        self.summarizer.set_value(Some(Rc::new(summarizer)));
+    }
+
+    /// Swap in the model's summary for `job`, unless the summary was edited
+    /// or a rebuild dropped it since. `None` keeps the placeholder.
+    pub fn resolve_summary(&self, job: &SummaryJob, summary: Option<String>) {
+        self.pending_summaries.update(|n| *n = n.saturating_sub(1));
+        let Some(summary) = summary else {
+            return;
+        };
+        let mut replaced = false;
+        self.compressed_summaries.update(|sums| {
+            if let Some(current) = sums.get_mut(job.index) {
+                if *current == job.placeholder {
+                    *current = summary;
+                    replaced = true;
+                }
+            }
+        });
+        if replaced {
+            self.update_token_count();
+        }
+    }
+
+    pub fn summaries(&self) -> Vec<String> {
+        self.compressed_summaries.get()
+    }
+
+    pub fn set_summary(&self, index: usize, summary: String) {
+        self.compressed_summaries.update(|sums| {
+            if let Some(current) = sums.get_mut(index) {
+                *current = summary;
+            }
+        });
+        self.update_token_count();
+    }
+
+    /// The messages the summary stood for stay out of the context too.
+    pub fn remove_summary(&self, index: usize) {
+        self.compressed_summaries.update(|sums| {
+            if index < sums.len() {
+                sums.remove(index);
+            }
+        });
+        if index < self.compressed_spans.with_untracked(Vec::len) {
+            let mut spans = self.compressed_spans.get_untracked();
+            for message in &spans.remove(index) {
+                self.record_override(message, None);
+            }
+            self.compressed_spans.set(spans);
+        }
+        self.update_token_count();
+    }
+
+    /// Model summaries still being written.
+    pub fn pending_summaries(&self) -> usize {
+        self.pending_summaries.get()
+    }
+
+    pub fn set_pinned(&self, pinned: Vec<ChatMessage>) {
+        self.pinned.set(pinned);
+        self.update_token_count();
     }
 
     pub fn to_state(&self) -> ContextState {
         ContextState {
             active_messages: self.messages.get(),
             compressed_summaries: self.compressed_summaries.get(),
+            compressed_spans: self.compressed_spans.get(),
+            overrides: self.overrides.get(),
             total_tokens: self.total_tokens.get(),
             compression_count: self.compression_count.get(),
+            strategy: self.strategy.get(),
         }
     }
 
@@ -65,25 +480,174 @@ impl ContextManager {
             });
         }
 
-        // Add active messages
-        context.extend(self.messages.get());
+        // Add active messages, as many as the strategy lets through
+        context.extend(self.selected_messages());
 
         context
     }
 
-    fn update_token_count(&self) {
-        let messages_tokens = estimate_context_tokens(&self.messages.get());
-        let summary_tokens: usize = self
-            .compressed_summaries
+    fn summary_tokens(&self) -> usize {
+        self.compressed_summaries
             .get()
             .iter()
             .map(|s| crate::storage::estimate_tokens(s))
-            .sum();
+            .sum()
+    }
+
+    /// Room for messages once the system prompt, attachments and summaries
+    /// are counted, leaving space for the reply.
+    fn message_budget(&self) -> usize {
+        ((MAX_CONTEXT_TOKENS as f32 * AUTO_COMPRESS_THRESHOLD) as usize)
+            .saturating_sub(self.reserved_tokens.get() + self.summary_tokens())
+    }
+
+    fn picks(&self) -> Vec<Pick> {
+        self.messages.with(|messages| {
+            self.strategy
+                .get()
+                .select(messages, &self.pinned.get(), self.message_budget())
+        })
+    }
+
+    fn selected_messages(&self) -> Vec<ChatMessage> {
+        let picks = self.picks();
+        self.messages
+            .with(|messages| picks.iter().map(|p| p.to_message(messages)).collect())
+    }
+
+    fn update_token_count(&self) {
+        let picks = self.picks();
+        let sent = picks.iter().filter(|p| p.index().is_some()).count();
+        self.omitted
+            .set(self.messages.with(Vec::len).saturating_sub(sent));
+        let selected = self.messages.with(|messages| {
+            picks
+                .iter()
+                .map(|p| p.to_message(messages))
+                .collect::<Vec<_>>()
+        });
+        self.total_tokens.set(
+            self.reserved_tokens.get() + self.summary_tokens() + estimate_context_tokens(&selected),
+        );
+    }
+
+    /// Everything the next request carries after the system prompt, in
+    /// order, followed by the messages the strategy leaves out.
+    pub fn inspect(&self) -> Vec<ContextEntry> {
+        self.token_epoch.track();
+        let pinned = self.pinned.get();
+        let mut entries: Vec<ContextEntry> = self
+            .compressed_summaries
+            .get()
+            .into_iter()
+            .enumerate()
+            .map(|(i, summary)| {
+                ContextEntry::new(
+                    EntrySource::Summary(i),
+                    ChatMessage {
+                        role: "system".into(),
+                        content: format!("Previous conversation summary: {}", summary),
+                    },
+                    false,
+                )
+            })
+            .collect();
+        let picks = self.picks();
+        self.messages.with(|messages| {
+            for pick in &picks {
+                let source = match pick.index() {
+                    Some(i) => EntrySource::Message(i),
+                    None => EntrySource::Note,
+                };
+                let is_pinned = pick.index().is_some_and(|i| pinned.contains(&messages[i]));
+                entries.push(ContextEntry::new(
+                    source,
+                    pick.to_message(messages),
+                    is_pinned,
+                ));
+            }
+            for (i, message) in messages.iter().enumerate() {
+                if !picks.iter().any(|p| p.index() == Some(i)) {
+                    entries.push(ContextEntry::new(
+                        EntrySource::Omitted(i),
+                        message.clone(),
+                        pinned.contains(message),
+                    ));
+                }
+            }
+        });
+        entries
+    }
+
+    pub fn message(&self, index: usize) -> Option<ChatMessage> {
+        self.messages.with(|messages| messages.get(index).cloned())
+    }
 
-        self.total_tokens.set(messages_tokens + summary_tokens);
+    /// Change what the model sees for one message; the thread keeps the
+    /// original, and rebuilds make the same change again.
+    pub fn set_message(&self, index: usize, content: String) {
+        let Some(old) = self.message(index) else {
+            return;
+        };
+        let new = ChatMessage {
+            role: old.role.clone(),
+            content,
+        };
+        self.record_override(&old, Some(new.clone()));
+        self.pinned.update(|pinned| {
+            for p in pinned.iter_mut().filter(|p| **p == old) {
+                *p = new.clone();
+            }
+        });
+        self.messages.update(|messages| messages[index] = new);
+        self.update_token_count();
+    }
+
+    pub fn remove_message(&self, index: usize) {
+        if let Some(message) = self.message(index) {
+            self.record_override(&message, None);
+        }
+        self.messages.update(|messages| {
+            if index < messages.len() {
+                messages.remove(index);
+            }
+        });
+        self.update_token_count();
+    }
+
+    /// Note that `current` becomes `replacement`. When `current` is itself an
+    /// edit, the thread message behind it is what gets replaced.
+    fn record_override(&self, current: &ChatMessage, replacement: Option<ChatMessage>) {
+        self.overrides.update(|overrides| {
+            match overrides
+                .iter_mut()
+                .find(|(_, edited)| edited.as_ref() == Some(current))
+            {
+                Some((_, edited)) => *edited = replacement,
+                None => overrides.push((current.clone(), replacement)),
+            }
+        });
+    }
+
+    /// Pin or unpin one message in the context only.
+    pub fn toggle_pinned_message(&self, index: usize) {
+        let Some(message) = self.message(index) else {
+            return;
+        };
+        self.pinned.update(|pinned| {
+            if let Some(at) = pinned.iter().position(|p| *p == message) {
+                pinned.remove(at);
+            } else {
+                pinned.push(message);
+            }
+        });
+        self.update_token_count();
     }
 
     fn should_auto_compress(&self) -> bool {
+        if self.strategy.get() != ContextStrategy::SummarizeOldest {
+            return false;
+        }
         let usage = self.total_tokens.get() as f32 / MAX_CONTEXT_TOKENS as f32;
         usage > AUTO_COMPRESS_THRESHOLD
     }
@@ -121,18 +685,21 @@ impl ContextManager {
             return false;
         }
 
-        // Split messages into compress and keep
-        let messages_to_compress: Vec<ChatMessage> = current_messages
-            .iter()
-            .take(compress_count)
-            .cloned()
-            .collect();
-
-        let messages_to_keep: Vec<ChatMessage> = current_messages
-            .iter()
-            .skip(compress_count)
-            .cloned()
-            .collect();
+        // Split messages into compress and keep; pinned ones are always kept
+        let pinned = self.pinned.get();
+        let mut messages_to_compress = Vec::new();
+        let mut messages_to_keep = Vec::new();
+        for (i, msg) in current_messages.iter().enumerate() {
+            if i >= compress_count || pinned.contains(msg) {
+                messages_to_keep.push(msg.clone());
+            } else {
+                messages_to_compress.push(msg.clone());
+            }
+        }
+        if messages_to_compress.len() < 4 {
+            web_sys::console::log_1(&"Too few unpinned messages to compress".into());
+            return false;
+        }
 
         // Create an intelligent summary
         let summary = self.create_summary(&messages_to_compress);
@@ -147,7 +714,7 @@ impl ContextManager {
             &format!(
                 "Compression: {} messages → summary + {} messages. Tokens: {} → {} (saved {})",
                 total_msgs,
-                keep_count,
+                messages_to_keep.len(),
                 original_tokens,
                 new_total,
                 original_tokens - new_total
@@ -162,14 +729,29 @@ impl ContextManager {
         }
 
         // Update state
-        self.compressed_summaries.update(|sums| sums.push(summary));
+        let index = self.compressed_summaries.with(Vec::len);
+        self.compressed_summaries
+            .update(|sums| sums.push(summary.clone()));
+        self.compressed_spans
+            .update(|spans| spans.push(messages_to_compress.clone()));
         self.messages.set(messages_to_keep);
         self.compression_count.update(|c| *c += 1);
         self.update_token_count();
 
+        if let Some(summarizer) = self.summarizer.get_value() {
+            self.pending_summaries.update(|n| *n += 1);
+            summarizer(SummaryJob {
+                index,
+                placeholder: summary,
+                messages: messages_to_compress,
+            });
+        }
+
         true
     }
 
+    /// Offline fallback: the opening of each user message and a canned
+    /// phrase for each reply.
     fn create_summary(&self, messages: &[ChatMessage]) -> String {
         let mut summary_parts = Vec::new();
 
@@ -201,19 +783,7 @@ impl ContextManager {
                 match msg.role.as_str() {
                     "user" => {
                         // Extract key intent from user message
-                        let content = &msg.content;
-                        let summary = if content.len() > 150 {
-                            // Try to find a natural break point
-                            if let Some(pos) = content[..150].rfind(". ") {
-                                format!("{}.", &content[..pos])
-                            } else if let Some(pos) = content[..150].rfind("? ") {
-                                format!("{}?", &content[..pos])
-                            } else {
-                                format!("{}...", &content[..147])
-                            }
-                        } else {
-                            content.clone()
-                        };
+                        let summary = clip_request(&msg.content);
 
                         if !topic_summary.is_empty() {
                             topic_summary.push_str(" → ");
@@ -278,11 +848,69 @@ impl ContextManager {
         final_summary
     }
 
+    /// Start over from `messages`, the active branch after an edit or a
+    /// branch switch, compressing again if they don't fit. `pinned` survive
+    /// the compression. Inspector edits are made again, and a summary
+    /// (edited or not) is kept for as long as the messages it stands for are
+    /// still the oldest unpinned ones; from the first that changed on, the
+    /// branch is summarized afresh.
+    pub fn rebuild(&self, messages: Vec<ChatMessage>, pinned: Vec<ChatMessage>) {
+        let summaries = self.compressed_summaries.get_untracked();
+        let spans = self.compressed_spans.get_untracked();
+        let overrides = self.overrides.get_untracked();
+        self.clear_context();
+
+        let edit = |message: ChatMessage| match overrides.iter().find(|(o, _)| *o == message) {
+            Some((_, edited)) => edited.clone(),
+            None => Some(message),
+        };
+        let mut messages: Vec<ChatMessage> = messages.into_iter().filter_map(edit).collect();
+        let pinned: Vec<ChatMessage> = pinned.into_iter().filter_map(edit).collect();
+        let (mut kept_summaries, mut kept_spans) = (Vec::new(), Vec::new());
+        if self.strategy.get_untracked() == ContextStrategy::SummarizeOldest {
+            for (summary, span) in summaries.into_iter().zip(spans) {
+                let Some(rest) = without_span(&messages, &span, &pinned) else {
+                    break;
+                };
+                messages = rest;
+                kept_summaries.push(summary);
+                kept_spans.push(span);
+            }
+        }
+        self.compression_count.set(kept_summaries.len() as u32);
+        self.compressed_summaries.set(kept_summaries);
+        self.compressed_spans.set(kept_spans);
+        self.overrides.set(overrides);
+        self.pinned.set(pinned);
+        self.messages.set(messages);
+        self.update_token_count();
+        while self.should_auto_compress() && self.compress_context() {}
+    }
+
+    /// Count again once exact token counts arrive, compressing if the real
+    /// numbers no longer fit.
+    pub fn refresh_tokens(&self) {
+        self.token_epoch.update(|e| *e = e.wrapping_add(1));
+        self.update_token_count();
+        while self.should_auto_compress() && self.compress_context() {}
+    }
+
+    /// Changes whenever token counts may have changed without the text
+    /// changing.
+    pub fn token_epoch(&self) -> u32 {
+        self.token_epoch.get()
+    }
+
     pub fn clear_context(&self) {
         self.messages.set(Vec::new());
         self.compressed_summaries.set(Vec::new());
+        self.compressed_spans.set(Vec::new());
+        self.overrides.set(Vec::new());
         self.total_tokens.set(0);
         self.compression_count.set(0);
+        self.pinned.set(Vec::new());
+        self.pending_summaries.set(0);
+        self.omitted.set(0);
     }
 }
 
@@ -389,3 +1017,49 @@ pub fn ContextDisplay(
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn requests_are_clipped_on_character_boundaries() {
+        assert_eq!(clip_request("short question"), "short question");
+        let long = "é".repeat(200);
+        assert_eq!(clip_request(&long), format!("{}...", "é".repeat(147)));
+        let sentences = format!("{}. {}", "ü".repeat(60), "日".repeat(120));
+        assert_eq!(clip_request(&sentences), format!("{}.", "ü".repeat(60)));
+        assert_eq!(clip_request(&"ab? ".repeat(50)).chars().last(), Some('?'));
+    }
+
+    #[test]
+    fn spans_match_the_oldest_unpinned_messages() {
+        let messages: Vec<ChatMessage> = ["a", "b", "c", "d"]
+            .into_iter()
+            .zip(["user", "assistant"].into_iter().cycle())
+            .map(|(content, role)| msg(role, content))
+            .collect();
+        let (a, b, c) = (&messages[0], &messages[1], &messages[2]);
+        let pinned_b = &messages[1..2];
+        assert_eq!(
+            without_span(&messages, &[a.clone(), c.clone()], pinned_b),
+            Some(vec![b.clone(), messages[3].clone()])
+        );
+        // An edit, a newly pinned message or a reordering changes the span.
+        assert_eq!(without_span(&messages, &[a.clone(), c.clone()], &[]), None);
+        assert_eq!(
+            without_span(&messages, &messages[..1], &messages[..1]),
+            None
+        );
+        assert_eq!(without_span(&messages, &[b.clone(), a.clone()], &[]), None);
+        assert_eq!(without_span(&messages[..1], &messages[..2], &[]), None);
+        assert_eq!(without_span(&messages, &[], &[]), None);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::api::SystemPrompt;
+use crate::components::icons::*;
+use crate::export::{self, ExportFormat};
+use crate::storage::{AppStorage, ConversationStore, StoredConversation};
+use leptos::ev::MouseEvent;
+use leptos::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Scope {
+    Current,
+    All,
+}
+
+async fn gather(
+    storage: &AppStorage,
+    scope: Scope,
+    current_id: &str,
+) -> Result<Vec<StoredConversation>, String> {
+    let ids = match scope {
+        Scope::Current => vec![current_id.to_string()],
+        Scope::All => storage
+            .list_conversations(u32::MAX)
+            .await
+            .map_err(|e| format!("{:?}", e))?
+            .into_iter()
+            .map(|c| c.id)
+            .collect(),
+    };
+    let mut out = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(conv) = storage
+            .load_conversation(&id)
+            .await
+            .map_err(|e| format!("{:?}", e))?
+        {
+            out.push(conv);
+        }
+    }
+    Ok(out)
+}
+
+/// Header button that downloads the open conversation, or every stored
+/// one, as Markdown, JSON, a standalone HTML page, or its rated replies as
+/// a fine-tuning set.
+#[component]
+pub fn ExportMenu(
+    storage: RwSignal<Option<AppStorage>>,
+    /// Persona prompts for rated replies that didn't record theirs.
+    prompts: RwSignal<Vec<SystemPrompt>>,
+    conversation_id: ReadSignal<String>,
+    title: Signal<String>,
+    disabled: Signal<bool>,
+) -> impl IntoView {
+    let (open, set_open) = create_signal(false);
+    let (busy, set_busy) = create_signal(false);
+    let (error, set_error) = create_signal(None::<String>);
+
+    let toggle = move |e: MouseEvent| {
+        e.stop_propagation();
+        set_error.set(None);
+        set_open.update(|o| *o = !*o);
+    };
+
+    create_effect(move |_| {
+        if !open.get() {
+            return;
+        }
+        let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+            if let Some(target) = e.target() {
+                if let Ok(el) = target.dyn_into::<web_sys::Element>() {
+                    if el
+                        .closest(".export-popover-anchor")
+                        .ok()
+                        .flatten()
+                        .is_none()
+                    {
+                        set_open.set(false);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+            let _ =
+                doc.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    });
+
+    let run = move |scope: Scope, format: ExportFormat| {
+        let Some(storage) = storage.get_untracked() else {
+            return;
+        };
+        let current_id = conversation_id.get_untracked();
+        let prompts = prompts.get_untracked();
+        let name = match scope {
+            Scope::Current => title.get_untracked(),
+            Scope::All => "all-conversations".to_string(),
+        };
+        set_busy.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            let result = match gather(&storage, scope, &current_id).await {
+                Ok(convs) if convs.is_empty() => Err("Nothing saved to export yet.".to_string()),
+                Ok(convs) => match export::render(format, &convs, &prompts) {
+                    contents if contents.is_empty() && format == ExportFormat::FineTune => {
+                        Err("No rated replies yet. Rate replies with 👍 or 👎 first.".to_string())
+                    }
+                    contents => {
+                        export::download(&export::file_name(&name, format), format, &contents)
+                            .map_err(|e| format!("{:?}", e))
+                    }
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(()) => set_open.set(false),
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let row = move |label: &'static str, scope: Scope| {
+        view! {
+            <div class="export-row">
+                <span class="export-row-label">{label}</span>
+                {ExportFormat::ALL.into_iter().map(|format| view! {
+                    <button
+                        class="export-format"
+                        disabled=move || busy.get() || (scope == Scope::Current && disabled.get())
+                        on:click=move |_| run(scope, format)
+                    >
+                        {format.label()}
+                    </button>
+                }).collect_view()}
+            </div>
+        }
+    };
+
+    view! {
+        <div class="popover-anchor export-popover-anchor">
+            <button class="icon-btn" title="Export or import" on:click=toggle>
+                <IconDownload/>
+            </button>
+            {move || if open.get() {
+                view! {
+                    <div class="popover export-popover" on:click=|e| e.stop_propagation()>
+                        {row("This conversation", Scope::Current)}
+                        {row("All conversations", Scope::All)}
+                        {move || error.get().map(|e| view! { <div class="error-message">{e}</div> })}
+                        <a class="export-import" href="/import">"Import conversations…"</a>
+                    </div>
+                }.into_view()
+            } else {
+                view! { <span></span> }.into_view()
+            }}
+        </div>
+    }
+}
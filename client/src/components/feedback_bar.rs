@@ -0,0 +1,108 @@
+use crate::components::icons::*;
+use crate::notebook::{CellId, Feedback, Notebook, Rating};
+use chrono::Utc;
+use leptos::ev::KeyboardEvent;
+use leptos::*;
+
+/// Thumbs up or down and a note under a reply. Clicking the chosen thumb
+/// again clears it; rating a reply opens the note field.
+#[component]
+pub fn FeedbackBar(
+    cell: CellId,
+    notebook: ReadSignal<Notebook>,
+    on_feedback: Callback<(CellId, Option<Feedback>)>,
+) -> impl IntoView {
+    let feedback = create_memo(move |_| {
+        notebook.with(|nb| nb.get_cell(cell).and_then(|c| c.metadata.feedback.clone()))
+    });
+    let rating = create_memo(move |_| feedback.with(|f| f.as_ref().and_then(|f| f.rating)));
+    let comment = create_memo(move |_| {
+        feedback.with(|f| f.as_ref().map(|f| f.comment.clone()).unwrap_or_default())
+    });
+    let editing = create_rw_signal(false);
+    let draft = create_rw_signal(String::new());
+
+    let set = move |rating: Option<Rating>, comment: String| {
+        let feedback = (rating.is_some() || !comment.is_empty()).then(|| Feedback {
+            rating,
+            comment,
+            at: Utc::now(),
+        });
+        on_feedback.call((cell, feedback));
+    };
+    let rate = move |r: Rating| {
+        let next = (rating.get_untracked() != Some(r)).then_some(r);
+        let note = comment.get_untracked();
+        set(next, note.clone());
+        if next.is_some() && note.is_empty() {
+            draft.set(String::new());
+            editing.set(true);
+        }
+    };
+    let save = move || {
+        set(
+            rating.get_untracked(),
+            draft.get_untracked().trim().to_string(),
+        );
+        editing.set(false);
+    };
+    let on_key = move |e: KeyboardEvent| match e.key().as_str() {
+        "Enter" => {
+            e.prevent_default();
+            save();
+        }
+        "Escape" => editing.set(false),
+        _ => {}
+    };
+
+    view! {
+        <div class="feedback">
+            <button
+                class="code-copy"
+                class:active=move || rating.get() == Some(Rating::Up)
+                title="Good reply"
+                on:click=move |_| rate(Rating::Up)
+            >
+                <IconThumbUp/>
+            </button>
+            <button
+                class="code-copy"
+                class:active=move || rating.get() == Some(Rating::Down)
+                title="Bad reply"
+                on:click=move |_| rate(Rating::Down)
+            >
+                <IconThumbDown/>
+            </button>
+            {move || if editing.get() {
+                view! {
+                    <input
+                        class="feedback-input"
+                        type="text"
+                        placeholder="What made it good or bad? (optional)"
+                        prop:value=move || draft.get()
+                        on:input=move |ev| draft.set(event_target_value(&ev))
+                        on:keydown=on_key
+                    />
+                    <button class="feedback-save" on:click=move |_| save()>"Save"</button>
+                }
+                .into_view()
+            } else {
+                let note = comment.get();
+                view! {
+                    <button
+                        class="feedback-note"
+                        class:empty=note.is_empty()
+                        title="Add a note"
+                        on:click=move |_| {
+                            draft.set(comment.get_untracked());
+                            editing.set(true);
+                        }
+                    >
+                        {if note.is_empty() { "Add note".to_string() } else { note }}
+                    </button>
+                }
+                .into_view()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,149 @@
+use crate::import::{self, Skipped};
+use crate::storage::{AppStorage, ConversationStore};
+use crate::sync;
+use leptos::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+#[derive(Clone)]
+struct FileReport {
+    file: String,
+    /// The format detected, or why the file couldn't be read at all.
+    outcome: Result<&'static str, String>,
+    imported: usize,
+    skipped: Vec<Skipped>,
+}
+
+async fn read_file(file: &web_sys::File) -> Result<String, String> {
+    let text = JsFuture::from(file.text())
+        .await
+        .map_err(|e| format!("{:?}", e))?;
+    text.as_string()
+        .ok_or_else(|| "File is not text".to_string())
+}
+
+async fn import_file(storage: &AppStorage, file: &web_sys::File) -> FileReport {
+    let mut report = FileReport {
+        file: file.name(),
+        outcome: Err(String::new()),
+        imported: 0,
+        skipped: Vec::new(),
+    };
+    let parsed = match read_file(file).await.and_then(|text| import::parse(&text)) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            report.outcome = Err(e);
+            return report;
+        }
+    };
+    report.outcome = Ok(parsed.source.label());
+    report.skipped = parsed.skipped;
+    for mut conv in parsed.conversations {
+        // Stamped now, so the import beats a tombstone left by deleting an
+        // earlier copy; the original dates stay on `created_at` and the cells.
+        conv.metadata.modified_at = chrono::Utc::now();
+        let title = conv.metadata.title.clone();
+        if matches!(storage.load_conversation(&conv.id).await, Ok(Some(_))) {
+            report.skipped.push(Skipped {
+                title,
+                reason: "Already in your conversations".to_string(),
+            });
+            continue;
+        }
+        match storage.save_conversation(&conv).await {
+            Ok(()) => {
+                sync::mark_dirty(&conv.id);
+                report.imported += 1;
+            }
+            Err(e) => report.skipped.push(Skipped {
+                title,
+                reason: format!("Could not be saved: {:?}", e),
+            }),
+        }
+    }
+    report
+}
+
+/// Upload ChatGPT, Open WebUI or gamecode JSON exports and report what was
+/// imported and what was left out.
+#[component]
+pub fn ImportPage() -> impl IntoView {
+    let reports = create_rw_signal(Vec::<FileReport>::new());
+    let (busy, set_busy) = create_signal(false);
+
+    let on_change = move |ev: ev::Event| {
+        let Some(input) = ev
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+        else {
+            return;
+        };
+        let Some(files) = input.files() else {
+            return;
+        };
+        let files = (0..files.length())
+            .filter_map(|i| files.get(i))
+            .collect::<Vec<_>>();
+        // Clear the input so choosing the same file again fires a change.
+        input.set_value("");
+        if files.is_empty() {
+            return;
+        }
+        set_busy.set(true);
+        spawn_local(async move {
+            let storage = AppStorage::open().await;
+            for file in &files {
+                let report = import_file(&storage, file).await;
+                reports.update(|list| list.insert(0, report));
+            }
+            set_busy.set(false);
+        });
+    };
+
+    view! {
+        <div class="admin-page">
+            <div class="admin-head">
+                <h2>"Import conversations"</h2>
+                <a href="/">"Back to chat"</a>
+            </div>
+            <section class="admin-section">
+                <p class="import-help">
+                    "Choose a ChatGPT " <code>"conversations.json"</code>
+                    ", an Open WebUI chat export or a JSON export from gamecode. "
+                    "Conversations are added to this browser and synced to your account."
+                </p>
+                <input
+                    type="file"
+                    accept=".json,application/json"
+                    multiple
+                    disabled=move || busy.get()
+                    on:change=on_change
+                />
+                {move || busy.get().then(|| view! { <span class="import-busy">"Importing…"</span> })}
+            </section>
+            {move || reports.get().into_iter().map(|report| view! {
+                <section class="admin-section import-report">
+                    <h3>{report.file.clone()}</h3>
+                    {match report.outcome.clone() {
+                        Ok(source) => view! {
+                            <p>{format!(
+                                "{} conversation{} imported from {}.",
+                                report.imported,
+                                if report.imported == 1 { "" } else { "s" },
+                                source
+                            )}</p>
+                        }.into_view(),
+                        Err(e) => view! { <div class="error-message">{e}</div> }.into_view(),
+                    }}
+                    {(!report.skipped.is_empty()).then(|| view! {
+                        <ul class="admin-warnings">
+                            {report.skipped.iter().map(|s| view! {
+                                <li><strong>{s.title.clone()}</strong>": "{s.reason.clone()}</li>
+                            }).collect_view()}
+                        </ul>
+                    })}
+                </section>
+            }).collect_view()}
+        </div>
+    }
+}
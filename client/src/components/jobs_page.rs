@@ -0,0 +1,425 @@
+use crate::api::{
+    ApiClient, ApiError, CreateJobRequest, Job, JobInput, JobStatus, ProviderInfo, SystemPrompt,
+};
+use crate::components::auth::redirect_to_login;
+use chrono::{Local, TimeZone};
+use leptos::*;
+use std::collections::HashMap;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+/// An uploaded file, read and ready to send.
+#[derive(Clone)]
+struct Upload {
+    file: String,
+    format: &'static str,
+    text: String,
+}
+
+fn format_time(secs: i64) -> String {
+    Local
+        .timestamp_opt(secs, 0)
+        .single()
+        .map(|t| t.format("%b %-d, %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn job_meta(job: &Job) -> String {
+    let mut parts = vec![format!("{}/{}", job.provider, job.model)];
+    if let Some(persona) = &job.persona {
+        parts.push(persona.clone());
+    }
+    if let Some(schedule) = &job.schedule {
+        parts.push(format!("{} UTC", schedule));
+    }
+    match job.next_run_at {
+        Some(at) if job.status == JobStatus::Scheduled => {
+            parts.push(format!("next run {}", format_time(at)))
+        }
+        _ => parts.push(format!("started {}", format_time(job.created_at))),
+    }
+    parts.join(" · ")
+}
+
+fn progress(job: &Job) -> String {
+    let mut text = format!("{}/{}", job.done + job.failed, job.total);
+    if job.failed > 0 {
+        text.push_str(&format!(" · {} failed", job.failed));
+    }
+    if job.run > 1 {
+        text.push_str(&format!(" · run {}", job.run));
+    }
+    text
+}
+
+fn upsert(jobs: RwSignal<Option<Vec<Job>>>, job: Job) {
+    jobs.update(|list| {
+        let list = list.get_or_insert_with(Vec::new);
+        match list.iter_mut().find(|j| j.id == job.id) {
+            Some(existing) => *existing = job,
+            None => list.insert(0, job),
+        }
+    });
+}
+
+fn load_jobs(jobs: RwSignal<Option<Vec<Job>>>, set_error: WriteSignal<Option<String>>) {
+    spawn_local(async move {
+        match ApiClient::new().list_jobs().await {
+            Ok(list) => jobs.set(Some(list)),
+            Err(ApiError::Unauthorized) => redirect_to_login(),
+            Err(ApiError::Status(403)) => set_error.set(Some(
+                "Batch jobs are turned off on this server (GAMECODE_JOBS_CONCURRENCY=0)."
+                    .to_string(),
+            )),
+            Err(e) => set_error.set(Some(e.to_string())),
+        }
+    });
+}
+
+/// Follow `/api/jobs/events` while the page is open.
+fn watch_events(jobs: RwSignal<Option<Vec<Job>>>, set_error: WriteSignal<Option<String>>) {
+    let Ok(source) = web_sys::EventSource::new(&ApiClient::new().job_events_url()) else {
+        return;
+    };
+    let on_job = Closure::wrap(Box::new(move |e: web_sys::MessageEvent| {
+        let job = e
+            .data()
+            .as_string()
+            .and_then(|data| serde_json::from_str::<Job>(&data).ok());
+        if let Some(job) = job {
+            upsert(jobs, job);
+        }
+    }) as Box<dyn FnMut(_)>);
+    let on_deleted = Closure::wrap(Box::new(move |e: web_sys::MessageEvent| {
+        if let Some(id) = e.data().as_string() {
+            jobs.update(|list| {
+                if let Some(list) = list {
+                    list.retain(|j| j.id != id);
+                }
+            });
+        }
+    }) as Box<dyn FnMut(_)>);
+    // Updates were dropped; start over from the list.
+    let on_lagged = Closure::wrap(Box::new(move |_: web_sys::MessageEvent| {
+        load_jobs(jobs, set_error);
+    }) as Box<dyn FnMut(_)>);
+    let _ = source.add_event_listener_with_callback("job", on_job.as_ref().unchecked_ref());
+    let _ = source.add_event_listener_with_callback("deleted", on_deleted.as_ref().unchecked_ref());
+    let _ = source.add_event_listener_with_callback("lagged", on_lagged.as_ref().unchecked_ref());
+    on_cleanup(move || {
+        source.close();
+        drop((on_job, on_deleted, on_lagged));
+    });
+}
+
+/// Run a persona and a prompt template over every row of a CSV or JSONL
+/// file, once or on a schedule, and follow the jobs as they run.
+#[component]
+pub fn JobsPage() -> impl IntoView {
+    let jobs = create_rw_signal(None::<Vec<Job>>);
+    let (error, set_error) = create_signal(None::<String>);
+    let providers = create_rw_signal(Vec::<ProviderInfo>::new());
+    let personas = create_rw_signal(Vec::<SystemPrompt>::new());
+
+    let name = create_rw_signal(String::new());
+    let template = create_rw_signal(String::new());
+    let target = create_rw_signal(String::new());
+    let persona = create_rw_signal(String::new());
+    let concurrency = create_rw_signal(1usize);
+    let schedule = create_rw_signal(String::new());
+    let upload = create_rw_signal(None::<Upload>);
+    let (form_error, set_form_error) = create_signal(None::<String>);
+    let (busy, set_busy) = create_signal(false);
+
+    load_jobs(jobs, set_error);
+    watch_events(jobs, set_error);
+    spawn_local(async move {
+        let client = ApiClient::new();
+        if let Ok(resp) = client.list_providers().await {
+            if let Some((p, m)) = resp
+                .providers
+                .iter()
+                .find_map(|p| p.models.first().map(|m| (p.name.clone(), m.clone())))
+            {
+                target.set(format!("{}/{}", p, m));
+            }
+            providers.set(resp.providers);
+        }
+        if let Ok(resp) = client.list_prompts().await {
+            personas.set(resp.prompts);
+        }
+    });
+
+    let on_file = move |ev: ev::Event| {
+        let Some(file) = ev
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+            .and_then(|input| input.files())
+            .and_then(|files| files.get(0))
+        else {
+            upload.set(None);
+            return;
+        };
+        let lower = file.name().to_lowercase();
+        let format = if lower.ends_with(".jsonl") || lower.ends_with(".ndjson") {
+            "jsonl"
+        } else {
+            "csv"
+        };
+        spawn_local(async move {
+            match JsFuture::from(file.text())
+                .await
+                .ok()
+                .and_then(|t| t.as_string())
+            {
+                Some(text) => {
+                    if name.get_untracked().trim().is_empty() {
+                        let stem = file.name();
+                        let stem = stem.rsplit_once('.').map_or(stem.as_str(), |(s, _)| s);
+                        name.set(stem.to_string());
+                    }
+                    upload.set(Some(Upload {
+                        file: file.name(),
+                        format,
+                        text,
+                    }));
+                    set_form_error.set(None);
+                }
+                None => set_form_error.set(Some("Could not read the file".to_string())),
+            }
+        });
+    };
+
+    let on_submit = move |ev: ev::SubmitEvent| {
+        ev.prevent_default();
+        let Some((provider, model)) = target
+            .get_untracked()
+            .split_once('/')
+            .map(|(p, m)| (p.to_string(), m.to_string()))
+        else {
+            set_form_error.set(Some("Pick a model".to_string()));
+            return;
+        };
+        let picked = personas
+            .get_untracked()
+            .into_iter()
+            .find(|p| p.name == persona.get_untracked());
+        let schedule = schedule.get_untracked();
+        let req = CreateJobRequest {
+            name: name.get_untracked(),
+            provider,
+            model,
+            persona: picked.as_ref().map(|p| p.name.clone()),
+            system_prompt: picked.as_ref().map(|p| p.prompt.clone()),
+            variables: picked
+                .as_ref()
+                .map(|p| p.resolve_variables(&HashMap::new()))
+                .unwrap_or_default(),
+            template: template.get_untracked(),
+            options: picked.map(|p| p.options).unwrap_or_default(),
+            concurrency: Some(concurrency.get_untracked()),
+            schedule: (!schedule.trim().is_empty()).then_some(schedule),
+            input: upload.get_untracked().map(|u| JobInput {
+                format: u.format.to_string(),
+                text: u.text,
+            }),
+        };
+        set_busy.set(true);
+        spawn_local(async move {
+            match ApiClient::new().create_job(&req).await {
+                Ok(job) => {
+                    upsert(jobs, job);
+                    set_form_error.set(None);
+                    name.set(String::new());
+                    upload.set(None);
+                }
+                Err(ApiError::Unauthorized) => redirect_to_login(),
+                Err(e) => set_form_error.set(Some(match e {
+                    ApiError::Server(msg) => msg,
+                    other => other.to_string(),
+                })),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_cancel = move |id: String| {
+        spawn_local(async move {
+            match ApiClient::new().cancel_job(&id).await {
+                Ok(job) => upsert(jobs, job),
+                Err(ApiError::Unauthorized) => redirect_to_login(),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    };
+    let on_delete = move |id: String| {
+        spawn_local(async move {
+            match ApiClient::new().delete_job(&id).await {
+                Ok(()) => jobs.update(|list| {
+                    if let Some(list) = list {
+                        list.retain(|j| j.id != id);
+                    }
+                }),
+                Err(ApiError::Unauthorized) => redirect_to_login(),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="admin-page">
+            <div class="admin-head">
+                <h2>"Batch jobs"</h2>
+                <a href="/">"Back to chat"</a>
+            </div>
+            {move || error.get().map(|e| view! { <div class="error-message">{e}</div> })}
+            <section class="admin-section">
+                <h3>"New job"</h3>
+                <p class="import-help">
+                    "Each row of a CSV (with a header line) or JSONL file fills the template's "
+                    <code>"{{column}}"</code>
+                    " placeholders. Replies can be downloaded below and also appear in the "
+                    "sidebar as a “Job: …” conversation."
+                </p>
+                <form class="job-form" on:submit=on_submit>
+                    <label>
+                        "Rows"
+                        <input type="file" accept=".csv,.jsonl,.ndjson,text/csv" on:change=on_file/>
+                    </label>
+                    <label>
+                        "Name"
+                        <input
+                            type="text"
+                            prop:value=move || name.get()
+                            on:input=move |ev| name.set(event_target_value(&ev))
+                        />
+                    </label>
+                    <label class="job-form-wide">
+                        "Prompt template"
+                        <textarea
+                            rows="4"
+                            placeholder="Summarize this ticket in one sentence:\n\n{{body}}"
+                            prop:value=move || template.get()
+                            on:input=move |ev| template.set(event_target_value(&ev))
+                        ></textarea>
+                    </label>
+                    <label>
+                        "Model"
+                        <select on:change=move |ev| target.set(event_target_value(&ev))>
+                            {move || providers.get().into_iter().flat_map(|p| {
+                                let provider = p.name;
+                                p.models.into_iter().map(move |m| format!("{}/{}", provider, m))
+                            }).map(|value| {
+                                let selected = target.get_untracked() == value;
+                                view! {
+                                    <option value=value.clone() selected=selected>{value.clone()}</option>
+                                }
+                            }).collect_view()}
+                        </select>
+                    </label>
+                    <label>
+                        "Persona"
+                        <select on:change=move |ev| persona.set(event_target_value(&ev))>
+                            <option value="">"None"</option>
+                            {move || personas.get().into_iter().map(|p| view! {
+                                <option value=p.name.clone()>{p.name.clone()}</option>
+                            }).collect_view()}
+                        </select>
+                    </label>
+                    <label>
+                        "Rows at a time"
+                        <input
+                            type="number"
+                            min="1"
+                            prop:value=move || concurrency.get().to_string()
+                            on:input=move |ev| {
+                                if let Ok(n) = event_target_value(&ev).parse::<usize>() {
+                                    concurrency.set(n.max(1));
+                                }
+                            }
+                        />
+                    </label>
+                    <label>
+                        "Schedule (cron, UTC)"
+                        <input
+                            type="text"
+                            placeholder="optional, e.g. 0 6 * * 1-5"
+                            prop:value=move || schedule.get()
+                            on:input=move |ev| schedule.set(event_target_value(&ev))
+                        />
+                    </label>
+                    <div class="job-form-actions job-form-wide">
+                        <span class="admin-meta job-upload">
+                            {move || upload.get().map(|u| u.file)}
+                        </span>
+                        <button class="share-create" type="submit" disabled=move || busy.get()>
+                            {move || if busy.get() { "Starting…" } else { "Start job" }}
+                        </button>
+                    </div>
+                </form>
+                {move || form_error.get().map(|e| view! { <div class="error-message">{e}</div> })}
+            </section>
+            {move || jobs.get().map(|list| view! {
+                <section class="admin-section">
+                    <h3>"Jobs"</h3>
+                    {if list.is_empty() {
+                        view! { <div class="share-empty">"No jobs yet."</div> }.into_view()
+                    } else {
+                        list.into_iter().map(|job| {
+                            let stoppable = matches!(
+                                job.status,
+                                JobStatus::Queued | JobStatus::Running | JobStatus::Scheduled
+                            );
+                            let (cancel_id, delete_id) = (job.id.clone(), job.id.clone());
+                            let client = ApiClient::new();
+                            view! {
+                                <div class="job-row">
+                                    <div class="share-row-main">
+                                        <div class="share-row-title">{job.name.clone()}</div>
+                                        <div class="share-row-meta">{job_meta(&job)}</div>
+                                        <div class="job-progress">
+                                            <progress
+                                                max=job.total.to_string()
+                                                value=(job.done + job.failed).to_string()
+                                            ></progress>
+                                            <span class=format!("job-status {}", job.status.label())>
+                                                {job.status.label()}
+                                            </span>
+                                            <span class="share-row-meta">{progress(&job)}</span>
+                                        </div>
+                                        {job.error.clone().map(|e| view! {
+                                            <div class="share-row-meta job-error">{e}</div>
+                                        })}
+                                    </div>
+                                    {(job.last_seq > 0).then(|| view! {
+                                        <a class="export-format" href=client.job_download_url(&job.id, "csv") download="">
+                                            "CSV"
+                                        </a>
+                                        <a class="export-format" href=client.job_download_url(&job.id, "jsonl") download="">
+                                            "JSONL"
+                                        </a>
+                                    })}
+                                    {stoppable.then(|| view! {
+                                        <button
+                                            class="export-format"
+                                            on:click=move |_| on_cancel(cancel_id.clone())
+                                        >
+                                            "Cancel"
+                                        </button>
+                                    })}
+                                    <button
+                                        class="export-format"
+                                        on:click=move |_| on_delete(delete_id.clone())
+                                    >
+                                        "Delete"
+                                    </button>
+                                </div>
+                            }
+                        }).collect_view()
+                    }}
+                </section>
+            })}
+        </div>
+    }
+}
@@ -1,10 +1,24 @@
+pub mod admin_health;
 pub mod auth;
 pub mod chat;
+pub mod compare_picker;
 pub mod composer;
+pub mod context_inspector;
 pub mod context_manager;
 pub mod empty_state;
+pub mod export_menu;
+pub mod feedback_bar;
 pub mod icons;
+pub mod import_page;
+pub mod jobs_page;
 pub mod model_picker;
 pub mod persona_picker;
+pub mod provenance_details;
+pub mod regenerate_menu;
+pub mod sampling_popover;
+pub mod share_menu;
+pub mod shared_view;
+pub mod shares_page;
 pub mod sidebar;
 pub mod sidebar_resize;
+pub mod summary_popover;
@@ -1,7 +1,9 @@
-use crate::api::SystemPrompt;
+use crate::api::{ApiClient, ApiError, PersonaInput, SystemPrompt};
+use crate::components::auth::redirect_to_login;
 use crate::components::icons::*;
 use leptos::ev::MouseEvent;
 use leptos::*;
+use std::collections::HashMap;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 
@@ -19,13 +21,126 @@ pub fn persona_color_var(name: &str) -> &'static str {
     }
 }
 
+/// The persona being created or edited in the picker.
+#[derive(Clone, Default, PartialEq)]
+struct Draft {
+    /// `None` while creating a new persona.
+    id: Option<String>,
+    name: String,
+    prompt: String,
+}
+
 #[component]
 pub fn PersonaPicker(
-    prompts: ReadSignal<Vec<SystemPrompt>>,
+    prompts: RwSignal<Vec<SystemPrompt>>,
     selected_name: RwSignal<String>,
-    custom_prompt: RwSignal<String>,
+    variables: RwSignal<HashMap<String, String>>,
 ) -> impl IntoView {
     let (open, set_open) = create_signal(false);
+    let editor = create_rw_signal(None::<Draft>);
+    let saving = create_rw_signal(false);
+    let edit_error = create_rw_signal(None::<String>);
+    // Re-render the editor only when a different persona is opened, not on
+    // every keystroke into the draft.
+    let editing = create_memo(move |_| editor.with(|d| d.as_ref().map(|d| d.id.clone())));
+
+    let report = move |e: ApiError| match e {
+        ApiError::Unauthorized => redirect_to_login(),
+        e => edit_error.set(Some(e.to_string())),
+    };
+
+    let save = move |_: MouseEvent| {
+        let Some(draft) = editor.get_untracked() else {
+            return;
+        };
+        // Sampling defaults and variables aren't editable here yet; keep them.
+        let existing = draft.id.as_ref().and_then(|id| {
+            prompts
+                .get_untracked()
+                .into_iter()
+                .find(|p| p.id.as_ref() == Some(id))
+        });
+        let input = PersonaInput {
+            name: draft.name.trim().to_string(),
+            prompt: draft.prompt,
+            suggested_models: existing
+                .as_ref()
+                .map(|p| p.suggested_models.clone())
+                .unwrap_or_default(),
+            options: existing
+                .as_ref()
+                .map(|p| p.options.clone())
+                .unwrap_or_default(),
+            variables: existing.map(|p| p.variables).unwrap_or_default(),
+        };
+        saving.set(true);
+        spawn_local(async move {
+            let client = ApiClient::new();
+            let result = match &draft.id {
+                Some(id) => client.update_persona(id, &input).await,
+                None => client.create_persona(&input).await,
+            };
+            saving.set(false);
+            match result {
+                Ok(saved) => {
+                    selected_name.set(saved.name.clone());
+                    prompts.update(|list| {
+                        match list.iter_mut().find(|p| p.id.is_some() && p.id == saved.id) {
+                            Some(slot) => *slot = saved,
+                            None => list.push(saved),
+                        }
+                    });
+                    editor.set(None);
+                    edit_error.set(None);
+                }
+                Err(e) => report(e),
+            }
+        });
+    };
+
+    let delete = move |_: MouseEvent| {
+        let Some(id) = editor.get_untracked().and_then(|d| d.id) else {
+            return;
+        };
+        saving.set(true);
+        spawn_local(async move {
+            let result = ApiClient::new().delete_persona(&id).await;
+            saving.set(false);
+            match result {
+                Ok(()) => {
+                    let removed = prompts
+                        .get_untracked()
+                        .into_iter()
+                        .find(|p| p.id.as_deref() == Some(id.as_str()));
+                    prompts.update(|list| list.retain(|p| p.id.as_deref() != Some(id.as_str())));
+                    if removed.is_some_and(|p| p.name == selected_name.get_untracked()) {
+                        if let Some(first) = prompts.get_untracked().first() {
+                            selected_name.set(first.name.clone());
+                        }
+                    }
+                    editor.set(None);
+                    edit_error.set(None);
+                }
+                Err(e) => report(e),
+            }
+        });
+    };
+
+    let selected_prompt = move || {
+        let name = selected_name.get();
+        prompts.get().into_iter().find(|p| p.name == name)
+    };
+    let unset_count = move || {
+        selected_prompt()
+            .map(|p| {
+                let resolved = p.resolve_variables(&variables.get());
+                p.variables
+                    .iter()
+                    .filter(|v| !resolved.contains_key(&v.name))
+                    .count()
+            })
+            .unwrap_or(0)
+    };
 
     let toggle = move |e: MouseEvent| {
         e.stop_propagation();
@@ -66,6 +181,14 @@ pub fn PersonaPicker(
                     style:background=move || persona_color_var(&selected_name.get()).to_string()
                 ></span>
                 <span>{move || selected_name.get()}</span>
+                {move || match unset_count() {
+                    0 => view! { <span></span> }.into_view(),
+                    n => view! {
+                        <span class="persona-var-badge" title="Prompt variables without a value">
+                            {format!("{} unset", n)}
+                        </span>
+                    }.into_view(),
+                }}
                 <IconChevronDown/>
             </button>
             {move || if open.get() {
@@ -78,6 +201,11 @@ pub fn PersonaPicker(
                                 let name_cmp = p.name.clone();
                                 let color = persona_color_var(&p.name);
                                 let suggested = p.suggested_models.join(", ");
+                                let draft = p.id.clone().map(|id| Draft {
+                                    id: Some(id),
+                                    name: p.name.clone(),
+                                    prompt: p.prompt.clone(),
+                                });
                                 let is_selected = create_memo(move |_| selected_name.get() == name_cmp);
                                 view! {
                                     <div
@@ -96,23 +224,113 @@ pub fn PersonaPicker(
                                                 view! { <div class="persona-desc">{suggested}</div> }.into_view()
                                             }}
                                         </div>
+                                        {draft.map(|draft| view! {
+                                            <button
+                                                class="persona-edit"
+                                                title="Edit persona"
+                                                on:click=move |e| {
+                                                    e.stop_propagation();
+                                                    edit_error.set(None);
+                                                    editor.set(Some(draft.clone()));
+                                                }
+                                            >
+                                                <IconEdit/>
+                                            </button>
+                                        })}
                                     </div>
                                 }
                             }).collect_view()}
                         </div>
-                        {move || if selected_name.get() == "Custom" {
+                        {move || match editing.get() {
+                            None => view! {
+                                <button
+                                    class="persona-new"
+                                    on:click=move |_| {
+                                        edit_error.set(None);
+                                        editor.set(Some(Draft::default()));
+                                    }
+                                >
+                                    <IconPlus/>
+                                    <span>"New persona"</span>
+                                </button>
+                            }.into_view(),
+                            Some(id) => {
+                                let draft = editor.get_untracked().unwrap_or_default();
+                                view! {
+                                    <div class="persona-custom persona-editor">
+                                        <div class="persona-custom-label">
+                                            {if id.is_some() { "Edit persona" } else { "New persona" }}
+                                        </div>
+                                        <input
+                                            type="text"
+                                            placeholder="Name"
+                                            value=draft.name
+                                            on:input=move |ev| {
+                                                let v = event_target_value(&ev);
+                                                editor.update(|d| if let Some(d) = d { d.name = v });
+                                            }
+                                        />
+                                        <textarea
+                                            placeholder="System prompt. {{date}}, {{username}} and {{model}} are filled in for you."
+                                            on:input=move |ev| {
+                                                let v = event_target_value(&ev);
+                                                editor.update(|d| if let Some(d) = d { d.prompt = v });
+                                            }
+                                        >{draft.prompt}</textarea>
+                                        {move || edit_error.get().map(|e| view! {
+                                            <div class="persona-editor-error">{e}</div>
+                                        })}
+                                        <div class="persona-editor-actions">
+                                            {id.is_some().then(|| view! {
+                                                <button
+                                                    class="persona-delete"
+                                                    disabled=move || saving.get()
+                                                    on:click=delete
+                                                >"Delete"</button>
+                                            })}
+                                            <button on:click=move |_| editor.set(None)>"Cancel"</button>
+                                            <button
+                                                class="persona-save"
+                                                disabled=move || saving.get()
+                                                on:click=save
+                                            >"Save"</button>
+                                        </div>
+                                    </div>
+                                }.into_view()
+                            }
+                        }}
+                        {move || {
+                            let vars = selected_prompt().map(|p| p.variables).unwrap_or_default();
+                            if vars.is_empty() {
+                                return view! { <span></span> }.into_view();
+                            }
+                            let current = variables.get_untracked();
                             view! {
-                                <div class="persona-custom">
-                                    <div class="persona-custom-label">"Custom prompt"</div>
-                                    <textarea
-                                        placeholder="Enter your custom system prompt..."
-                                        prop:value=move || custom_prompt.get()
-                                        on:input=move |ev| custom_prompt.set(event_target_value(&ev))
-                                    />
+                                <div class="persona-custom persona-vars">
+                                    <div class="persona-custom-label">"Prompt variables"</div>
+                                    {vars.into_iter().map(|var| {
+                                        let name = var.name.clone();
+                                        let label = var.label.clone().unwrap_or_else(|| var.name.clone());
+                                        let value = current.get(&var.name).cloned().unwrap_or_default();
+                                        view! {
+                                            <label class="persona-var">
+                                                <span>{label}</span>
+                                                <input
+                                                    type="text"
+                                                    placeholder=var.default.unwrap_or_default()
+                                                    value=value
+                                                    on:input=move |ev| {
+                                                        let v = event_target_value(&ev);
+                                                        variables.update(|m| {
+                                                            m.insert(name.clone(), v);
+                                                        });
+                                                    }
+                                                />
+                                            </label>
+                                        }
+                                    }).collect_view()}
                                 </div>
                             }.into_view()
-                        } else {
-                            view! { <span></span> }.into_view()
                         }}
                     </div>
                 }.into_view()
@@ -0,0 +1,191 @@
+use crate::notebook::{CellContent, CellId, ContextItem, Notebook, Provenance};
+use leptos::*;
+
+/// The request recorded on a reply: where it went, how it was sampled,
+/// which persona spoke and every message it was sent. `on_replay` sends
+/// the same request again as a new version.
+#[component]
+pub fn ProvenanceDetails(
+    cell: CellId,
+    notebook: ReadSignal<Notebook>,
+    on_replay: Option<Callback<CellId>>,
+    busy: Signal<bool>,
+) -> impl IntoView {
+    let provenance = create_memo(move |_| {
+        notebook.with(|nb| {
+            nb.get_cell(cell)
+                .and_then(|c| c.metadata.provenance.clone())
+        })
+    });
+    // Digest of the reply this one replayed, to flag a model that changed
+    // underneath it.
+    let original_digest = create_memo(move |_| {
+        let replay_of = provenance.with(|p| p.as_ref().and_then(|p| p.replay_of))?;
+        notebook.with(|nb| {
+            nb.get_cell(replay_of)
+                .and_then(|c| c.metadata.provenance.as_ref())
+                .map(|p| p.model_digest.clone())
+        })
+    });
+    let missing = create_memo(move |_| {
+        provenance.with(|p| {
+            p.as_ref().map_or(0, |p| {
+                notebook.with(|nb| {
+                    p.context
+                        .iter()
+                        .filter(|item| {
+                            matches!(item, ContextItem::Cell(id) if nb.get_cell(*id).is_none())
+                        })
+                        .count()
+                })
+            })
+        })
+    });
+
+    move || {
+        provenance.get().map(|p| {
+            let digest = p.model_digest.clone();
+            let changed = original_digest
+                .get()
+                .is_some_and(|original| original.is_some() && original != digest);
+            let context = p
+                .context
+                .iter()
+                .map(|item| context_row(item, notebook))
+                .collect_view();
+            view! {
+                <div class="provenance">
+                    <dl class="provenance-fields">
+                        <dt>"provider"</dt>
+                        <dd>{p.provider.clone()}</dd>
+                        <dt>"model"</dt>
+                        <dd>{p.model.clone()}</dd>
+                        <dt>"digest"</dt>
+                        <dd title=digest.clone().unwrap_or_default() class:provenance-warn=changed>
+                            {match &digest {
+                                Some(d) => short_digest(d),
+                                None => "not reported".to_string(),
+                            }}
+                            {changed.then_some(" · differs from the original")}
+                        </dd>
+                        <dt>"sampling"</dt>
+                        <dd>{sampling_summary(&p)}</dd>
+                        <dt>"persona"</dt>
+                        <dd>
+                            {match (&p.persona, &p.prompt_hash) {
+                                (Some(name), Some(hash)) => format!("{} · prompt {}", name, hash),
+                                (Some(name), None) => name.clone(),
+                                _ => "none".to_string(),
+                            }}
+                        </dd>
+                        {(!p.variables.is_empty()).then(|| {
+                            let mut vars = p
+                                .variables
+                                .iter()
+                                .map(|(k, v)| format!("{}={}", k, v))
+                                .collect::<Vec<_>>();
+                            vars.sort();
+                            view! {
+                                <dt>"variables"</dt>
+                                <dd>{vars.join(", ")}</dd>
+                            }
+                        })}
+                    </dl>
+                    <div class="provenance-context-head">
+                        {format!("{} messages sent", p.context.len())}
+                    </div>
+                    <ol class="provenance-context">{context}</ol>
+                    {on_replay.map(|on_replay| view! {
+                        <div class="provenance-actions">
+                            {move || (missing.get() > 0).then(|| view! {
+                                <span class="provenance-warn">
+                                    "A message it was sent has been deleted."
+                                </span>
+                            })}
+                            <button
+                                class="msg-edit-send"
+                                title="Send the identical request again as a new version"
+                                disabled=move || { busy.get() || missing.get() > 0 }
+                                on:click=move |_| on_replay.call(cell)
+                            >
+                                "Replay exactly"
+                            </button>
+                        </div>
+                    })}
+                </div>
+            }
+        })
+    }
+}
+
+fn context_row(item: &ContextItem, notebook: ReadSignal<Notebook>) -> impl IntoView {
+    let (source, message) = match item {
+        ContextItem::Cell(id) => (
+            "cell",
+            notebook.with_untracked(|nb| match nb.get_cell(*id).map(|c| &c.content) {
+                Some(CellContent::UserInput { text }) => Some(("user".to_string(), text.clone())),
+                Some(CellContent::TextResponse { text, .. }) => {
+                    Some(("assistant".to_string(), text.clone()))
+                }
+                _ => None,
+            }),
+        ),
+        ContextItem::Text(m) => ("text", Some((m.role.clone(), m.content.clone()))),
+    };
+    let (role, preview) = match message {
+        Some((role, text)) => (role, preview(&text)),
+        None => ("deleted".to_string(), String::new()),
+    };
+    view! {
+        <li>
+            <span class="inspector-role">{format!("{} · {}", role, source)}</span>
+            <span class="provenance-preview">{preview}</span>
+        </li>
+    }
+}
+
+fn preview(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if flat.chars().count() > 120 {
+        format!("{}…", flat.chars().take(120).collect::<String>())
+    } else {
+        flat
+    }
+}
+
+/// `sha256:` prefix dropped, first 12 hex digits kept, as `ollama list`
+/// shows them.
+fn short_digest(digest: &str) -> String {
+    let hex = digest.strip_prefix("sha256:").unwrap_or(digest);
+    hex.chars().take(12).collect()
+}
+
+fn sampling_summary(p: &Provenance) -> String {
+    let s = &p.sampling;
+    let mut parts = vec![format!("temperature {}", p.temperature)];
+    if let Some(v) = s.max_tokens {
+        parts.push(format!("max_tokens {}", v));
+    }
+    if let Some(v) = s.top_p {
+        parts.push(format!("top_p {}", v));
+    }
+    if let Some(v) = s.top_k {
+        parts.push(format!("top_k {}", v));
+    }
+    if let Some(v) = s.min_p {
+        parts.push(format!("min_p {}", v));
+    }
+    if let Some(v) = s.repeat_penalty {
+        parts.push(format!("repeat_penalty {}", v));
+    }
+    if let Some(v) = s.seed {
+        parts.push(format!("seed {}", v));
+    }
+    if let Some(v) = s.num_ctx {
+        parts.push(format!("num_ctx {}", v));
+    }
+    if !s.stop.is_empty() {
+        parts.push(format!("stop {:?}", s.stop));
+    }
+    parts.join(" · ")
+}
@@ -0,0 +1,140 @@
+use crate::components::icons::*;
+use crate::notebook::CellId;
+use leptos::ev::MouseEvent;
+use leptos::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+/// Answer the same message again as a new version of `cell`. `None` keeps
+/// the chat's current model or temperature.
+#[derive(Clone)]
+pub struct Regenerate {
+    pub cell: CellId,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+/// What the menu offers: the provider's models and the chat's settings as
+/// defaults.
+#[derive(Clone, Copy)]
+pub struct RegenerateOptions {
+    pub models: Signal<Vec<String>>,
+    pub model: Signal<String>,
+    pub temperature: Signal<f32>,
+    pub on_regenerate: Callback<Regenerate>,
+}
+
+/// "Regenerate" on a finished reply, with a popover to pick another model
+/// or temperature for that one version.
+#[component]
+pub fn RegenerateMenu(
+    cell: CellId,
+    options: RegenerateOptions,
+    busy: Signal<bool>,
+) -> impl IntoView {
+    let (open, set_open) = create_signal(false);
+    let model = create_rw_signal(String::new());
+    let temperature = create_rw_signal(String::new());
+
+    let toggle = move |e: MouseEvent| {
+        e.stop_propagation();
+        if !open.get_untracked() {
+            model.set(options.model.get_untracked());
+            temperature.set(format!("{:.1}", options.temperature.get_untracked()));
+        }
+        set_open.update(|o| *o = !*o);
+    };
+
+    create_effect(move |_| {
+        if !open.get() {
+            return;
+        }
+        let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+            if let Some(target) = e.target() {
+                if let Ok(el) = target.dyn_into::<web_sys::Element>() {
+                    if el.closest(".regen-popover-anchor").ok().flatten().is_none() {
+                        set_open.set(false);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+        if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+            let _ =
+                doc.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    });
+
+    let regenerate_same = move |_| {
+        options.on_regenerate.call(Regenerate {
+            cell,
+            model: None,
+            temperature: None,
+        })
+    };
+    let regenerate_with = move |_| {
+        set_open.set(false);
+        options.on_regenerate.call(Regenerate {
+            cell,
+            model: Some(model.get_untracked()).filter(|m| !m.is_empty()),
+            temperature: temperature.get_untracked().trim().parse().ok(),
+        });
+    };
+
+    view! {
+        <div class="popover-anchor regen-popover-anchor">
+            <span class="regen">
+                <button
+                    class="code-copy"
+                    title="Regenerate this reply as a new version"
+                    disabled=move || busy.get()
+                    on:click=regenerate_same
+                >
+                    <IconRefresh/>
+                    <span>"Regenerate"</span>
+                </button>
+                <button
+                    class="code-copy"
+                    title="Regenerate with another model or temperature"
+                    disabled=move || busy.get()
+                    on:click=toggle
+                >
+                    <IconChevronDown/>
+                </button>
+            </span>
+            {move || open.get().then(|| view! {
+                <div class="popover regen-popover" on:click=|e| e.stop_propagation()>
+                    <label class="sampling-field">
+                        <span class="sampling-label">"model"</span>
+                        <select on:change=move |ev| model.set(event_target_value(&ev))>
+                            {options.models.get().into_iter().map(|m| {
+                                let selected = m == model.get_untracked();
+                                view! { <option value=m.clone() selected=selected>{m}</option> }
+                            }).collect_view()}
+                        </select>
+                    </label>
+                    <label class="sampling-field">
+                        <span class="sampling-label">"temperature"</span>
+                        <input
+                            type="number"
+                            step="0.1"
+                            min="0"
+                            max="2"
+                            prop:value=move || temperature.get()
+                            on:input=move |ev| temperature.set(event_target_value(&ev))
+                        />
+                    </label>
+                    <div class="sampling-foot">
+                        <button
+                            class="regen-go"
+                            disabled=move || busy.get()
+                            on:click=regenerate_with
+                        >
+                            "Regenerate"
+                        </button>
+                    </div>
+                </div>
+            })}
+        </div>
+    }
+}
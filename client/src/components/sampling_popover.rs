@@ -0,0 +1,184 @@
+use crate::api::SamplingParams;
+use crate::components::icons::*;
+use leptos::ev::MouseEvent;
+use leptos::*;
+use std::str::FromStr;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+fn parse_opt<T: FromStr>(raw: &str) -> Option<T> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        None
+    } else {
+        raw.parse().ok()
+    }
+}
+
+fn show_opt<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+#[component]
+fn SamplingField(
+    label: &'static str,
+    hint: &'static str,
+    step: &'static str,
+    initial: String,
+    on_input: Callback<String>,
+) -> impl IntoView {
+    view! {
+        <label class="sampling-field">
+            <span class="sampling-label">{label}</span>
+            <input
+                type="number"
+                step=step
+                placeholder=hint
+                value=initial
+                on:input=move |ev| on_input.call(event_target_value(&ev))
+            />
+        </label>
+    }
+}
+
+/// Advanced sampling settings shown next to the composer's temperature
+/// slider. Empty fields fall back to the provider default.
+#[component]
+pub fn SamplingPopover(sampling: RwSignal<SamplingParams>) -> impl IntoView {
+    let (open, set_open) = create_signal(false);
+    // Bumped on reset so the uncontrolled inputs re-render with fresh values.
+    let (generation, set_generation) = create_signal(0u32);
+
+    let toggle = move |e: MouseEvent| {
+        e.stop_propagation();
+        set_open.update(|o| *o = !*o);
+    };
+
+    create_effect(move |_| {
+        if !open.get() {
+            return;
+        }
+        let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+            if let Some(target) = e.target() {
+                if let Ok(el) = target.dyn_into::<web_sys::Element>() {
+                    if el
+                        .closest(".sampling-popover-anchor")
+                        .ok()
+                        .flatten()
+                        .is_none()
+                    {
+                        set_open.set(false);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+            let _ =
+                doc.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    });
+
+    let customized = move || sampling.get() != SamplingParams::default();
+
+    view! {
+        <div class="popover-anchor sampling-popover-anchor">
+            <button
+                class="temp-control"
+                class:active=customized
+                title="Advanced sampling settings"
+                on:click=toggle
+            >
+                <IconSliders/>
+                <span class="temp-label">"ADV"</span>
+            </button>
+            {move || if open.get() {
+                let _ = generation.get();
+                let s = sampling.get_untracked();
+                view! {
+                    <div class="popover sampling-popover" on:click=|e| e.stop_propagation()>
+                        <div class="sampling-grid">
+                            <SamplingField
+                                label="top_p" hint="0.9" step="0.05"
+                                initial=show_opt(s.top_p)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.top_p = parse_opt(&v))
+                                })
+                            />
+                            <SamplingField
+                                label="top_k" hint="40" step="1"
+                                initial=show_opt(s.top_k)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.top_k = parse_opt(&v))
+                                })
+                            />
+                            <SamplingField
+                                label="min_p" hint="0.0" step="0.01"
+                                initial=show_opt(s.min_p)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.min_p = parse_opt(&v))
+                                })
+                            />
+                            <SamplingField
+                                label="repeat_penalty" hint="1.1" step="0.05"
+                                initial=show_opt(s.repeat_penalty)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.repeat_penalty = parse_opt(&v))
+                                })
+                            />
+                            <SamplingField
+                                label="seed" hint="random" step="1"
+                                initial=show_opt(s.seed)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.seed = parse_opt(&v))
+                                })
+                            />
+                            <SamplingField
+                                label="num_ctx" hint="model" step="256"
+                                initial=show_opt(s.num_ctx)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.num_ctx = parse_opt(&v))
+                                })
+                            />
+                            <SamplingField
+                                label="max_tokens" hint="unlimited" step="64"
+                                initial=show_opt(s.max_tokens)
+                                on_input=Callback::new(move |v: String| {
+                                    sampling.update(|s| s.max_tokens = parse_opt(&v))
+                                })
+                            />
+                        </div>
+                        <label class="sampling-field sampling-stop">
+                            <span class="sampling-label">"stop (one per line)"</span>
+                            <textarea
+                                rows="2"
+                                on:input=move |ev| {
+                                    let stop = event_target_value(&ev)
+                                        .lines()
+                                        .filter(|l| !l.is_empty())
+                                        .map(str::to_string)
+                                        .collect();
+                                    sampling.update(|s| s.stop = stop);
+                                }
+                            >{s.stop.join("\n")}</textarea>
+                        </label>
+                        <div class="sampling-foot">
+                            <button
+                                class="sampling-reset"
+                                on:click=move |_| {
+                                    sampling.set(SamplingParams::default());
+                                    set_generation.update(|g| *g += 1);
+                                }
+                            >
+                                "Reset to provider defaults"
+                            </button>
+                        </div>
+                    </div>
+                }.into_view()
+            } else {
+                view! { <span></span> }.into_view()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,243 @@
+use crate::api::{ApiClient, ApiError, CreateShareRequest, Share, ShareSnapshot};
+use crate::components::auth::redirect_to_login;
+use crate::components::icons::*;
+use chrono::{Local, TimeZone};
+use leptos::ev::MouseEvent;
+use leptos::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+const EXPIRY_CHOICES: &[(&str, Option<i64>)] = &[
+    ("Never", None),
+    ("1 day", Some(86_400)),
+    ("7 days", Some(7 * 86_400)),
+    ("30 days", Some(30 * 86_400)),
+];
+
+fn copy_text(text: String) {
+    if let Some(nav) = web_sys::window().map(|w| w.navigator()) {
+        let clipboard = nav.clipboard();
+        spawn_local(async move {
+            let _ = wasm_bindgen_futures::JsFuture::from(clipboard.write_text(&text)).await;
+        });
+    }
+}
+
+fn share_meta(share: &Share) -> String {
+    let audience = if share.require_auth {
+        "Signed-in users"
+    } else {
+        "Anyone with the link"
+    };
+    let expiry = match share.expires_at {
+        _ if share.is_expired() => "expired".to_string(),
+        Some(at) => Local
+            .timestamp_opt(at, 0)
+            .single()
+            .map(|t| format!("expires {}", t.format("%b %-d, %H:%M")))
+            .unwrap_or_default(),
+        None => "never expires".to_string(),
+    };
+    format!("{audience} · {expiry}")
+}
+
+/// One link with copy and revoke buttons; also used by the links page.
+#[component]
+pub fn ShareRow(share: Share, on_revoke: Callback<String>) -> impl IntoView {
+    let (copied, set_copied) = create_signal(false);
+    let url = share.url();
+    let token = share.token.clone();
+    let meta = share_meta(&share);
+    view! {
+        <div class="share-row" class:expired=share.is_expired()>
+            <div class="share-row-main">
+                <a class="share-row-title" href=url.clone() target="_blank">{share.title.clone()}</a>
+                <span class="share-row-meta">{meta}</span>
+            </div>
+            <button
+                class="icon-btn"
+                title="Copy link"
+                on:click=move |_| {
+                    copy_text(url.clone());
+                    set_copied.set(true);
+                }
+            >
+                {move || if copied.get() {
+                    view! { <IconCheck/> }.into_view()
+                } else {
+                    view! { <IconCopy/> }.into_view()
+                }}
+            </button>
+            <button
+                class="icon-btn"
+                title="Revoke link"
+                on:click=move |_| on_revoke.call(token.clone())
+            >
+                <IconTrash/>
+            </button>
+        </div>
+    }
+}
+
+/// Header button that publishes a read-only snapshot of the open
+/// conversation and lists (and revokes) its existing links.
+#[component]
+pub fn ShareMenu(
+    conversation_id: ReadSignal<String>,
+    title: Signal<String>,
+    snapshot: Callback<(), ShareSnapshot>,
+    disabled: Signal<bool>,
+) -> impl IntoView {
+    let (open, set_open) = create_signal(false);
+    let require_auth = create_rw_signal(true);
+    let expiry = create_rw_signal(0usize);
+    let shares = create_rw_signal(Vec::<Share>::new());
+    let (busy, set_busy) = create_signal(false);
+    let (error, set_error) = create_signal(None::<String>);
+
+    let on_api_error = move |e: ApiError| match e {
+        ApiError::Unauthorized => redirect_to_login(),
+        e => set_error.set(Some(e.to_string())),
+    };
+
+    let toggle = move |e: MouseEvent| {
+        e.stop_propagation();
+        if open.get_untracked() {
+            set_open.set(false);
+            return;
+        }
+        set_open.set(true);
+        set_error.set(None);
+        spawn_local(async move {
+            match ApiClient::new().list_shares().await {
+                Ok(list) => shares.set(list),
+                Err(e) => on_api_error(e),
+            }
+        });
+    };
+
+    create_effect(move |_| {
+        if !open.get() {
+            return;
+        }
+        let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+            if let Some(target) = e.target() {
+                if let Ok(el) = target.dyn_into::<web_sys::Element>() {
+                    if el.closest(".share-popover-anchor").ok().flatten().is_none() {
+                        set_open.set(false);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+            let _ =
+                doc.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    });
+
+    let create = move |_| {
+        set_busy.set(true);
+        set_error.set(None);
+        let req = CreateShareRequest {
+            conversation_id: conversation_id.get_untracked(),
+            title: title.get_untracked(),
+            snapshot: snapshot.call(()),
+            require_auth: require_auth.get_untracked(),
+            expires_in_secs: EXPIRY_CHOICES
+                .get(expiry.get_untracked())
+                .and_then(|(_, secs)| *secs),
+        };
+        spawn_local(async move {
+            match ApiClient::new().create_share(&req).await {
+                Ok(share) => {
+                    copy_text(share.url());
+                    shares.update(|list| list.insert(0, share));
+                }
+                Err(e) => on_api_error(e),
+            }
+            set_busy.set(false);
+        });
+    };
+
+    let on_revoke = Callback::new(move |token: String| {
+        spawn_local(async move {
+            match ApiClient::new().delete_share(&token).await {
+                Ok(()) => shares.update(|list| list.retain(|s| s.token != token)),
+                Err(e) => on_api_error(e),
+            }
+        });
+    });
+
+    view! {
+        <div class="popover-anchor share-popover-anchor">
+            <button
+                class="icon-btn"
+                title="Share a read-only link"
+                disabled=move || disabled.get()
+                on:click=toggle
+            >
+                <IconShare/>
+            </button>
+            {move || if open.get() {
+                view! {
+                    <div class="popover share-popover" on:click=|e| e.stop_propagation()>
+                        <div class="share-options">
+                            <label class="share-check">
+                                <input
+                                    type="checkbox"
+                                    prop:checked=move || require_auth.get()
+                                    on:change=move |ev| require_auth.set(event_target_checked(&ev))
+                                />
+                                "Only signed-in users"
+                            </label>
+                            <label class="share-expiry">
+                                "Expires"
+                                <select on:change=move |ev| {
+                                    expiry.set(event_target_value(&ev).parse().unwrap_or(0))
+                                }>
+                                    {EXPIRY_CHOICES.iter().enumerate().map(|(i, (label, _))| view! {
+                                        <option value=i.to_string() selected=move || expiry.get() == i>
+                                            {*label}
+                                        </option>
+                                    }).collect_view()}
+                                </select>
+                            </label>
+                            <button
+                                class="share-create"
+                                disabled=move || busy.get()
+                                on:click=create
+                            >
+                                "Create & copy link"
+                            </button>
+                        </div>
+                        {move || error.get().map(|e| view! { <div class="error-message">{e}</div> })}
+                        <div class="share-list">
+                            {move || {
+                                let id = conversation_id.get();
+                                let links = shares
+                                    .get()
+                                    .into_iter()
+                                    .filter(|s| s.conversation_id == id)
+                                    .collect::<Vec<_>>();
+                                if links.is_empty() {
+                                    view! {
+                                        <div class="share-empty">"No links to this conversation yet."</div>
+                                    }.into_view()
+                                } else {
+                                    links.into_iter().map(|share| view! {
+                                        <ShareRow share=share on_revoke=on_revoke/>
+                                    }).collect_view()
+                                }
+                            }}
+                        </div>
+                        <a class="share-manage" href="/shares">"Manage all links"</a>
+                    </div>
+                }.into_view()
+            } else {
+                view! { <span></span> }.into_view()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,98 @@
+use crate::api::{ApiClient, ApiError, SharedConversation};
+use crate::components::auth::redirect_to_login_then;
+use crate::notebook::cell::{CellContext, CellView};
+use leptos::*;
+use leptos_router::use_params_map;
+
+#[derive(Clone)]
+enum Status {
+    Loading,
+    Ready(SharedConversation),
+    NeedsLogin,
+    Gone,
+    Failed(String),
+}
+
+/// Read-only rendering of a shared conversation at `/s/:token`. Needs no
+/// session unless the owner limited the link to signed-in users.
+#[component]
+pub fn SharedConversationPage() -> impl IntoView {
+    let token = use_params_map().with_untracked(|p| p.get("token").cloned().unwrap_or_default());
+    let (status, set_status) = create_signal(Status::Loading);
+
+    spawn_local(async move {
+        let next = match ApiClient::new().get_shared(&token).await {
+            Ok(shared) => Status::Ready(shared),
+            Err(ApiError::Unauthorized) => Status::NeedsLogin,
+            Err(ApiError::Status(404)) => Status::Gone,
+            Err(e) => Status::Failed(e.to_string()),
+        };
+        set_status.set(next);
+    });
+
+    let login = move |_| {
+        let path = web_sys::window()
+            .and_then(|w| w.location().pathname().ok())
+            .unwrap_or_default();
+        redirect_to_login_then(&path);
+    };
+
+    view! {
+        <div class="shared-page">
+            {move || match status.get() {
+                Status::Loading => view! {
+                    <div class="auth-container"><p>"Loading…"</p></div>
+                }.into_view(),
+                Status::NeedsLogin => view! {
+                    <div class="auth-container">
+                        <p>"This conversation is only shared with signed-in users."</p>
+                        <button on:click=login>"Sign in to view"</button>
+                    </div>
+                }.into_view(),
+                Status::Gone => view! {
+                    <div class="auth-container">
+                        <p>"This link has expired or been revoked."</p>
+                    </div>
+                }.into_view(),
+                Status::Failed(e) => view! {
+                    <div class="auth-container"><div class="error-message">{e}</div></div>
+                }.into_view(),
+                Status::Ready(shared) => {
+                    let snapshot = shared.snapshot;
+                    let (notebook, _) = create_signal(snapshot.notebook.clone());
+                    let ctx = CellContext {
+                        user_initial: shared
+                            .owner
+                            .chars()
+                            .next()
+                            .map(|c| c.to_ascii_uppercase().to_string())
+                            .unwrap_or_else(|| "U".into()),
+                        persona_name: snapshot.persona.clone(),
+                        run_languages: Signal::derive(Vec::new),
+                        on_run: None,
+                        on_edit: None,
+                        on_switch: None,
+                        regenerate: None,
+                        on_op: None,
+                        on_replay: None,
+                        on_feedback: None,
+                        busy: Signal::derive(|| false),
+                    };
+                    view! {
+                        <div class="shared-head">
+                            <h2>{shared.title}</h2>
+                            <span class="chat-title-meta">
+                                {format!("Shared by {} · {} · read-only", shared.owner, snapshot.model)}
+                            </span>
+                        </div>
+                        <div class="thread">
+                            {snapshot.notebook.active_path().into_iter().map(|cell| view! {
+                                <CellView cell=cell.clone() ctx=ctx.clone() notebook=notebook/>
+                            }).collect_view()}
+                        </div>
+                    }.into_view()
+                }
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,56 @@
+use crate::api::{ApiClient, ApiError, Share};
+use crate::components::auth::redirect_to_login;
+use crate::components::share_menu::ShareRow;
+use leptos::*;
+
+/// Every link the user has shared, newest first, for revoking.
+#[component]
+pub fn SharesPage() -> impl IntoView {
+    let shares = create_rw_signal(None::<Vec<Share>>);
+    let (error, set_error) = create_signal(None::<String>);
+
+    spawn_local(async move {
+        match ApiClient::new().list_shares().await {
+            Ok(list) => shares.set(Some(list)),
+            Err(ApiError::Unauthorized) => redirect_to_login(),
+            Err(e) => set_error.set(Some(e.to_string())),
+        }
+    });
+
+    let on_revoke = Callback::new(move |token: String| {
+        spawn_local(async move {
+            match ApiClient::new().delete_share(&token).await {
+                Ok(()) => shares.update(|list| {
+                    if let Some(list) = list {
+                        list.retain(|s| s.token != token);
+                    }
+                }),
+                Err(ApiError::Unauthorized) => redirect_to_login(),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    });
+
+    view! {
+        <div class="admin-page">
+            <div class="admin-head">
+                <h2>"Shared links"</h2>
+                <a href="/">"Back to chat"</a>
+            </div>
+            {move || error.get().map(|e| view! { <div class="error-message">{e}</div> })}
+            {move || shares.get().map(|list| view! {
+                <section class="admin-section">
+                    {if list.is_empty() {
+                        view! {
+                            <div class="share-empty">"You haven't shared any conversations."</div>
+                        }.into_view()
+                    } else {
+                        list.into_iter().map(|share| view! {
+                            <ShareRow share=share on_revoke=on_revoke/>
+                        }).collect_view()
+                    }}
+                </section>
+            })}
+        </div>
+    }
+}
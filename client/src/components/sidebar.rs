@@ -1,4 +1,7 @@
 use crate::components::icons::*;
+use crate::notebook::CellId;
+use crate::search_index::SearchHit;
+use crate::semantic_index::SemanticHit;
 use crate::storage::ConversationRef;
 use chrono::{DateTime, Local, TimeZone, Utc};
 use leptos::*;
@@ -46,21 +49,48 @@ fn short_time(dt: &DateTime<Utc>) -> String {
     }
 }
 
+/// The snippet as text with `<mark>` around each highlighted range.
+fn highlighted_snippet(hit: &SearchHit) -> View {
+    let mut parts = Vec::new();
+    let mut at = 0;
+    for &(start, end) in &hit.highlights {
+        parts.push(hit.snippet[at..start].to_string().into_view());
+        parts.push(view! { <mark>{hit.snippet[start..end].to_string()}</mark> }.into_view());
+        at = end;
+    }
+    parts.push(hit.snippet[at..].to_string().into_view());
+    parts.into_iter().collect_view()
+}
+
 #[component]
 pub fn Sidebar(
     conversations: Signal<Vec<ConversationRef>>,
     current_id: ReadSignal<String>,
     search: RwSignal<String>,
+    message_hits: Signal<Vec<SearchHit>>,
+    on_open_hit: Callback<SearchHit>,
+    /// Search by meaning: Enter embeds the query instead of filtering.
+    semantic: RwSignal<bool>,
+    semantic_hits: Signal<Vec<SemanticHit>>,
+    /// "Searching…" or why the last semantic search failed.
+    semantic_status: Signal<Option<String>>,
+    on_semantic_search: Callback<()>,
+    on_open_semantic: Callback<(String, CellId)>,
     theme: RwSignal<String>,
     provider_online: Signal<bool>,
     user_name: Signal<String>,
+    is_admin: Signal<bool>,
     on_new: Callback<()>,
     on_select: Callback<String>,
     on_delete: Callback<String>,
     on_logout: Callback<()>,
 ) -> impl IntoView {
     let filtered = move || {
-        let q = search.get().trim().to_lowercase();
+        let q = if semantic.get() {
+            String::new()
+        } else {
+            search.get().trim().to_lowercase()
+        };
         conversations
             .get()
             .into_iter()
@@ -127,13 +157,70 @@ pub fn Sidebar(
                 <IconSearch/>
                 <input
                     type="text"
-                    placeholder="Search chats"
+                    placeholder=move || if semantic.get() {
+                        "Describe it, then Enter"
+                    } else {
+                        "Search chats"
+                    }
                     prop:value=move || search.get()
                     on:input=move |ev| search.set(event_target_value(&ev))
+                    on:keydown=move |ev| {
+                        if semantic.get_untracked() && ev.key() == "Enter" {
+                            on_semantic_search.call(());
+                        }
+                    }
                 />
+                <button
+                    class="search-mode"
+                    class:active=move || semantic.get()
+                    title=move || if semantic.get() {
+                        "Searching by meaning; click to match words"
+                    } else {
+                        "Search by meaning"
+                    }
+                    on:click=move |_| semantic.update(|s| *s = !*s)
+                >
+                    <IconSparkles/>
+                </button>
             </div>
 
             <div class="conv-list">
+                {move || semantic.get().then(|| {
+                    let hits = semantic_hits.get();
+                    view! {
+                        <>
+                            {semantic_status.get().map(|s| view! {
+                                <div class="semantic-status">{s}</div>
+                            })}
+                            {(!hits.is_empty()).then(|| view! {
+                                <div class="conv-section-label">"By meaning"</div>
+                            })}
+                            {hits.into_iter().map(|hit| {
+                                view! {
+                                    <div class="semantic-hit">
+                                        <div class="search-hit-title">
+                                            <span>{hit.title.clone()}</span>
+                                            <span class="semantic-score">
+                                                {format!("{:.0}%", hit.score * 100.0)}
+                                            </span>
+                                        </div>
+                                        {hit.cells.into_iter().map(|c| {
+                                            let id = hit.conversation_id.clone();
+                                            view! {
+                                                <div
+                                                    class="search-hit-row"
+                                                    on:click=move |_| on_open_semantic.call((id.clone(), c.cell))
+                                                >
+                                                    <div class="search-hit-snippet">{c.snippet}</div>
+                                                </div>
+                                            }
+                                        }).collect_view()}
+                                    </div>
+                                }
+                            }).collect_view()}
+                        </>
+                    }
+                })}
                 {move || grouped().into_iter().filter(|(_, items)| !items.is_empty()).map(|(label, items)| {
                     view! {
                         <>
@@ -145,11 +232,17 @@ pub fn Sidebar(
                                 let is_active = create_memo(move |_| current_id.get() == cid);
                                 let title = c.title.clone();
                                 let meta = short_time(&c.modified_at);
+                                let server_only = c.server_only;
                                 view! {
-                                    <div class="conv" class:active=move || is_active.get()>
+                                    <div
+                                        class="conv"
+                                        class:active=move || is_active.get()
+                                        class:server-only=server_only
+                                    >
                                         <span class="conv-dot"></span>
                                         <span
                                             class="conv-title"
+                                            title=server_only.then_some("Only on the server; opening it fetches it")
                                             on:click=move |_| on_select.call(cid_sel.clone())
                                         >{title}</span>
                                         <span class="conv-meta">{meta}</span>
@@ -169,6 +262,24 @@ pub fn Sidebar(
                         </>
                     }
                 }).collect_view()}
+                {move || {
+                    let hits = message_hits.get();
+                    (!hits.is_empty()).then(|| view! {
+                        <>
+                            <div class="conv-section-label">"In messages"</div>
+                            {hits.into_iter().map(|hit| {
+                                let title = hit.title.clone();
+                                let snippet = highlighted_snippet(&hit);
+                                view! {
+                                    <div class="search-hit-row" on:click=move |_| on_open_hit.call(hit.clone())>
+                                        <div class="search-hit-title">{title}</div>
+                                        <div class="search-hit-snippet">{snippet}</div>
+                                    </div>
+                                }
+                            }).collect_view()}
+                        </>
+                    })
+                }}
             </div>
 
             <div class="sidebar-foot">
@@ -191,6 +302,14 @@ pub fn Sidebar(
                         </div>
                     </div>
                 </div>
+                <a class="icon-btn" href="/jobs" title="Batch jobs">
+                    <IconList/>
+                </a>
+                {move || is_admin.get().then(|| view! {
+                    <a class="icon-btn" href="/admin" title="Server health">
+                        <IconActivity/>
+                    </a>
+                })}
                 <button class="icon-btn" title="Sign out" on:click=move |_| on_logout.call(())>
                     <IconLogout/>
                 </button>
@@ -0,0 +1,103 @@
+use crate::components::context_manager::ContextManager;
+use crate::components::icons::*;
+use leptos::ev::MouseEvent;
+use leptos::*;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+
+/// The summaries standing in for compressed turns, next to the context
+/// gauge. Each one is editable; the model sees the edited text.
+#[component]
+pub fn SummaryPopover(context_manager: ContextManager) -> impl IntoView {
+    let (open, set_open) = create_signal(false);
+
+    let toggle = move |e: MouseEvent| {
+        e.stop_propagation();
+        set_open.update(|o| *o = !*o);
+    };
+
+    create_effect(move |_| {
+        if !open.get() {
+            return;
+        }
+        let closure = Closure::wrap(Box::new(move |e: web_sys::MouseEvent| {
+            if let Some(target) = e.target() {
+                if let Ok(el) = target.dyn_into::<web_sys::Element>() {
+                    if el
+                        .closest(".summary-popover-anchor")
+                        .ok()
+                        .flatten()
+                        .is_none()
+                    {
+                        set_open.set(false);
+                    }
+                }
+            }
+        }) as Box<dyn FnMut(_)>);
+        if let Some(doc) = web_sys::window().and_then(|w| w.document()) {
+            let _ =
+                doc.add_event_listener_with_callback("mousedown", closure.as_ref().unchecked_ref());
+        }
+        closure.forget();
+    });
+
+    let cm_count = context_manager.clone();
+    let count = create_memo(move |_| cm_count.summaries().len());
+    let cm_pending = context_manager.clone();
+    let pending = create_memo(move |_| cm_pending.pending_summaries());
+
+    move || {
+        (count.get() > 0).then(|| {
+            let context_manager = context_manager.clone();
+            view! {
+                <div class="popover-anchor summary-popover-anchor">
+                    <button
+                        class="summary-toggle"
+                        title="Summaries of compressed turns"
+                        on:click=toggle
+                    >
+                        <IconCompress/>
+                        {move || if pending.get() > 0 {
+                            "summarizing…".to_string()
+                        } else if count.get() == 1 {
+                            "1 summary".to_string()
+                        } else {
+                            format!("{} summaries", count.get())
+                        }}
+                    </button>
+                    {move || open.get().then(|| {
+                        let context_manager = context_manager.clone();
+                        view! {
+                            <div class="popover summary-popover" on:click=|e| e.stop_propagation()>
+                                <div class="summary-note">
+                                    "Older turns were replaced by these summaries. Edits apply to \
+                                     the next message."
+                                </div>
+                                {(0..count.get()).map(|i| {
+                                    let cm_value = context_manager.clone();
+                                    let cm_set = context_manager.clone();
+                                    view! {
+                                        <label class="sampling-field">
+                                            <span class="sampling-label">
+                                                {format!("summary {}", i + 1)}
+                                            </span>
+                                            <textarea
+                                                rows="5"
+                                                prop:value=move || {
+                                                    cm_value.summaries().get(i).cloned().unwrap_or_default()
+                                                }
+                                                on:change=move |ev| {
+                                                    cm_set.set_summary(i, event_target_value(&ev))
+                                                }
+                                            />
+                                        </label>
+                                    }
+                                }).collect_view()}
+                            </div>
+                        }
+                    })}
+                </div>
+            }
+        })
+    }
+}
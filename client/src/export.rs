@@ -0,0 +1,459 @@
+use crate::api::{ChatMessage, SystemPrompt};
+use crate::notebook::{Cell, CellContent, Provenance, Rating};
+use crate::storage::StoredConversation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+/// Identifies our JSON exports; bump `EXPORT_VERSION` whenever
+/// `StoredConversation` changes in a way older readers can't ignore.
+pub const EXPORT_SCHEMA: &str = "gamecode.conversations";
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+    /// Rated replies as a Hugging Face chat dataset.
+    FineTune,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [Self::Markdown, Self::Json, Self::Html, Self::FineTune];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Json => "JSON",
+            Self::Html => "HTML",
+            Self::FineTune => "Rated JSONL",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+            Self::FineTune => "jsonl",
+        }
+    }
+
+    fn mime(self) -> &'static str {
+        match self {
+            Self::Markdown => "text/markdown;charset=utf-8",
+            Self::Json => "application/json",
+            Self::Html => "text/html;charset=utf-8",
+            Self::FineTune => "application/jsonl",
+        }
+    }
+}
+
+/// The JSON export: full conversations, one or many, under a versioned
+/// envelope so they can be imported again.
+#[derive(Serialize, Deserialize)]
+pub struct ExportBundle {
+    pub schema: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub conversations: Vec<StoredConversation>,
+}
+
+/// `prompts` supply the persona prompt for rated replies that didn't
+/// record their own.
+pub fn render(
+    format: ExportFormat,
+    conversations: &[StoredConversation],
+    prompts: &[SystemPrompt],
+) -> String {
+    match format {
+        ExportFormat::Markdown => conversations
+            .iter()
+            .map(to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n---\n\n"),
+        ExportFormat::Json => {
+            let bundle = ExportBundle {
+                schema: EXPORT_SCHEMA.to_string(),
+                version: EXPORT_VERSION,
+                exported_at: Utc::now(),
+                conversations: conversations.to_vec(),
+            };
+            serde_json::to_string_pretty(&bundle).unwrap_or_default()
+        }
+        ExportFormat::Html => to_html(conversations),
+        ExportFormat::FineTune => to_finetune(conversations, prompts),
+    }
+}
+
+/// One line of the fine-tuning export: the conversation in the chat
+/// template's `messages` form, ending with the rated reply.
+#[derive(Serialize)]
+struct RatedExchange<'a> {
+    messages: Vec<ChatMessage>,
+    /// Thumbs up. Filter on it for supervised fine-tuning; keep both for
+    /// preference methods such as KTO.
+    label: bool,
+    comment: &'a str,
+    model: Option<&'a str>,
+    persona: Option<&'a str>,
+}
+
+/// Every rated reply, on any branch, as JSONL ready for `datasets`'
+/// `load_dataset("json", ...)`. Each record starts with the persona's
+/// system prompt as the model saw it, then what the model was sent, then
+/// the reply.
+pub fn to_finetune(conversations: &[StoredConversation], prompts: &[SystemPrompt]) -> String {
+    let mut out = String::new();
+    for conv in conversations {
+        let nb = &conv.notebook;
+        for cell in &nb.cells {
+            let Some(rating) = cell.metadata.feedback.as_ref().and_then(|f| f.rating) else {
+                continue;
+            };
+            let Some(reply) = cell.chat_message().filter(|m| m.role == "assistant") else {
+                continue;
+            };
+            let provenance = cell.metadata.provenance.as_ref();
+            let persona = provenance.and_then(|p| p.persona.as_deref()).or((!conv
+                .metadata
+                .persona
+                .is_empty())
+            .then_some(conv.metadata.persona.as_str()));
+            let system_prompt = match provenance {
+                Some(p) => sent_system_prompt(p, cell.timestamp),
+                None => persona
+                    .and_then(|name| prompts.iter().find(|p| p.name == name))
+                    .map(|p| {
+                        let variables = p.resolve_variables(&conv.metadata.variables);
+                        fill_template(&p.prompt, |name| match name {
+                            "model" => cell.metadata.model.clone(),
+                            "date" => Some(cell.timestamp.date_naive().to_string()),
+                            _ => variables.get(name).cloned(),
+                        })
+                    }),
+            };
+            let mut messages = Vec::new();
+            if let Some(prompt) = system_prompt.filter(|p| !p.trim().is_empty()) {
+                messages.push(ChatMessage {
+                    role: "system".into(),
+                    content: prompt,
+                });
+            }
+            messages.extend(nb.sent_messages(cell.id));
+            messages.push(reply);
+            let record = RatedExchange {
+                messages,
+                label: rating == Rating::Up,
+                comment: cell
+                    .metadata
+                    .feedback
+                    .as_ref()
+                    .map_or("", |f| f.comment.as_str()),
+                model: cell.metadata.model.as_deref(),
+                persona,
+            };
+            out.push_str(&serde_json::to_string(&record).unwrap_or_default());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// The system prompt a reply was sent: as the server rendered it, or for
+/// records made before that was kept, the template with its variables,
+/// model and the reply's date filled in.
+pub fn sent_system_prompt(provenance: &Provenance, sent: DateTime<Utc>) -> Option<String> {
+    if let Some(rendered) = &provenance.rendered_system_prompt {
+        return Some(rendered.clone());
+    }
+    let template = provenance.system_prompt.as_deref()?;
+    Some(fill_template(template, |name| match name {
+        "model" => Some(provenance.model.clone()),
+        "date" => Some(sent.date_naive().to_string()),
+        _ => provenance.variables.get(name).cloned(),
+    }))
+}
+
+/// Replace each `{{name}}` that `value` knows, as the server's renderer
+/// does; unknown names are left in place.
+fn fill_template(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        match value(after[..end].trim()) {
+            Some(v) => out.push_str(&v),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `gamecode-<title>-<date>.<ext>`, with the title reduced to safe characters.
+pub fn file_name(title: &str, format: ExportFormat) -> String {
+    let mut slug = String::new();
+    for c in title.chars().flat_map(char::to_lowercase) {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-').chars().take(48).collect::<String>();
+    let slug = if slug.is_empty() {
+        "chat".to_string()
+    } else {
+        slug
+    };
+    format!(
+        "gamecode-{}-{}.{}",
+        slug,
+        Utc::now().format("%Y%m%d"),
+        format.extension()
+    )
+}
+
+/// Hand `contents` to the browser as a file download.
+pub fn download(name: &str, format: ExportFormat, contents: &str) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(contents));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(format.mime());
+    let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob)?;
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("No document")?;
+    let link: web_sys::HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    link.set_href(&url);
+    link.set_download(name);
+    link.click();
+    web_sys::Url::revoke_object_url(&url)
+}
+
+/// A fence long enough that backticks inside `source` can't close it.
+fn fence(language: &str, source: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in source.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let ticks = "`".repeat((longest + 1).max(3));
+    format!(
+        "{ticks}{language}\n{}\n{ticks}\n\n",
+        source.trim_end_matches('\n')
+    )
+}
+
+fn model_line(conv: &StoredConversation) -> String {
+    let meta = &conv.metadata;
+    let mut parts = Vec::new();
+    if !meta.persona.is_empty() {
+        parts.push(format!("**Persona:** {}", meta.persona));
+    }
+    if !meta.model.is_empty() {
+        parts.push(format!("**Model:** {} ({})", meta.model, meta.provider));
+    }
+    parts.push(format!(
+        "**Created:** {}",
+        meta.created_at.format("%Y-%m-%d %H:%M UTC")
+    ));
+    parts.join(" · ")
+}
+
+fn author(cell: &Cell, conv: &StoredConversation) -> String {
+    let persona = if conv.metadata.persona.is_empty() {
+        "Assistant"
+    } else {
+        conv.metadata.persona.as_str()
+    };
+    match &cell.metadata.model {
+        Some(model) => format!("{persona} · {model}"),
+        None => persona.to_string(),
+    }
+}
+
+fn run_markdown(result: &crate::notebook::CodeRun) -> String {
+    let status = match (result.timed_out, result.exit_code) {
+        (true, _) => "timed out".to_string(),
+        (false, Some(code)) => format!("exit {code}"),
+        (false, None) => "killed".to_string(),
+    };
+    let mut out = format!("**Output** ({status})\n\n");
+    if !result.stdout.is_empty() {
+        out.push_str(&fence("text", &result.stdout));
+    }
+    if !result.stderr.is_empty() {
+        out.push_str(&fence("stderr", &result.stderr));
+    }
+    out
+}
+
+fn cell_markdown(cell: &Cell, conv: &StoredConversation) -> Option<String> {
+    let text = match &cell.content {
+        CellContent::UserInput { text } => format!("### You\n\n{}\n\n", text.trim_end()),
+        CellContent::TextResponse { text, .. } => {
+            format!("### {}\n\n{}\n\n", author(cell, conv), text.trim_end())
+        }
+        CellContent::Code {
+            language, source, ..
+        } => fence(language, source),
+        CellContent::Diagram { format, source, .. } => {
+            fence(&format.to_string().to_lowercase(), source)
+        }
+        CellContent::CodeOutput {
+            result: Some(result),
+            ..
+        } => run_markdown(result),
+        CellContent::Image { url, alt, .. } => format!("![{alt}]({url})\n\n"),
+        CellContent::Table { headers, rows } => {
+            let row = |cells: &[String]| format!("| {} |\n", cells.join(" | "));
+            let mut out = row(headers);
+            out.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+            for r in rows {
+                out.push_str(&row(r));
+            }
+            out.push('\n');
+            out
+        }
+        CellContent::Chart { data, .. } => fence(
+            "json",
+            &serde_json::to_string_pretty(data).unwrap_or_default(),
+        ),
+        CellContent::Error { message, details } => {
+            let mut out = format!("> **Error:** {message}\n");
+            if let Some(d) = details {
+                out.push_str(&format!("> {d}\n"));
+            }
+            out.push('\n');
+            out
+        }
+        CellContent::CodeOutput { result: None, .. } | CellContent::Loading { .. } => return None,
+    };
+    Some(text)
+}
+
+pub fn to_markdown(conv: &StoredConversation) -> String {
+    let mut out = format!("# {}\n\n{}\n\n", conv.metadata.title, model_line(conv));
+    for cell in conv.notebook.active_path() {
+        if let Some(text) = cell_markdown(cell, conv) {
+            out.push_str(&text);
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = "
+body { font: 15px/1.6 system-ui, sans-serif; max-width: 820px; margin: 40px auto; padding: 0 20px; color: #1d1d1f; }
+article + article { border-top: 2px solid #ddd; margin-top: 48px; padding-top: 24px; }
+h1 { font-size: 24px; margin-bottom: 4px; }
+.meta { color: #777; font-size: 13px; }
+.cell { margin: 20px 0; }
+.who { font-size: 12px; font-weight: 600; color: #555; text-transform: uppercase; letter-spacing: 0.04em; }
+.user .body { white-space: pre-wrap; background: #f4f4f6; border-radius: 8px; padding: 10px 14px; }
+.code-block { margin: 12px 0; border-radius: 8px; overflow: hidden; }
+.code-head { background: #1f2430; color: #a7adba; font: 11px ui-monospace, monospace; padding: 4px 12px; }
+.code-block pre { margin: 0; padding: 12px; overflow-x: auto; font: 13px/1.5 ui-monospace, monospace; }
+.error { color: #b3261e; }
+table { border-collapse: collapse; } td, th { border: 1px solid #ccc; padding: 4px 8px; }
+";
+
+fn cell_html(cell: &Cell, conv: &StoredConversation) -> Option<String> {
+    use html_escape::encode_text;
+    let (class, who, body) = match &cell.content {
+        CellContent::UserInput { text } => (
+            "user",
+            "You".to_string(),
+            format!("<div class=\"body\">{}</div>", encode_text(text)),
+        ),
+        CellContent::TextResponse { text, .. } => (
+            "assistant",
+            author(cell, conv),
+            crate::markdown::render_html(text),
+        ),
+        CellContent::Error { message, details } => (
+            "error",
+            "Error".to_string(),
+            format!(
+                "<p>{}</p>{}",
+                encode_text(message),
+                details
+                    .as_ref()
+                    .map(|d| format!("<p>{}</p>", encode_text(d)))
+                    .unwrap_or_default()
+            ),
+        ),
+        CellContent::Table { headers, rows } => {
+            let row = |tag: &str, cells: &[String]| {
+                let cells = cells
+                    .iter()
+                    .map(|c| format!("<{tag}>{}</{tag}>", encode_text(c)))
+                    .collect::<String>();
+                format!("<tr>{cells}</tr>")
+            };
+            let body = rows.iter().map(|r| row("td", r)).collect::<String>();
+            (
+                "table",
+                String::new(),
+                format!("<table>{}{}</table>", row("th", headers), body),
+            )
+        }
+        // Everything else is code-like; render its Markdown form so it gets
+        // the same highlighting.
+        _ => (
+            "output",
+            String::new(),
+            crate::markdown::render_html(&cell_markdown(cell, conv)?),
+        ),
+    };
+    let who = if who.is_empty() {
+        String::new()
+    } else {
+        format!("<div class=\"who\">{}</div>", encode_text(&who))
+    };
+    Some(format!(
+        "<section class=\"cell {class}\">{who}{body}</section>\n"
+    ))
+}
+
+pub fn to_html(conversations: &[StoredConversation]) -> String {
+    use html_escape::encode_text;
+    let title = match conversations {
+        [one] => one.metadata.title.clone(),
+        _ => format!("{} conversations", conversations.len()),
+    };
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{}</title><style>{}</style></head><body>\n",
+        encode_text(&title),
+        HTML_STYLE
+    );
+    for conv in conversations {
+        // The Markdown header line, minus the emphasis markers.
+        let meta = model_line(conv).replace("**", "");
+        out.push_str(&format!(
+            "<article><h1>{}</h1><p class=\"meta\">{}</p>\n",
+            encode_text(&conv.metadata.title),
+            encode_text(&meta)
+        ));
+        for cell in conv.notebook.active_path() {
+            if let Some(html) = cell_html(cell, conv) {
+                out.push_str(&html);
+            }
+        }
+        out.push_str("</article>\n");
+    }
+    out.push_str("</body></html>\n");
+    out
+}
@@ -0,0 +1,233 @@
+use crate::semantic_index::ExchangeEmbedding;
+use crate::simple_storage::SimpleStorage;
+use crate::storage::{ConversationRef, ConversationStore, StoredConversation};
+use js_sys::{Array, Function, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Event, IdbDatabase, IdbObjectStore, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+const DB_NAME: &str = "gamecode_conversations";
+const DB_VERSION: u32 = 2;
+/// Full `StoredConversation` JSON, keyed by conversation id.
+const CONVERSATIONS: &str = "conversations";
+/// `ConversationRef`s, so listing the sidebar doesn't parse every notebook.
+const SUMMARIES: &str = "summaries";
+/// `ExchangeEmbedding`s for semantic search (version 2), keyed by
+/// `ExchangeEmbedding::key`.
+const EMBEDDINGS: &str = "embeddings";
+
+/// Resolve with the request's result once it succeeds.
+fn request_done(request: &IdbRequest) -> JsFuture {
+    let request = request.clone();
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        let req = request.clone();
+        let on_success = Closure::once_into_js(move |_: Event| {
+            let _ = resolve.call1(&JsValue::NULL, &req.result().unwrap_or(JsValue::UNDEFINED));
+        });
+        let req = request.clone();
+        let on_error = Closure::once_into_js(move |_: Event| {
+            let err = req
+                .error()
+                .ok()
+                .flatten()
+                .map(JsValue::from)
+                .unwrap_or_else(|| "IndexedDB request failed".into());
+            let _ = reject.call1(&JsValue::NULL, &err);
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise)
+}
+
+/// Resolve once a write transaction has committed, so callers know the data
+/// is on disk and not just queued.
+fn transaction_done(tx: &IdbTransaction) -> JsFuture {
+    let tx = tx.clone();
+    let promise = Promise::new(&mut |resolve: Function, reject: Function| {
+        let on_complete = Closure::once_into_js(move |_: Event| {
+            let _ = resolve.call0(&JsValue::NULL);
+        });
+        let t = tx.clone();
+        let on_error = Closure::once_into_js(move |_: Event| {
+            let err = t
+                .error()
+                .map(JsValue::from)
+                .unwrap_or_else(|| "IndexedDB transaction aborted".into());
+            let _ = reject.call1(&JsValue::NULL, &err);
+        });
+        tx.set_oncomplete(Some(on_complete.unchecked_ref()));
+        // A failed request aborts the transaction; both paths end up here.
+        tx.set_onabort(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise)
+}
+
+/// Conversations in IndexedDB, which unlike localStorage is bounded by the
+/// origin's storage quota rather than a few megabytes.
+#[derive(Clone)]
+pub struct IdbStorage {
+    db: IdbDatabase,
+}
+
+impl IdbStorage {
+    /// Open (creating on first use) the database, then move over anything an
+    /// older build left in localStorage.
+    pub async fn open() -> Result<Self, JsValue> {
+        let factory = web_sys::window()
+            .ok_or("No window")?
+            .indexed_db()?
+            .ok_or("IndexedDB unavailable")?;
+        let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+        let req = request.clone();
+        let on_upgrade = Closure::once_into_js(move |_: Event| {
+            let Ok(db) = req.result().and_then(|r| r.dyn_into::<IdbDatabase>()) else {
+                return;
+            };
+            // Create whatever the stored version is missing.
+            let existing = db.object_store_names();
+            for name in [CONVERSATIONS, SUMMARIES, EMBEDDINGS] {
+                if !existing.contains(name) {
+                    let _ = db.create_object_store(name);
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+
+        let db: IdbDatabase = request_done(&request).await?.dyn_into()?;
+        let storage = Self { db };
+        storage.migrate_local().await;
+        Ok(storage)
+    }
+
+    /// One-time move of `conversation_*` localStorage entries. Each is removed
+    /// only after it has been written here; unreadable ones are left alone.
+    async fn migrate_local(&self) {
+        let local = SimpleStorage;
+        let Ok(ids) = local.stored_ids() else {
+            return;
+        };
+        let mut moved = 0;
+        for id in ids {
+            match local.load_conversation(&id).await {
+                Ok(Some(conv)) => {
+                    if self.save_conversation(&conv).await.is_ok() {
+                        let _ = local.delete_conversation(&id).await;
+                        // Never pushed: the server may not have it.
+                        crate::sync::mark_dirty(&id);
+                        moved += 1;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    web_sys::console::warn_2(
+                        &format!(
+                            "storage: leaving unreadable conversation {} in localStorage",
+                            id
+                        )
+                        .into(),
+                        &e,
+                    );
+                }
+            }
+        }
+        if moved > 0 {
+            web_sys::console::log_1(
+                &format!("storage: moved {} conversations to IndexedDB", moved).into(),
+            );
+        }
+    }
+
+    pub async fn save_embeddings(&self, records: &[ExchangeEmbedding]) -> Result<(), JsValue> {
+        let tx = self
+            .db
+            .transaction_with_str_and_mode(EMBEDDINGS, IdbTransactionMode::Readwrite)?;
+        let store = tx.object_store(EMBEDDINGS)?;
+        for record in records {
+            let value = serde_wasm_bindgen::to_value(record)?;
+            store.put_with_key(&value, &JsValue::from_str(&record.key()))?;
+        }
+        transaction_done(&tx).await?;
+        Ok(())
+    }
+
+    pub async fn load_embeddings(&self) -> Result<Vec<ExchangeEmbedding>, JsValue> {
+        let request = self.store(EMBEDDINGS)?.get_all()?;
+        let all: Array = request_done(&request).await?.dyn_into()?;
+        Ok(all
+            .iter()
+            .filter_map(|v| serde_wasm_bindgen::from_value(v).ok())
+            .collect())
+    }
+
+    pub async fn delete_embeddings(&self, keys: &[String]) -> Result<(), JsValue> {
+        let tx = self
+            .db
+            .transaction_with_str_and_mode(EMBEDDINGS, IdbTransactionMode::Readwrite)?;
+        let store = tx.object_store(EMBEDDINGS)?;
+        for key in keys {
+            store.delete(&JsValue::from_str(key))?;
+        }
+        transaction_done(&tx).await?;
+        Ok(())
+    }
+
+    fn transaction(&self, mode: IdbTransactionMode) -> Result<IdbTransaction, JsValue> {
+        let stores = Array::of2(&CONVERSATIONS.into(), &SUMMARIES.into());
+        self.db
+            .transaction_with_str_sequence_and_mode(&stores, mode)
+    }
+
+    fn store(&self, name: &str) -> Result<IdbObjectStore, JsValue> {
+        self.db.transaction_with_str(name)?.object_store(name)
+    }
+}
+
+impl ConversationStore for IdbStorage {
+    async fn save_conversation(&self, conversation: &StoredConversation) -> Result<(), JsValue> {
+        let json =
+            serde_json::to_string(conversation).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let summary = serde_wasm_bindgen::to_value(&ConversationRef::from(conversation))?;
+        let key = JsValue::from_str(&conversation.id);
+
+        let tx = self.transaction(IdbTransactionMode::Readwrite)?;
+        tx.object_store(CONVERSATIONS)?
+            .put_with_key(&JsValue::from_str(&json), &key)?;
+        tx.object_store(SUMMARIES)?.put_with_key(&summary, &key)?;
+        transaction_done(&tx).await?;
+        Ok(())
+    }
+
+    async fn load_conversation(&self, id: &str) -> Result<Option<StoredConversation>, JsValue> {
+        let request = self.store(CONVERSATIONS)?.get(&JsValue::from_str(id))?;
+        let value = request_done(&request).await?;
+        let Some(json) = value.as_string() else {
+            return Ok(None);
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    async fn list_conversations(&self, limit: u32) -> Result<Vec<ConversationRef>, JsValue> {
+        let request = self.store(SUMMARIES)?.get_all()?;
+        let all: Array = request_done(&request).await?.dyn_into()?;
+        let mut conversations = all
+            .iter()
+            .filter_map(|v| serde_wasm_bindgen::from_value::<ConversationRef>(v).ok())
+            .collect::<Vec<_>>();
+        conversations.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+        conversations.truncate(limit as usize);
+        Ok(conversations)
+    }
+
+    async fn delete_conversation(&self, id: &str) -> Result<(), JsValue> {
+        let key = JsValue::from_str(id);
+        let tx = self.transaction(IdbTransactionMode::Readwrite)?;
+        tx.object_store(CONVERSATIONS)?.delete(&key)?;
+        tx.object_store(SUMMARIES)?.delete(&key)?;
+        transaction_done(&tx).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+use crate::api::ChatMessage;
+use crate::export::{EXPORT_SCHEMA, EXPORT_VERSION};
+use crate::notebook::{CellContent, Notebook};
+use crate::storage::{
+    estimate_context_tokens, ContextState, ConversationMetadata, StoredConversation,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImportSource {
+    ChatGpt,
+    OpenWebUi,
+    Gamecode,
+}
+
+impl ImportSource {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ChatGpt => "ChatGPT",
+            Self::OpenWebUi => "Open WebUI",
+            Self::Gamecode => "gamecode export",
+        }
+    }
+}
+
+/// Something left out of an import: a whole conversation, or parts of one
+/// that have no cell equivalent (images, tool calls, system messages).
+#[derive(Clone, Debug)]
+pub struct Skipped {
+    pub title: String,
+    pub reason: String,
+}
+
+pub struct ParsedImport {
+    pub source: ImportSource,
+    pub conversations: Vec<StoredConversation>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// Recognise and convert one uploaded file. Errors mean the file as a whole
+/// couldn't be read; per-conversation problems land in `skipped`.
+pub fn parse(text: &str) -> Result<ParsedImport, String> {
+    let value: Value =
+        serde_json::from_str(text).map_err(|e| format!("Not a valid JSON file: {}", e))?;
+
+    if value.get("schema").is_some() {
+        return parse_gamecode(value);
+    }
+    let items = match value {
+        Value::Array(items) => items,
+        // Open WebUI exports a single chat as a bare object.
+        single @ Value::Object(_) if single.get("chat").is_some() => vec![single],
+        _ => return Err(UNRECOGNISED.to_string()),
+    };
+    let source = match items.first() {
+        None => return Err("The file contains no conversations.".to_string()),
+        Some(first) if first.get("mapping").is_some() => ImportSource::ChatGpt,
+        Some(first) if first.get("chat").is_some() => ImportSource::OpenWebUi,
+        Some(_) => return Err(UNRECOGNISED.to_string()),
+    };
+
+    let mut parsed = ParsedImport {
+        source,
+        conversations: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for item in &items {
+        let title = str_field(item, "title")
+            .or_else(|| item.get("chat").and_then(|c| str_field(c, "title")))
+            .unwrap_or("Untitled")
+            .to_string();
+        let converted = match source {
+            ImportSource::ChatGpt => chatgpt_conversation(item),
+            _ => open_webui_conversation(item),
+        };
+        match converted {
+            Ok((conv, dropped)) => {
+                if let Some(reason) = describe_dropped(&dropped) {
+                    parsed.skipped.push(Skipped {
+                        title: title.clone(),
+                        reason,
+                    });
+                }
+                parsed.conversations.push(conv);
+            }
+            Err(reason) => parsed.skipped.push(Skipped { title, reason }),
+        }
+    }
+    Ok(parsed)
+}
+
+const UNRECOGNISED: &str = "Unrecognised file: expected a ChatGPT conversations.json, \
+                            an Open WebUI export or a gamecode JSON export.";
+
+/// Counts of dropped message parts by kind, e.g. `"image" => 2`.
+type Dropped = BTreeMap<&'static str, usize>;
+
+fn describe_dropped(dropped: &Dropped) -> Option<String> {
+    if dropped.is_empty() {
+        return None;
+    }
+    let parts = dropped
+        .iter()
+        .map(|(kind, n)| format!("{} {}{}", n, kind, if *n == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>();
+    Some(format!("Left out {}", parts.join(", ")))
+}
+
+fn str_field<'a>(v: &'a Value, key: &str) -> Option<&'a str> {
+    v.get(key).and_then(Value::as_str)
+}
+
+/// Epoch timestamps in seconds (ChatGPT uses fractional seconds); values
+/// too large to be seconds are taken as milliseconds.
+fn timestamp(v: Option<&Value>) -> Option<DateTime<Utc>> {
+    let n = v?.as_f64()?;
+    let millis = if n > 1e11 { n } else { n * 1000.0 };
+    DateTime::from_timestamp_millis(millis as i64)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    User,
+    Assistant,
+}
+
+struct Turn {
+    role: Role,
+    text: String,
+    at: Option<DateTime<Utc>>,
+    model: Option<String>,
+}
+
+/// Turn a flat message list into a conversation. Consecutive assistant
+/// messages (tool-using replies) are merged into one response cell.
+fn build(
+    id: String,
+    title: String,
+    created: Option<DateTime<Utc>>,
+    modified: Option<DateTime<Utc>>,
+    turns: Vec<Turn>,
+) -> Result<StoredConversation, String> {
+    let mut merged: Vec<Turn> = Vec::new();
+    for turn in turns {
+        match merged.last_mut() {
+            Some(last) if last.role == Role::Assistant && turn.role == Role::Assistant => {
+                last.text.push_str("\n\n");
+                last.text.push_str(&turn.text);
+                last.model = last.model.take().or(turn.model);
+            }
+            _ => merged.push(turn),
+        }
+    }
+    if merged.is_empty() {
+        return Err("No text messages to import".to_string());
+    }
+
+    let created = created
+        .or_else(|| merged.iter().find_map(|t| t.at))
+        .unwrap_or_else(Utc::now);
+    let modified = modified
+        .or_else(|| merged.iter().rev().find_map(|t| t.at))
+        .unwrap_or(created);
+
+    let mut notebook = Notebook::new();
+    let mut messages = Vec::new();
+    for turn in merged {
+        let (content, role) = match turn.role {
+            Role::User => (
+                CellContent::UserInput {
+                    text: turn.text.clone(),
+                },
+                "user",
+            ),
+            Role::Assistant => (
+                CellContent::TextResponse {
+                    text: turn.text.clone(),
+                    streaming: false,
+                },
+                "assistant",
+            ),
+        };
+        let cell_id = notebook.add_cell(content);
+        if let Some(cell) = notebook.get_cell_mut(cell_id) {
+            cell.timestamp = turn.at.unwrap_or(created);
+            cell.metadata.model = turn.model;
+        }
+        messages.push(ChatMessage {
+            role: role.to_string(),
+            content: turn.text,
+        });
+    }
+
+    Ok(StoredConversation {
+        id,
+        notebook,
+        context_state: ContextState {
+            compressed_summaries: Vec::new(),
+            compressed_spans: Vec::new(),
+            overrides: Vec::new(),
+            total_tokens: estimate_context_tokens(&messages),
+            active_messages: messages,
+            compression_count: 0,
+            strategy: Default::default(),
+        },
+        metadata: ConversationMetadata {
+            created_at: created,
+            modified_at: modified,
+            title,
+            // Left empty so opening the chat keeps the current provider and
+            // model; the original model is recorded on each response cell.
+            model: String::new(),
+            provider: String::new(),
+            persona: String::new(),
+            variables: HashMap::new(),
+            job: None,
+        },
+    })
+}
+
+/// ChatGPT's export stores each conversation as a tree of edits and
+/// regenerations; the visible thread is the path from `current_node` up.
+fn chatgpt_conversation(item: &Value) -> Result<(StoredConversation, Dropped), String> {
+    let mapping = item
+        .get("mapping")
+        .and_then(Value::as_object)
+        .ok_or("Missing message tree")?;
+    let mut chain = Vec::new();
+    let mut seen = HashSet::new();
+    let mut next = str_field(item, "current_node");
+    while let Some(node_id) = next {
+        if !seen.insert(node_id) {
+            break;
+        }
+        let Some(node) = mapping.get(node_id) else {
+            break;
+        };
+        chain.push(node);
+        next = str_field(node, "parent");
+    }
+    chain.reverse();
+
+    let mut dropped = Dropped::new();
+    let mut turns = Vec::new();
+    for message in chain.iter().filter_map(|n| n.get("message")) {
+        let meta = message.get("metadata");
+        if meta
+            .and_then(|m| m.get("is_visually_hidden_from_conversation"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        let content = message.get("content");
+        let content_type = content.and_then(|c| str_field(c, "content_type"));
+        let mut text = String::new();
+        match content_type {
+            Some("text") | Some("multimodal_text") => {
+                let parts = content
+                    .and_then(|c| c.get("parts"))
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                for part in parts {
+                    match part.as_str() {
+                        Some(s) => text.push_str(s),
+                        None => *dropped.entry("attachment").or_default() += 1,
+                    }
+                }
+            }
+            Some("code") => {
+                let language = content
+                    .and_then(|c| str_field(c, "language"))
+                    .filter(|l| *l != "unknown")
+                    .unwrap_or("");
+                let source = content.and_then(|c| str_field(c, "text")).unwrap_or("");
+                if !source.trim().is_empty() {
+                    text = format!("```{}\n{}\n```", language, source.trim_end());
+                }
+            }
+            _ => {
+                *dropped.entry("non-text message").or_default() += 1;
+                continue;
+            }
+        }
+        if text.trim().is_empty() {
+            continue;
+        }
+        let role = match message
+            .get("author")
+            .and_then(|a| str_field(a, "role"))
+            .unwrap_or("")
+        {
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "system" => {
+                *dropped.entry("system message").or_default() += 1;
+                continue;
+            }
+            _ => {
+                *dropped.entry("tool output").or_default() += 1;
+                continue;
+            }
+        };
+        turns.push(Turn {
+            role,
+            text,
+            at: timestamp(message.get("create_time")),
+            model: meta
+                .and_then(|m| str_field(m, "model_slug"))
+                .map(str::to_string),
+        });
+    }
+
+    let source_id = str_field(item, "conversation_id").or_else(|| str_field(item, "id"));
+    let conv = build(
+        import_id("chatgpt", source_id),
+        str_field(item, "title").unwrap_or("Untitled").to_string(),
+        timestamp(item.get("create_time")),
+        timestamp(item.get("update_time")),
+        turns,
+    )?;
+    Ok((conv, dropped))
+}
+
+/// Open WebUI keeps the full branch history under `chat.history`, with the
+/// visible thread ending at `currentId`; older exports only have the flat
+/// `chat.messages` list.
+fn open_webui_conversation(item: &Value) -> Result<(StoredConversation, Dropped), String> {
+    let chat = item.get("chat").ok_or("Missing chat body")?;
+    let history = chat.get("history");
+    let tree = history
+        .and_then(|h| h.get("messages"))
+        .and_then(Value::as_object);
+    let mut messages = Vec::new();
+    if let (Some(tree), Some(mut next)) = (tree, history.and_then(|h| str_field(h, "currentId"))) {
+        let mut seen = HashSet::new();
+        while seen.insert(next) {
+            let Some(message) = tree.get(next) else {
+                break;
+            };
+            messages.push(message);
+            match str_field(message, "parentId") {
+                Some(parent) => next = parent,
+                None => break,
+            }
+        }
+        messages.reverse();
+    } else if let Some(list) = chat.get("messages").and_then(Value::as_array) {
+        messages.extend(list.iter());
+    }
+
+    let mut dropped = Dropped::new();
+    let mut turns = Vec::new();
+    for message in messages {
+        for key in ["files", "images"] {
+            let n = message
+                .get(key)
+                .and_then(Value::as_array)
+                .map_or(0, Vec::len);
+            if n > 0 {
+                *dropped.entry("attachment").or_default() += n;
+            }
+        }
+        let text = str_field(message, "content").unwrap_or("");
+        if text.trim().is_empty() {
+            continue;
+        }
+        let role = match str_field(message, "role").unwrap_or("") {
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "system" => {
+                *dropped.entry("system message").or_default() += 1;
+                continue;
+            }
+            _ => {
+                *dropped.entry("tool output").or_default() += 1;
+                continue;
+            }
+        };
+        turns.push(Turn {
+            role,
+            text: text.to_string(),
+            at: timestamp(message.get("timestamp")),
+            model: str_field(message, "model").map(str::to_string),
+        });
+    }
+
+    let title = str_field(item, "title")
+        .or_else(|| str_field(chat, "title"))
+        .unwrap_or("Untitled")
+        .to_string();
+    let conv = build(
+        import_id("openwebui", str_field(item, "id")),
+        title,
+        timestamp(item.get("created_at")),
+        timestamp(item.get("updated_at")),
+        turns,
+    )?;
+    Ok((conv, dropped))
+}
+
+/// Stable ids for imported conversations, so importing the same file twice
+/// is recognised instead of duplicating everything.
+fn import_id(prefix: &str, source_id: Option<&str>) -> String {
+    match source_id.filter(|id| !id.is_empty()) {
+        Some(id) => format!("{}-{}", prefix, id),
+        None => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+fn parse_gamecode(value: Value) -> Result<ParsedImport, String> {
+    if str_field(&value, "schema") != Some(EXPORT_SCHEMA) {
+        return Err(UNRECOGNISED.to_string());
+    }
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > EXPORT_VERSION as u64 {
+        return Err(format!(
+            "This export uses format version {}, newer than this app supports ({}).",
+            version, EXPORT_VERSION
+        ));
+    }
+    let items = match value.get("conversations") {
+        Some(Value::Array(items)) => items.clone(),
+        _ => return Err("The export contains no conversations.".to_string()),
+    };
+
+    let mut parsed = ParsedImport {
+        source: ImportSource::Gamecode,
+        conversations: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for item in items {
+        let title = item
+            .get("metadata")
+            .and_then(|m| str_field(m, "title"))
+            .unwrap_or("Untitled")
+            .to_string();
+        match serde_json::from_value::<StoredConversation>(item) {
+            Ok(conv) => parsed.conversations.push(conv),
+            Err(e) => parsed.skipped.push(Skipped {
+                title,
+                reason: format!("Unreadable conversation: {}", e),
+            }),
+        }
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,179 @@
+//! Batch job results as conversations. Each job's replies are appended to a
+//! `job-<id>` conversation after every sync, so they show up in the sidebar,
+//! sync to other devices and can be searched and continued like any chat.
+
+use crate::api::{ApiClient, ApiError, ChatMessage, Job, JobResult};
+use crate::notebook::{CellContent, Notebook};
+use crate::storage::{
+    estimate_context_tokens, ContextState, ConversationMetadata, ConversationStore, JobLink,
+    StoredConversation,
+};
+use crate::sync;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+const DISMISSED_KEY: &str = "dismissed_jobs";
+const ID_PREFIX: &str = "job-";
+/// The server's page size for `/api/jobs/:id/results`; a shorter page is
+/// the last one.
+const RESULTS_PAGE: usize = 500;
+
+pub fn conversation_id(job_id: &str) -> String {
+    format!("{}{}", ID_PREFIX, job_id)
+}
+
+fn load_dismissed() -> HashSet<String> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|s| s.get_item(DISMISSED_KEY).ok().flatten())
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_dismissed(dismissed: &HashSet<String>) {
+    if let Some(s) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        if let Ok(v) = serde_json::to_string(dismissed) {
+            let _ = s.set_item(DISMISSED_KEY, &v);
+        }
+    }
+}
+
+/// A deleted job conversation stays deleted: later results of the job are
+/// no longer imported.
+pub fn note_deleted(conversation_id: &str) {
+    if let Some(job_id) = conversation_id.strip_prefix(ID_PREFIX) {
+        let mut dismissed = load_dismissed();
+        if dismissed.insert(job_id.to_string()) {
+            save_dismissed(&dismissed);
+        }
+    }
+}
+
+/// Append results not yet imported to each job's conversation. Returns the
+/// ids of the conversations that changed. A 403 means the server runs no
+/// batch jobs.
+pub async fn import_results(storage: &impl ConversationStore) -> Result<Vec<String>, ApiError> {
+    let client = ApiClient::new();
+    let jobs = client.list_jobs().await?;
+
+    let mut dismissed = load_dismissed();
+    // Forget jobs that no longer exist, so the set doesn't grow forever.
+    let before = dismissed.len();
+    dismissed.retain(|id| jobs.iter().any(|j| j.id == *id));
+    if dismissed.len() != before {
+        save_dismissed(&dismissed);
+    }
+
+    let mut updated = Vec::new();
+    for job in jobs.iter().filter(|j| !dismissed.contains(&j.id)) {
+        let id = conversation_id(&job.id);
+        let existing = storage.load_conversation(&id).await.ok().flatten();
+        let imported = existing
+            .as_ref()
+            .and_then(|c| c.metadata.job.as_ref())
+            .map_or(0, |link| link.last_seq);
+        if job.last_seq <= imported {
+            continue;
+        }
+
+        let mut conv = existing.unwrap_or_else(|| new_conversation(job));
+        let mut after = imported;
+        loop {
+            let page = client.job_results(&job.id, after).await?;
+            for result in &page {
+                append(&mut conv, job, result);
+                after = result.seq;
+            }
+            if page.len() < RESULTS_PAGE {
+                break;
+            }
+        }
+        if after == imported {
+            continue;
+        }
+
+        conv.context_state.total_tokens =
+            estimate_context_tokens(&conv.context_state.active_messages);
+        conv.metadata.title = format!("Job: {}", job.name);
+        conv.metadata.modified_at = Utc::now();
+        conv.metadata.job = Some(JobLink {
+            id: job.id.clone(),
+            last_seq: after,
+        });
+        match storage.save_conversation(&conv).await {
+            Ok(()) => {
+                sync::mark_dirty(&id);
+                updated.push(id);
+            }
+            Err(e) => web_sys::console::error_2(&"jobs: saving results:".into(), &e),
+        }
+    }
+    Ok(updated)
+}
+
+fn new_conversation(job: &Job) -> StoredConversation {
+    let created = DateTime::from_timestamp(job.created_at, 0).unwrap_or_else(Utc::now);
+    StoredConversation {
+        id: conversation_id(&job.id),
+        notebook: Notebook::new(),
+        context_state: ContextState {
+            compressed_summaries: Vec::new(),
+            compressed_spans: Vec::new(),
+            overrides: Vec::new(),
+            active_messages: Vec::new(),
+            total_tokens: 0,
+            compression_count: 0,
+            strategy: Default::default(),
+        },
+        metadata: ConversationMetadata {
+            created_at: created,
+            modified_at: created,
+            title: format!("Job: {}", job.name),
+            model: job.model.clone(),
+            provider: job.provider.clone(),
+            persona: job.persona.clone().unwrap_or_default(),
+            variables: HashMap::new(),
+            job: None,
+        },
+    }
+}
+
+/// The rendered prompt as the question and the reply (or why there is
+/// none) as the answer.
+fn append(conv: &mut StoredConversation, job: &Job, result: &JobResult) {
+    let at = DateTime::from_timestamp(result.finished_at, 0).unwrap_or_else(Utc::now);
+    let question = conv.notebook.add_cell(CellContent::UserInput {
+        text: result.prompt.clone(),
+    });
+    let answer = match (&result.reply, &result.error) {
+        (Some(reply), _) => CellContent::TextResponse {
+            text: reply.clone(),
+            streaming: false,
+        },
+        (None, error) => CellContent::Error {
+            message: format!("Row {} failed", result.index + 1),
+            details: error.clone(),
+        },
+    };
+    let answer = conv.notebook.add_cell(answer);
+    if let Some(cell) = conv.notebook.get_cell_mut(question) {
+        cell.timestamp = at;
+    }
+    if let Some(cell) = conv.notebook.get_cell_mut(answer) {
+        cell.timestamp = at;
+        cell.metadata.provider = Some(job.provider.clone());
+        cell.metadata.model = Some(job.model.clone());
+    }
+
+    let messages = &mut conv.context_state.active_messages;
+    messages.push(ChatMessage {
+        role: "user".to_string(),
+        content: result.prompt.clone(),
+    });
+    if let Some(reply) = &result.reply {
+        messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: reply.clone(),
+        });
+    }
+}
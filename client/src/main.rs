@@ -4,15 +4,28 @@ use leptos_router::*;
 use wasm_bindgen::JsCast;
 mod api;
 mod components;
+mod export;
+mod idb_storage;
+mod import;
+mod jobs;
 mod markdown;
 mod notebook;
+mod search_index;
+mod semantic_index;
 mod simple_storage;
 mod storage;
+mod sync;
+mod token_count;
 
 use api::{ApiClient, ApiError};
 use components::{
+    admin_health::AdminHealthPage,
     auth::{redirect_to_login, LoginRedirect},
     chat::Chat,
+    import_page::ImportPage,
+    jobs_page::JobsPage,
+    shared_view::SharedConversationPage,
+    shares_page::SharesPage,
 };
 
 #[derive(Clone, Copy, PartialEq)]
@@ -33,6 +46,11 @@ fn App() -> impl IntoView {
         <Router>
             <Routes>
                 <Route path="/" view=HomePage/>
+                <Route path="/admin" view=AdminHealthPage/>
+                <Route path="/import" view=ImportPage/>
+                <Route path="/jobs" view=JobsPage/>
+                <Route path="/shares" view=SharesPage/>
+                <Route path="/s/:token" view=SharedConversationPage/>
                 <Route path="/*any" view=NotFound/>
             </Routes>
         </Router>
@@ -43,6 +61,7 @@ fn App() -> impl IntoView {
 fn HomePage() -> impl IntoView {
     let (auth_state, set_auth_state) = create_signal(AuthState::Checking);
     let (username, set_username) = create_signal(String::new());
+    let (is_admin, set_is_admin) = create_signal(false);
 
     create_effect(move |_| {
         spawn_local(async move {
@@ -50,6 +69,7 @@ fn HomePage() -> impl IntoView {
             match client.me().await {
                 Ok(me) => {
                     set_username.set(me.username);
+                    set_is_admin.set(me.is_admin);
                     set_auth_state.set(AuthState::Authenticated);
                 }
                 Err(ApiError::Unauthorized) => {
@@ -75,6 +95,7 @@ fn HomePage() -> impl IntoView {
                     view! {
                         <Chat
                             user_name=user_signal
+                            is_admin=is_admin.into()
                             on_auth_error=move || redirect_to_login()
                             on_logout=move || {
                                 spawn_local(async move {
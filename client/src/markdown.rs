@@ -1,4 +1,4 @@
-use crate::components::icons::{IconCheck, IconCopy};
+use crate::components::icons::{IconCheck, IconCopy, IconPlay};
 use leptos::*;
 use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Parser, Tag, TagEnd};
 use syntect::highlighting::ThemeSet;
@@ -22,6 +22,9 @@ fn render_segments(text: &str) -> Vec<Segment> {
     let mut code_src = String::new();
     let mut code_lang = String::new();
     let mut heading_level: Option<HeadingLevel> = None;
+    // Inside a link whose target isn't safe to follow; its text is written
+    // without the anchor.
+    let mut in_plain_link = false;
 
     let syntax_set = SyntaxSet::load_defaults_newlines();
     let theme_set = ThemeSet::load_defaults();
@@ -102,13 +105,20 @@ fn render_segments(text: &str) -> Vec<Segment> {
             Event::Start(Tag::Link {
                 dest_url, title, ..
             }) => {
-                html_buf.push_str(&format!(
-                    r#"<a href="{}" title="{}" target="_blank" rel="noopener">"#,
-                    html_escape::encode_double_quoted_attribute(&dest_url),
-                    html_escape::encode_double_quoted_attribute(&title)
-                ));
+                in_plain_link = !safe_href(&dest_url);
+                if !in_plain_link {
+                    html_buf.push_str(&format!(
+                        r#"<a href="{}" title="{}" target="_blank" rel="noopener">"#,
+                        html_escape::encode_double_quoted_attribute(&dest_url),
+                        html_escape::encode_double_quoted_attribute(&title)
+                    ));
+                }
+            }
+            Event::End(TagEnd::Link) => {
+                if !std::mem::take(&mut in_plain_link) {
+                    html_buf.push_str("</a>");
+                }
             }
-            Event::End(TagEnd::Link) => html_buf.push_str("</a>"),
             Event::Start(Tag::BlockQuote(_)) => html_buf.push_str("<blockquote>"),
             Event::End(TagEnd::BlockQuote(_)) => html_buf.push_str("</blockquote>"),
             Event::HardBreak => html_buf.push_str("<br>"),
@@ -122,6 +132,25 @@ fn render_segments(text: &str) -> Vec<Segment> {
     out
 }
 
+/// Link targets worth rendering as anchors: `http`, `https`, `mailto` and
+/// relative URLs. Shared conversations put other people's text on this
+/// origin, so `javascript:` and the like must never become a link.
+fn safe_href(url: &str) -> bool {
+    // Browsers skip whitespace and control characters inside a scheme.
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            matches!(
+                url[..i].to_ascii_lowercase().as_str(),
+                "http" | "https" | "mailto"
+            )
+        }
+        _ => true,
+    }
+}
+
 fn heading_num(level: HeadingLevel) -> u8 {
     match level {
         HeadingLevel::H1 => 1,
@@ -133,8 +162,33 @@ fn heading_num(level: HeadingLevel) -> u8 {
     }
 }
 
+/// `text` as plain HTML with code blocks highlighted through inline styles,
+/// so it renders without the app's stylesheet (used by HTML export).
+pub fn render_html(text: &str) -> String {
+    render_segments(text)
+        .into_iter()
+        .map(|seg| match seg {
+            Segment::Html(html) => html,
+            Segment::Code {
+                lang, highlighted, ..
+            } => format!(
+                "<div class=\"code-block\"><div class=\"code-head\">{}</div>{}</div>",
+                html_escape::encode_text(&lang),
+                highlighted
+            ),
+        })
+        .collect()
+}
+
 #[component]
-pub fn MarkdownRenderer(text: String, #[prop(into)] show_cursor: Signal<bool>) -> impl IntoView {
+pub fn MarkdownRenderer(
+    text: String,
+    #[prop(into)] show_cursor: Signal<bool>,
+    /// Fence languages that get a Run button (from `/api/sandbox`).
+    #[prop(optional)]
+    runnable: Vec<String>,
+    #[prop(optional_no_strip)] on_run: Option<Callback<(String, String)>>,
+) -> impl IntoView {
     let segments = render_segments(&text);
     let last_idx = segments.len().saturating_sub(1);
 
@@ -164,8 +218,12 @@ pub fn MarkdownRenderer(text: String, #[prop(into)] show_cursor: Signal<bool>) -
                     .into_view()
                 }
                 Segment::Code { lang, source, highlighted } => {
-                    view! { <CodeBlock lang=lang source=source highlighted=highlighted/> }
-                        .into_view()
+                    let on_run =
+                        on_run.filter(|_| runnable.iter().any(|l| l.eq_ignore_ascii_case(&lang)));
+                    view! {
+                        <CodeBlock lang=lang source=source highlighted=highlighted on_run=on_run/>
+                    }
+                    .into_view()
                 }
             }
         })
@@ -177,9 +235,16 @@ pub fn MarkdownRenderer(text: String, #[prop(into)] show_cursor: Signal<bool>) -
 }
 
 #[component]
-fn CodeBlock(lang: String, source: String, highlighted: String) -> impl IntoView {
+fn CodeBlock(
+    lang: String,
+    source: String,
+    highlighted: String,
+    #[prop(optional_no_strip)] on_run: Option<Callback<(String, String)>>,
+) -> impl IntoView {
     let (copied, set_copied) = create_signal(false);
     let src_clone = source.clone();
+    let run_lang = lang.clone();
+    let run_src = source.clone();
 
     let handle_copy = move |_| {
         let text = src_clone.clone();
@@ -206,19 +271,82 @@ fn CodeBlock(lang: String, source: String, highlighted: String) -> impl IntoView
         <div class="code-block">
             <div class="code-head">
                 <span>{lang}</span>
-                <button
-                    class="code-copy"
-                    class:copied=move || copied.get()
-                    on:click=handle_copy
-                >
-                    {move || if copied.get() {
-                        view! { <IconCheck/> <span>"Copied"</span> }.into_view()
-                    } else {
-                        view! { <IconCopy/> <span>"Copy"</span> }.into_view()
-                    }}
-                </button>
+                <div class="code-actions">
+                    {on_run.map(|run| view! {
+                        <button
+                            class="code-copy code-run"
+                            title="Run in sandbox"
+                            on:click=move |_| run.call((run_lang.clone(), run_src.clone()))
+                        >
+                            <IconPlay/>
+                            <span>"Run"</span>
+                        </button>
+                    })}
+                    <button
+                        class="code-copy"
+                        class:copied=move || copied.get()
+                        on:click=handle_copy
+                    >
+                        {move || if copied.get() {
+                            view! { <IconCheck/> <span>"Copied"</span> }.into_view()
+                        } else {
+                            view! { <IconCopy/> <span>"Copy"</span> }.into_view()
+                        }}
+                    </button>
+                </div>
             </div>
             <div inner_html=highlighted></div>
         </div>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_keep_web_mail_and_relative_targets() {
+        for url in [
+            "https://example.com/a?b#c",
+            "HTTP://example.com",
+            "mailto:someone@example.com",
+            "/s/3f9a0c2e7b",
+            "docs/page.html",
+            "#section",
+            "?q=1",
+            "./a:b",
+        ] {
+            assert!(safe_href(url), "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn links_refuse_script_and_other_schemes() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:fetch('/api/conversations')",
+            "java\tscript:alert(1)",
+            " javascript:alert(1)",
+            "vbscript:msgbox",
+            "data:text/html,<script>alert(1)</script>",
+            "file:///etc/passwd",
+            ":alert(1)",
+        ] {
+            assert!(!safe_href(url), "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn unsafe_links_render_as_plain_text() {
+        let html = render_html("[click](javascript:alert(1)) and [ok](https://example.com)");
+        assert!(!html.contains("javascript"), "{}", html);
+        assert!(html.contains("click and "), "{}", html);
+        assert!(
+            html.contains(r#"<a href="https://example.com""#),
+            "{}",
+            html
+        );
+        let html = render_html("<javascript:alert(1)>");
+        assert!(!html.contains("<a "), "{}", html);
+    }
+}
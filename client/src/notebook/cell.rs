@@ -1,20 +1,360 @@
+use crate::components::feedback_bar::FeedbackBar;
+use crate::components::icons::{
+    IconCopy, IconEdit, IconEye, IconEyeOff, IconInfo, IconPin, IconTrash,
+};
 use crate::components::persona_picker::persona_color_var;
-use crate::notebook::{Cell, CellContent, CellId, Notebook};
+use crate::components::provenance_details::ProvenanceDetails;
+use crate::components::regenerate_menu::{RegenerateMenu, RegenerateOptions};
+use crate::notebook::{Cell, CellContent, CellId, Feedback, Notebook};
+use crate::search_index::{cell_text, find_matches};
 use leptos::*;
+use wasm_bindgen::{JsCast, JsValue};
 
 #[derive(Clone)]
 pub struct CellContext {
     pub user_initial: String,
     pub persona_name: String,
+    pub run_languages: Signal<Vec<String>>,
+    pub on_run: Option<Callback<RunSnippet>>,
+    /// Resend an earlier message with new text as a sibling branch.
+    pub on_edit: Option<Callback<(CellId, String)>>,
+    /// Show the branch through the given cell.
+    pub on_switch: Option<Callback<CellId>>,
+    pub regenerate: Option<RegenerateOptions>,
+    /// Apply a toolbar edit to a cell.
+    pub on_op: Option<Callback<(CellId, CellOp)>>,
+    /// Resend a reply's recorded request as a new version of it.
+    pub on_replay: Option<Callback<CellId>>,
+    /// Rate a reply; `None` clears the rating and note.
+    pub on_feedback: Option<Callback<(CellId, Option<Feedback>)>>,
+    /// A reply is streaming; branches can't change under it.
+    pub busy: Signal<bool>,
+}
+
+/// Edits from the cell toolbar; each one can be undone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellOp {
+    Delete,
+    MoveUp,
+    MoveDown,
+    ToggleCollapsed,
+    TogglePinned,
+    ToggleHidden,
+}
+
+/// A code block the user asked to run, tagged with the cell it came from.
+#[derive(Clone)]
+pub struct RunSnippet {
+    pub cell: CellId,
+    pub language: String,
+    pub source: String,
 }
 
 #[component]
 pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) -> impl IntoView {
+    let id = cell.id;
+    // Rebuilt when the cell is collapsed or expanded, or its run finishes;
+    // streamed text updates in place.
+    let shape = create_memo(move |_| {
+        notebook.with(|nb| {
+            nb.get_cell(id).map(|c| {
+                (
+                    c.metadata.collapsed,
+                    matches!(
+                        &c.content,
+                        CellContent::CodeOutput {
+                            result: Some(_),
+                            ..
+                        }
+                    ),
+                )
+            })
+        })
+    });
+    let flags = create_memo(move |_| {
+        notebook.with(|nb| {
+            nb.get_cell(id)
+                .map(|c| (c.metadata.pinned, c.metadata.hidden))
+                .unwrap_or_default()
+        })
+    });
+    let columns = create_memo(move |_| notebook.with(|nb| nb.compare_group(id)));
+    view! {
+        <div
+            class="cell"
+            class:cell-pinned=move || flags.get().0
+            class:cell-hidden=move || flags.get().1
+        >
+            {move || {
+                let collapsed = shape.get().is_some_and(|(collapsed, _)| collapsed);
+                let columns = columns.get();
+                let cell = notebook
+                    .with_untracked(|nb| nb.get_cell(id).cloned())
+                    .unwrap_or_else(|| cell.clone());
+                if collapsed {
+                    collapsed_view(&cell, &ctx, notebook)
+                } else if columns.len() > 1 {
+                    compare_view(id, columns, &ctx, notebook)
+                } else {
+                    cell_body(cell, ctx.clone(), notebook)
+                }
+            }}
+        </div>
+    }
+}
+
+/// Replies from a comparison side by side. `active` is the one on the
+/// branch; choosing another switches to it, so it's what the model sees
+/// from then on.
+fn compare_view(
+    active: CellId,
+    columns: Vec<CellId>,
+    ctx: &CellContext,
+    notebook: ReadSignal<Notebook>,
+) -> View {
+    let count = columns.len();
+    let busy = ctx.busy;
+    let on_switch = ctx.on_switch;
+    let bodies = columns
+        .into_iter()
+        .filter_map(|id| notebook.with_untracked(|nb| nb.get_cell(id).cloned()))
+        .map(|cell| {
+            let id = cell.id;
+            let chosen = id == active;
+            view! {
+                <div class="compare-col" class:chosen=chosen>
+                    {cell_body(cell, ctx.clone(), notebook)}
+                    <div class="compare-foot">
+                        {if chosen {
+                            view! { <span class="compare-chosen">"In context"</span> }.into_view()
+                        } else {
+                            on_switch.map(|on_switch| view! {
+                                <button
+                                    class="compare-use"
+                                    title="Continue the conversation from this reply"
+                                    disabled=move || busy.get()
+                                    on:click=move |_| on_switch.call(id)
+                                >
+                                    "Use this reply"
+                                </button>
+                            })
+                            .into_view()
+                        }}
+                    </div>
+                </div>
+            }
+        })
+        .collect_view();
+    view! {
+        <div
+            class="compare-row"
+            style=format!("grid-template-columns: repeat({}, minmax(0, 1fr));", count)
+        >
+            {bodies}
+        </div>
+    }
+    .into_view()
+}
+
+fn collapsed_view(cell: &Cell, ctx: &CellContext, notebook: ReadSignal<Notebook>) -> View {
+    let (avatar_class, avatar, author) = match &cell.content {
+        CellContent::UserInput { .. } => {
+            ("msg-avatar user", ctx.user_initial.clone(), "You".into())
+        }
+        CellContent::TextResponse { .. } => (
+            "msg-avatar assistant",
+            "ai".into(),
+            ctx.persona_name.clone(),
+        ),
+        CellContent::CodeOutput { .. } => ("msg-avatar assistant", "▶".into(), "Output".into()),
+        CellContent::Error { .. } => ("msg-avatar assistant", "!".into(), "Error".into()),
+        _ => ("msg-avatar assistant", "ai".into(), "Code".into()),
+    };
+    let preview = cell_text(&cell.content)
+        .map(|t| t.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default()
+        .chars()
+        .take(200)
+        .collect::<String>();
+    let id = cell.id;
+    let on_op = ctx.on_op;
+    let in_context = matches!(
+        cell.content,
+        CellContent::UserInput { .. } | CellContent::TextResponse { .. }
+    );
+    view! {
+        <div class="msg msg-collapsed" id=cell_anchor(id)>
+            <div class="msg-rail">
+                <div class=avatar_class>{avatar}</div>
+            </div>
+            <div class="msg-body">
+                <div class="msg-head">
+                    <span class="msg-author">{author}</span>
+                    <span
+                        class="msg-collapsed-preview"
+                        title="Expand"
+                        on:click=move |_| {
+                            if let Some(on_op) = on_op {
+                                on_op.call((id, CellOp::ToggleCollapsed));
+                            }
+                        }
+                    >
+                        {preview}
+                    </span>
+                    <CellActions
+                        cell=id
+                        notebook=notebook
+                        on_op=on_op
+                        busy=ctx.busy
+                        in_context=in_context
+                    />
+                </div>
+            </div>
+        </div>
+    }
+    .into_view()
+}
+
+/// Copy, collapse, move and delete buttons for one cell, plus pin and hide
+/// for cells the model sees (`in_context`). Without `on_op` (a shared,
+/// read-only view) only Copy is offered.
+#[component]
+fn CellActions(
+    cell: CellId,
+    notebook: ReadSignal<Notebook>,
+    on_op: Option<Callback<(CellId, CellOp)>>,
+    busy: Signal<bool>,
+    #[prop(optional)] in_context: bool,
+) -> impl IntoView {
+    let (copied, set_copied) = create_signal(false);
+    let copy = move |_| {
+        let Some(text) =
+            notebook.with_untracked(|nb| nb.get_cell(cell).and_then(|c| cell_text(&c.content)))
+        else {
+            return;
+        };
+        if let Some(nav) = web_sys::window().map(|w| w.navigator()) {
+            let clipboard = nav.clipboard();
+            spawn_local(async move {
+                let _ = wasm_bindgen_futures::JsFuture::from(clipboard.write_text(&text)).await;
+            });
+        }
+        set_copied.set(true);
+        gloo_timers::callback::Timeout::new(1400, move || set_copied.set(false)).forget();
+    };
+    let collapsed = create_memo(move |_| {
+        notebook.with(|nb| nb.get_cell(cell).is_some_and(|c| c.metadata.collapsed))
+    });
+    let pinned = create_memo(move |_| {
+        notebook.with(|nb| nb.get_cell(cell).is_some_and(|c| c.metadata.pinned))
+    });
+    let hidden = create_memo(move |_| {
+        notebook.with(|nb| nb.get_cell(cell).is_some_and(|c| c.metadata.hidden))
+    });
+    let can_move_up = create_memo(move |_| notebook.with(|nb| nb.can_move_up(cell)));
+    let can_move_down = create_memo(move |_| notebook.with(|nb| nb.can_move_down(cell)));
+    view! {
+        <span class="cell-actions">
+            {move || (in_context && hidden.get()).then(|| view! {
+                <span class="cell-flag">"hidden from context"</span>
+            })}
+            <button class="code-copy" class:copied=copied title="Copy" on:click=copy>
+                <IconCopy/>
+            </button>
+            {on_op.map(|on_op| {
+                let op = move |op: CellOp| move |_: ev::MouseEvent| on_op.call((cell, op));
+                let context_toggles = in_context.then(|| view! {
+                    <button
+                        class="code-copy cell-pin"
+                        class:active=pinned
+                        title=move || if pinned.get() {
+                            "Unpin"
+                        } else {
+                            "Pin: always keep this exchange in context"
+                        }
+                        disabled=move || busy.get()
+                        on:click=op(CellOp::TogglePinned)
+                    >
+                        <IconPin/>
+                    </button>
+                    <button
+                        class="code-copy"
+                        title=move || if hidden.get() {
+                            "Show to the model again"
+                        } else {
+                            "Hide from context"
+                        }
+                        disabled=move || busy.get()
+                        on:click=op(CellOp::ToggleHidden)
+                    >
+                        {move || if hidden.get() {
+                            view! { <IconEye/> }
+                        } else {
+                            view! { <IconEyeOff/> }
+                        }}
+                    </button>
+                });
+                view! {
+                    {context_toggles}
+                    <button
+                        class="code-copy"
+                        title=move || if collapsed.get() { "Expand" } else { "Collapse" }
+                        disabled=move || busy.get()
+                        on:click=op(CellOp::ToggleCollapsed)
+                    >
+                        {move || if collapsed.get() { "▸" } else { "▾" }}
+                    </button>
+                    <button
+                        class="code-copy"
+                        title="Move up"
+                        disabled=move || busy.get() || !can_move_up.get()
+                        on:click=op(CellOp::MoveUp)
+                    >
+                        "↑"
+                    </button>
+                    <button
+                        class="code-copy"
+                        title="Move down"
+                        disabled=move || busy.get() || !can_move_down.get()
+                        on:click=op(CellOp::MoveDown)
+                    >
+                        "↓"
+                    </button>
+                    <button
+                        class="code-copy cell-delete"
+                        title="Delete"
+                        disabled=move || busy.get()
+                        on:click=op(CellOp::Delete)
+                    >
+                        <IconTrash/>
+                    </button>
+                }
+            })}
+        </span>
+    }
+}
+
+fn cell_body(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) -> View {
     match cell.content {
         CellContent::UserInput { text } => {
             let initial = ctx.user_initial.clone();
+            let cell_id = cell.id;
+            let busy = ctx.busy;
+            let editing = create_rw_signal(false);
+            let draft = create_rw_signal(String::new());
+            let original = text.clone();
+            let start_edit = move |_| {
+                draft.set(original.clone());
+                editing.set(true);
+            };
+            let send_edit = move |_| {
+                if let Some(on_edit) = ctx.on_edit {
+                    editing.set(false);
+                    on_edit.call((cell_id, draft.get_untracked()));
+                }
+            };
             view! {
-                <div class="msg">
+                <div class="msg" id=cell_anchor(cell_id)>
                     <div class="msg-rail">
                         <div class="msg-avatar user">{initial}</div>
                         <div class="persona-line" style="background: var(--ink-4);"></div>
@@ -23,9 +363,55 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
                         <div class="msg-head">
                             <span class="msg-author">"You"</span>
                             <span class="msg-meta">{format_timestamp(&cell.timestamp)}</span>
+                            <BranchSwitcher
+                                cell=cell_id
+                                notebook=notebook
+                                on_switch=ctx.on_switch
+                                busy=busy
+                            />
+                            {ctx.on_edit.is_some().then(|| view! {
+                                <button
+                                    class="code-copy msg-edit-btn"
+                                    title="Edit and resend as a new branch"
+                                    disabled=move || busy.get() || editing.get()
+                                    on:click=start_edit
+                                >
+                                    <IconEdit/>
+                                </button>
+                            })}
+                            <CellActions
+                                cell=cell_id
+                                notebook=notebook
+                                on_op=ctx.on_op
+                                busy=busy
+                                in_context=true
+                            />
                         </div>
                         <div class="msg-content">
-                            <p>{text}</p>
+                            {move || if editing.get() {
+                                view! {
+                                    <div class="msg-edit">
+                                        <textarea
+                                            prop:value=move || draft.get()
+                                            on:input=move |ev| draft.set(event_target_value(&ev))
+                                        ></textarea>
+                                        <div class="msg-edit-actions">
+                                            <button on:click=move |_| editing.set(false)>
+                                                "Cancel"
+                                            </button>
+                                            <button
+                                                class="msg-edit-send"
+                                                disabled=move || busy.get() || draft.with(|d| d.trim().is_empty())
+                                                on:click=send_edit
+                                            >
+                                                "Send"
+                                            </button>
+                                        </div>
+                                    </div>
+                                }.into_view()
+                            } else {
+                                view! { <p>{text.clone()}</p> }.into_view()
+                            }}
                         </div>
                     </div>
                 </div>
@@ -43,6 +429,16 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
                 .unwrap_or_else(|| "assistant".to_string());
             let cell_id = cell.id;
             let timestamp = cell.timestamp;
+            let run_languages = ctx.run_languages;
+            let on_run = ctx.on_run.map(|run| {
+                Callback::new(move |(language, source): (String, String)| {
+                    run.call(RunSnippet {
+                        cell: cell_id,
+                        language,
+                        source,
+                    })
+                })
+            });
 
             let streaming = create_memo(move |_| {
                 live_text_response(notebook, cell_id)
@@ -54,9 +450,20 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
                     .map(|(t, _)| t)
                     .unwrap_or_default()
             });
+            // Replies from before requests were recorded have no details.
+            let has_provenance = create_memo(move |_| {
+                notebook.with(|nb| {
+                    nb.get_cell(cell_id)
+                        .is_some_and(|c| c.metadata.provenance.is_some())
+                })
+            });
+            let details_open = create_rw_signal(false);
+            let on_replay = ctx.on_replay;
+            let busy = ctx.busy;
+            let busy_or_streaming = Signal::derive(move || busy.get() || streaming.get());
 
             view! {
-                <div class="msg">
+                <div class="msg" id=cell_anchor(cell_id)>
                     <div class="msg-rail">
                         <div class="msg-avatar assistant">"ai"</div>
                         <div class="persona-line" style:background=color.to_string()></div>
@@ -69,7 +476,44 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
                                 <span>{model_tag}</span>
                             </span>
                             <span class="msg-meta">{format_timestamp(&timestamp)}</span>
+                            <BranchSwitcher
+                                cell=cell_id
+                                notebook=notebook
+                                on_switch=ctx.on_switch
+                                busy=ctx.busy
+                            />
+                            {ctx.regenerate.map(|options| {
+                                let busy = ctx.busy;
+                                move || (!streaming.get()).then(|| view! {
+                                    <RegenerateMenu cell=cell_id options=options busy=busy/>
+                                })
+                            })}
+                            {move || has_provenance.get().then(|| view! {
+                                <button
+                                    class="code-copy"
+                                    class:active=details_open
+                                    title="Request details"
+                                    on:click=move |_| details_open.update(|o| *o = !*o)
+                                >
+                                    <IconInfo/>
+                                </button>
+                            })}
+                            <CellActions
+                                cell=cell_id
+                                notebook=notebook
+                                on_op=ctx.on_op
+                                busy=ctx.busy
+                                in_context=true
+                            />
                         </div>
+                        {move || details_open.get().then(|| view! {
+                            <ProvenanceDetails
+                                cell=cell_id
+                                notebook=notebook
+                                on_replay=on_replay
+                                busy=busy_or_streaming
+                            />
+                        })}
                         <div class="msg-content">
                             {move || if streaming.get() {
                                 view! {
@@ -83,10 +527,15 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
                                     <crate::markdown::MarkdownRenderer
                                         text=text.get()
                                         show_cursor=Signal::derive(|| false)
+                                        runnable=run_languages.get()
+                                        on_run=on_run
                                     />
                                 }.into_view()
                             }}
                         </div>
+                        {ctx.on_feedback.map(|on_feedback| move || (!streaming.get()).then(|| view! {
+                            <FeedbackBar cell=cell_id notebook=notebook on_feedback=on_feedback/>
+                        }))}
                     </div>
                 </div>
             }
@@ -98,7 +547,7 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
             source,
             rendered: _,
         } => view! {
-            <div class="msg">
+            <div class="msg" id=cell_anchor(cell.id)>
                 <div class="msg-rail">
                     <div class="msg-avatar assistant">"ai"</div>
                 </div>
@@ -107,6 +556,14 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
                         <div class="code-block">
                             <div class="code-head">
                                 <span>{language.clone()}</span>
+                                <div class="code-actions">
+                                    <CellActions
+                                        cell=cell.id
+                                        notebook=notebook
+                                        on_op=ctx.on_op
+                                        busy=ctx.busy
+                                    />
+                                </div>
                             </div>
                             <pre><code class=format!("language-{}", language)>{source}</code></pre>
                         </div>
@@ -116,8 +573,77 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
         }
         .into_view(),
 
+        CellContent::CodeOutput {
+            source_cell,
+            language,
+            result,
+        } => {
+            let status = match &result {
+                None => "running…".to_string(),
+                Some(run) if run.timed_out => "timed out".to_string(),
+                Some(run) => match run.exit_code {
+                    Some(code) => format!("exit {}", code),
+                    None => "killed".to_string(),
+                },
+            };
+            let failed = result
+                .as_ref()
+                .map(|run| run.timed_out || run.exit_code != Some(0))
+                .unwrap_or(false);
+            view! {
+                <div class="msg" id=cell_anchor(cell.id)>
+                    <div class="msg-rail">
+                        <div class="msg-avatar assistant">"▶"</div>
+                    </div>
+                    <div class="msg-body">
+                        <div class="msg-content">
+                            <div class="code-block code-output">
+                                <div class="code-head">
+                                    <span>{format!("output · {}", language)}</span>
+                                    <div class="code-actions">
+                                        <span class="code-output-status" class:failed=failed>
+                                            {status}
+                                        </span>
+                                        <button
+                                            class="code-copy"
+                                            title="Jump to the source block"
+                                            on:click=move |_| scroll_to_cell(source_cell)
+                                        >
+                                            "↑ source"
+                                        </button>
+                                        <CellActions
+                                            cell=cell.id
+                                            notebook=notebook
+                                            on_op=ctx.on_op
+                                            busy=ctx.busy
+                                        />
+                                    </div>
+                                </div>
+                                {match result {
+                                    None => view! {
+                                        <pre><span class="streaming-cursor"></span></pre>
+                                    }.into_view(),
+                                    Some(run) => view! {
+                                        <pre class="code-output-stdout">{run.stdout}</pre>
+                                        {(!run.stderr.is_empty()).then(|| view! {
+                                            <pre class="code-output-stderr">{run.stderr}</pre>
+                                        })}
+                                        {run.truncated.then(|| view! {
+                                            <div class="code-output-note">"Output truncated"</div>
+                                        })}
+                                    }.into_view(),
+                                }}
+                            </div>
+                        </div>
+                    </div>
+                </div>
+            }
+            .into_view()
+        }
+
         CellContent::Error { message, details } => view! {
-            <div class="err-card">
+            <div class="err-card" id=cell_anchor(cell.id)>
+                <CellActions cell=cell.id notebook=notebook on_op=ctx.on_op busy=ctx.busy/>
                 <div class="err-title">{message}</div>
                 {details.map(|d| view! { <div class="err-details">{d}</div> })}
             </div>
@@ -142,6 +668,46 @@ pub fn CellView(cell: Cell, ctx: CellContext, notebook: ReadSignal<Notebook>) ->
     }
 }
 
+/// "‹ 2 / 3 ›" on a cell that has siblings from other branches.
+#[component]
+fn BranchSwitcher(
+    cell: CellId,
+    notebook: ReadSignal<Notebook>,
+    on_switch: Option<Callback<CellId>>,
+    busy: Signal<bool>,
+) -> impl IntoView {
+    let siblings = create_memo(move |_| notebook.with(|nb| nb.siblings(cell)));
+    move || {
+        let on_switch = on_switch?;
+        let siblings = siblings.get();
+        if siblings.len() < 2 {
+            return None;
+        }
+        let pos = siblings.iter().position(|c| *c == cell).unwrap_or(0);
+        let prev = pos.checked_sub(1).map(|i| siblings[i]);
+        let next = siblings.get(pos + 1).copied();
+        Some(view! {
+            <span class="branch-switch">
+                <button
+                    title="Previous branch"
+                    disabled=move || busy.get() || prev.is_none()
+                    on:click=move |_| prev.into_iter().for_each(|c| on_switch.call(c))
+                >
+                    "‹"
+                </button>
+                <span>{format!("{} / {}", pos + 1, siblings.len())}</span>
+                <button
+                    title="Next branch"
+                    disabled=move || busy.get() || next.is_none()
+                    on:click=move |_| next.into_iter().for_each(|c| on_switch.call(c))
+                >
+                    "›"
+                </button>
+            </span>
+        })
+    }
+}
+
 fn live_text_response(notebook: ReadSignal<Notebook>, cell_id: CellId) -> Option<(String, bool)> {
     notebook
         .get()
@@ -154,6 +720,89 @@ fn live_text_response(notebook: ReadSignal<Notebook>, cell_id: CellId) -> Option
         })
 }
 
+pub fn cell_anchor(id: CellId) -> String {
+    format!("cell-{}", id.0)
+}
+
+pub fn scroll_to_cell(id: CellId) {
+    if let Some(el) = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(&cell_anchor(id)))
+    {
+        el.scroll_into_view();
+    }
+}
+
+fn text_nodes(node: &web_sys::Node, out: &mut Vec<web_sys::Text>) {
+    let children = node.child_nodes();
+    for i in 0..children.length() {
+        let Some(child) = children.item(i) else {
+            continue;
+        };
+        match child.dyn_into::<web_sys::Text>() {
+            Ok(text) => out.push(text),
+            Err(child) => text_nodes(&child, out),
+        }
+    }
+}
+
+/// Wrap every occurrence of `terms` in the cell's text in `<mark>`.
+fn mark_terms(el: &web_sys::Element, terms: &[String]) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or("No document")?;
+    let mut nodes = Vec::new();
+    text_nodes(el, &mut nodes);
+    for node in nodes {
+        let text = node.data();
+        let utf16 = |byte: usize| text[..byte].encode_utf16().count() as u32;
+        // Back to front, so splitting never shifts a range still to come.
+        for (start, end) in find_matches(&text, terms).into_iter().rev() {
+            node.split_text(utf16(end))?;
+            let hit = node.split_text(utf16(start))?;
+            let mark = document.create_element("mark")?;
+            mark.set_class_name("search-mark");
+            let parent = hit.parent_node().ok_or("Detached text")?;
+            parent.replace_child(&mark, &hit)?;
+            mark.append_child(&hit)?;
+        }
+    }
+    Ok(())
+}
+
+/// Scroll to a search hit and highlight the matched terms, clearing marks
+/// left from an earlier jump.
+pub fn reveal_cell(id: CellId, terms: &[String]) {
+    let Some(document) = web_sys::window().and_then(|w| w.document()) else {
+        return;
+    };
+    if let Ok(old) = document.query_selector_all("mark.search-mark") {
+        for i in 0..old.length() {
+            if let Some(mark) = old.item(i) {
+                if let (Some(parent), Some(text)) = (mark.parent_node(), mark.text_content()) {
+                    let _ = parent.replace_child(&document.create_text_node(&text), &mark);
+                    parent.normalize();
+                }
+            }
+        }
+    }
+    let Some(el) = document.get_element_by_id(&cell_anchor(id)) else {
+        return;
+    };
+    let _ = mark_terms(&el, terms);
+    let target = el
+        .query_selector("mark.search-mark")
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| el.clone());
+    target.scroll_into_view_with_bool(false);
+    let _ = el.class_list().add_1("search-hit");
+    gloo_timers::callback::Timeout::new(2000, move || {
+        let _ = el.class_list().remove_1("search-hit");
+    })
+    .forget();
+}
+
 fn format_timestamp(dt: &chrono::DateTime<chrono::Utc>) -> String {
     use chrono::{Local, TimeZone};
     let local = Local.from_utc_datetime(&dt.naive_utc());
@@ -1,14 +1,68 @@
+use crate::api::{ChatMessage, SamplingParams};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 
 pub mod cell;
 
+/// A conversation as a tree of cells. Editing an earlier message starts a
+/// sibling branch instead of overwriting what followed; the thread shows
+/// the path from the root to `active_leaf`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "StoredNotebook")]
 pub struct Notebook {
+    /// Every cell of every branch, in creation order.
     pub cells: Vec<Cell>,
     pub cursor_position: CellId,
     pub active_input: String,
+    pub active_leaf: Option<CellId>,
+    /// Id for the next cell. Ids are never reused, so nothing that refers
+    /// to a deleted cell can land on a newer one.
+    pub next_id: usize,
+}
+
+/// `Notebook` as stored. Notebooks saved before branching have no
+/// `active_leaf` or parents and read back as a single branch.
+#[derive(Deserialize)]
+struct StoredNotebook {
+    cells: Vec<Cell>,
+    cursor_position: CellId,
+    active_input: String,
+    #[serde(default)]
+    active_leaf: Option<CellId>,
+    #[serde(default)]
+    next_id: usize,
+}
+
+impl From<StoredNotebook> for Notebook {
+    fn from(stored: StoredNotebook) -> Self {
+        let mut cells = stored.cells;
+        let active_leaf = match stored.active_leaf {
+            Some(leaf) => Some(leaf),
+            None => {
+                let mut prev = None;
+                for cell in &mut cells {
+                    cell.parent = prev;
+                    prev = Some(cell.id);
+                }
+                cells.last().map(|c| c.id)
+            }
+        };
+        let next_id = cells
+            .iter()
+            .map(|c| c.id.0 + 1)
+            .max()
+            .unwrap_or(0)
+            .max(stored.next_id);
+        Self {
+            cells,
+            cursor_position: stored.cursor_position,
+            active_input: stored.active_input,
+            active_leaf,
+            next_id,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -17,17 +71,126 @@ pub struct CellId(pub usize);
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Cell {
     pub id: CellId,
+    /// The cell this one follows; `None` for the first cell of a branch
+    /// that starts at the top.
+    #[serde(default)]
+    pub parent: Option<CellId>,
     pub content: CellContent,
     pub timestamp: DateTime<Utc>,
     pub metadata: CellMetadata,
 }
 
+impl Cell {
+    /// The cell as a chat message: user messages and finished replies.
+    pub fn chat_message(&self) -> Option<ChatMessage> {
+        match &self.content {
+            CellContent::UserInput { text } => Some(ChatMessage {
+                role: "user".into(),
+                content: text.clone(),
+            }),
+            CellContent::TextResponse {
+                text,
+                streaming: false,
+            } if !text.is_empty() => Some(ChatMessage {
+                role: "assistant".into(),
+                content: text.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct CellMetadata {
     pub provider: Option<String>,
     pub model: Option<String>,
+    /// Shown dimmed and left out of the model's context.
     pub hidden: bool,
+    /// The exchange stays in the context even when older turns are
+    /// compressed.
     pub pinned: bool,
+    /// Shown as a one-line preview in the thread.
+    #[serde(default)]
+    pub collapsed: bool,
+    /// The request that produced a reply.
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// Replies sent to several models at once share the id of the first
+    /// one and are shown side by side.
+    #[serde(default)]
+    pub compare_group: Option<CellId>,
+    /// The user's rating of a reply.
+    #[serde(default)]
+    pub feedback: Option<Feedback>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Feedback {
+    /// `None` keeps a comment without a thumb either way.
+    pub rating: Option<Rating>,
+    #[serde(default)]
+    pub comment: String,
+    pub at: DateTime<Utc>,
+}
+
+/// Everything sent to the provider for one reply, recorded so the reply
+/// can be explained and the request replayed. `system_prompt` is the
+/// persona template before the server fills in its variables;
+/// `rendered_system_prompt` is what the model was actually sent.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Provenance {
+    pub provider: String,
+    pub model: String,
+    /// Digest of the weights the provider resolved `model` to, when it
+    /// reports one.
+    pub model_digest: Option<String>,
+    pub temperature: f32,
+    pub sampling: SamplingParams,
+    pub persona: Option<String>,
+    /// Hex FNV-1a of `system_prompt`.
+    pub prompt_hash: Option<String>,
+    pub system_prompt: Option<String>,
+    pub variables: HashMap<String, String>,
+    /// Filled in from the server's reply; `None` in records made before it
+    /// was kept.
+    pub rendered_system_prompt: Option<String>,
+    /// The messages sent, in order.
+    pub context: Vec<ContextItem>,
+    /// The reply this one replayed.
+    pub replay_of: Option<CellId>,
+}
+
+impl Provenance {
+    /// The messages the request sent, or the first cell it needs that has
+    /// since been deleted.
+    pub fn messages(&self, nb: &Notebook) -> Result<Vec<ChatMessage>, CellId> {
+        self.context
+            .iter()
+            .map(|item| match item {
+                ContextItem::Cell(id) => nb.get_cell(*id).and_then(Cell::chat_message).ok_or(*id),
+                ContextItem::Text(message) => Ok(message.clone()),
+            })
+            .collect()
+    }
+}
+
+/// One message of a recorded request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextItem {
+    /// A message or reply cell, sent as written. Cells don't change after
+    /// they're written (edits fork), so the id is enough.
+    Cell(CellId),
+    /// Anything else: summaries, notes, truncated or hand-edited messages.
+    Text(ChatMessage),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -63,6 +226,11 @@ pub enum CellContent {
         data: serde_json::Value,
         rendered: Option<RenderedContent>,
     },
+    CodeOutput {
+        source_cell: CellId,
+        language: String,
+        result: Option<CodeRun>,
+    },
     Error {
         message: String,
         details: Option<String>,
@@ -72,6 +240,17 @@ pub enum CellContent {
     },
 }
 
+/// Result of running a code block in the server sandbox. `exit_code` is
+/// `None` when the process was killed (timeout) or never started.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CodeRun {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub truncated: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DiagramFormat {
     Graphviz,
@@ -117,23 +296,239 @@ impl Notebook {
             cells: Vec::new(),
             cursor_position: CellId(0),
             active_input: String::new(),
+            active_leaf: None,
+            next_id: 0,
         }
     }
 
-    pub fn add_cell(&mut self, content: CellContent) -> CellId {
-        let id = CellId(self.cells.len());
-        let cell = Cell {
+    fn push_cell(&mut self, parent: Option<CellId>, content: CellContent) -> CellId {
+        let id = CellId(self.next_id);
+        self.next_id += 1;
+        self.cells.push(Cell {
             id,
+            parent,
             content,
             timestamp: Utc::now(),
             metadata: CellMetadata::default(),
-        };
-        self.cells.push(cell);
+        });
+        self.active_leaf = Some(id);
         id
     }
 
+    /// Append to the active branch.
+    pub fn add_cell(&mut self, content: CellContent) -> CellId {
+        self.push_cell(self.active_leaf, content)
+    }
+
+    /// Start a new branch beside `sibling`, leaving its own branch intact,
+    /// and make it the active one.
+    pub fn fork(&mut self, sibling: CellId, content: CellContent) -> CellId {
+        let parent = self.get_cell(sibling).and_then(|c| c.parent);
+        self.push_cell(parent, content)
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.cursor_position = CellId(0);
+        self.active_leaf = None;
+        self.next_id = 0;
+    }
+
+    /// Remove one cell; whatever followed it now follows its parent.
+    pub fn delete_cell(&mut self, id: CellId) {
+        let Some(pos) = self.cells.iter().position(|c| c.id == id) else {
+            return;
+        };
+        let parent = self.cells.remove(pos).parent;
+        for cell in &mut self.cells {
+            if cell.parent == Some(id) {
+                cell.parent = parent;
+            }
+        }
+        if self.active_leaf == Some(id) {
+            self.active_leaf = parent;
+            if parent.is_none() {
+                if let Some(root) = self.cells.iter().rev().find(|c| c.parent.is_none()) {
+                    self.switch_to(root.id);
+                }
+            }
+        }
+    }
+
+    /// Whether `move_up` can swap `id` with the cell before it. Not when
+    /// either has sibling versions: those belong at the position they were
+    /// written for, and after a swap the tree has no place for them.
+    pub fn can_move_up(&self, id: CellId) -> bool {
+        let Some(parent) = self.get_cell(id).and_then(|c| c.parent) else {
+            return false;
+        };
+        self.siblings(id).len() == 1 && self.siblings(parent).len() == 1
+    }
+
+    pub fn can_move_down(&self, id: CellId) -> bool {
+        self.next_on_path(id)
+            .is_some_and(|next| self.can_move_up(next))
+    }
+
+    /// Swap a cell with the one before it on its branch. Cells with sibling
+    /// versions stay put; see `can_move_up`.
+    pub fn move_up(&mut self, id: CellId) {
+        if !self.can_move_up(id) {
+            return;
+        }
+        let Some(parent) = self.get_cell(id).and_then(|c| c.parent) else {
+            return;
+        };
+        let grandparent = self.get_cell(parent).and_then(|c| c.parent);
+        for cell in &mut self.cells {
+            if cell.id == id {
+                cell.parent = grandparent;
+            } else if cell.id == parent {
+                cell.parent = Some(id);
+            } else if cell.parent == Some(id) {
+                cell.parent = Some(parent);
+            }
+        }
+        if self.active_leaf == Some(id) {
+            self.active_leaf = Some(parent);
+        }
+    }
+
+    /// Swap a cell with the one after it on the active branch.
+    pub fn move_down(&mut self, id: CellId) {
+        if let Some(next) = self.next_on_path(id) {
+            self.move_up(next);
+        }
+    }
+
+    fn next_on_path(&self, id: CellId) -> Option<CellId> {
+        let path = self.active_path();
+        path.iter()
+            .position(|c| c.id == id)
+            .and_then(|i| path.get(i + 1))
+            .map(|c| c.id)
+    }
+
+    pub fn toggle_collapsed(&mut self, id: CellId) {
+        if let Some(cell) = self.get_cell_mut(id) {
+            cell.metadata.collapsed = !cell.metadata.collapsed;
+        }
+    }
+
+    pub fn toggle_pinned(&mut self, id: CellId) {
+        if let Some(cell) = self.get_cell_mut(id) {
+            cell.metadata.pinned = !cell.metadata.pinned;
+        }
+    }
+
+    pub fn toggle_hidden(&mut self, id: CellId) {
+        if let Some(cell) = self.get_cell_mut(id) {
+            cell.metadata.hidden = !cell.metadata.hidden;
+        }
+    }
+
+    pub fn set_feedback(&mut self, id: CellId, feedback: Option<Feedback>) {
+        if let Some(cell) = self.get_cell_mut(id) {
+            cell.metadata.feedback = feedback;
+        }
+    }
+
+    /// What the model was sent for the reply `id`: its recorded request,
+    /// or for replies from before requests were recorded (or whose
+    /// context cells were since deleted), the visible messages above it on
+    /// its branch.
+    pub fn sent_messages(&self, id: CellId) -> Vec<ChatMessage> {
+        let recorded = self
+            .get_cell(id)
+            .and_then(|c| c.metadata.provenance.as_ref())
+            .and_then(|p| p.messages(self).ok());
+        if let Some(messages) = recorded {
+            return messages;
+        }
+        let mut above = Vec::new();
+        let mut next = self.get_cell(id).and_then(|c| c.parent);
+        while let Some(cell) = next.and_then(|p| self.get_cell(p)) {
+            if !cell.metadata.hidden {
+                above.extend(cell.chat_message());
+            }
+            next = cell.parent;
+        }
+        above.reverse();
+        above
+    }
+
+    pub fn get_cell(&self, id: CellId) -> Option<&Cell> {
+        self.cells.iter().find(|c| c.id == id)
+    }
+
     pub fn get_cell_mut(&mut self, id: CellId) -> Option<&mut Cell> {
-        self.cells.get_mut(id.0)
+        self.cells.iter_mut().find(|c| c.id == id)
+    }
+
+    /// The cells on screen: root to `active_leaf`.
+    pub fn active_path(&self) -> Vec<&Cell> {
+        let mut path = Vec::new();
+        let mut next = self.active_leaf;
+        while let Some(cell) = next.and_then(|id| self.get_cell(id)) {
+            path.push(cell);
+            next = cell.parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// A copy holding only the cells on screen, without the versions and
+    /// edits left on other branches.
+    pub fn active_branch(&self) -> Notebook {
+        Notebook {
+            cells: self.active_path().into_iter().cloned().collect(),
+            ..self.clone()
+        }
+    }
+
+    /// `id` and the other cells with the same parent, oldest first.
+    pub fn siblings(&self, id: CellId) -> Vec<CellId> {
+        let Some(parent) = self.get_cell(id).map(|c| c.parent) else {
+            return Vec::new();
+        };
+        self.cells
+            .iter()
+            .filter(|c| c.parent == parent)
+            .map(|c| c.id)
+            .collect()
+    }
+
+    /// The replies compared alongside `id`, itself included, in the order
+    /// they were sent; empty if it wasn't part of a comparison.
+    pub fn compare_group(&self, id: CellId) -> Vec<CellId> {
+        let Some(group) = self.get_cell(id).and_then(|c| c.metadata.compare_group) else {
+            return Vec::new();
+        };
+        self.siblings(id)
+            .into_iter()
+            .filter(|s| {
+                self.get_cell(*s)
+                    .is_some_and(|c| c.metadata.compare_group == Some(group))
+            })
+            .collect()
+    }
+
+    /// Show the branch through `id`, following the newest child below it.
+    pub fn switch_to(&mut self, id: CellId) {
+        if self.get_cell(id).is_none() {
+            return;
+        }
+        let mut leaf = id;
+        while let Some(child) = self
+            .cells
+            .iter()
+            .filter(|c| c.parent == Some(leaf))
+            .map(|c| c.id)
+            .max_by_key(|c| c.0)
+        {
+            leaf = child;
+        }
+        self.active_leaf = Some(leaf);
     }
 
     pub fn update_streaming_response(&mut self, id: CellId, text: &str) {
@@ -167,4 +562,110 @@ impl Notebook {
             }
         }
     }
+
+    pub fn complete_code_output(&mut self, id: CellId, run: CodeRun) {
+        if let Some(cell) = self.get_cell_mut(id) {
+            if let CellContent::CodeOutput { result, .. } = &mut cell.content {
+                *result = Some(run);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(text: &str) -> CellContent {
+        CellContent::UserInput {
+            text: text.to_string(),
+        }
+    }
+
+    fn reply(text: &str) -> CellContent {
+        CellContent::TextResponse {
+            text: text.to_string(),
+            streaming: false,
+        }
+    }
+
+    fn path(nb: &Notebook) -> Vec<CellId> {
+        nb.active_path().iter().map(|c| c.id).collect()
+    }
+
+    fn parent(nb: &Notebook, id: CellId) -> Option<CellId> {
+        nb.get_cell(id).and_then(|c| c.parent)
+    }
+
+    #[test]
+    fn active_branch_drops_other_versions() {
+        let mut nb = Notebook::new();
+        let q1 = nb.add_cell(user("one"));
+        let a1 = nb.add_cell(reply("1"));
+        let regenerated = nb.fork(a1, reply("1 again"));
+        let q2 = nb.add_cell(user("two"));
+        let edited = nb.fork(q2, user("two, edited"));
+        let a2 = nb.add_cell(reply("2"));
+
+        let branch = nb.active_branch();
+        let ids: Vec<CellId> = branch.cells.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![q1, regenerated, edited, a2]);
+        assert_eq!(path(&branch), path(&nb));
+        assert_eq!(branch.next_id, nb.next_id);
+    }
+
+    #[test]
+    fn move_up_swaps_with_the_cell_before() {
+        let mut nb = Notebook::new();
+        let q1 = nb.add_cell(user("one"));
+        let a1 = nb.add_cell(reply("1"));
+        let q2 = nb.add_cell(user("two"));
+        let a2 = nb.add_cell(reply("2"));
+
+        nb.move_up(q2);
+        assert_eq!(path(&nb), vec![q1, q2, a1, a2]);
+        nb.move_down(q2);
+        assert_eq!(path(&nb), vec![q1, a1, q2, a2]);
+    }
+
+    #[test]
+    fn cells_with_sibling_versions_stay_put() {
+        let mut nb = Notebook::new();
+        let q1 = nb.add_cell(user("one"));
+        let a1 = nb.add_cell(reply("1"));
+        let regenerated = nb.fork(a1, reply("1 again"));
+        let q2 = nb.add_cell(user("two"));
+        let before = nb.clone();
+
+        // The regenerated reply has a sibling, and so does the parent of
+        // the question after it.
+        assert!(!nb.can_move_up(regenerated));
+        assert!(!nb.can_move_down(q1));
+        assert!(!nb.can_move_up(q2));
+        assert!(!nb.can_move_down(regenerated));
+        nb.move_up(regenerated);
+        nb.move_down(q1);
+        nb.move_up(q2);
+        nb.move_down(regenerated);
+        assert_eq!(nb, before);
+        assert_eq!(nb.siblings(a1), vec![a1, regenerated]);
+        assert_eq!(parent(&nb, q2), Some(regenerated));
+    }
+
+    #[test]
+    fn forks_below_a_moved_cell_move_with_its_position() {
+        let mut nb = Notebook::new();
+        let q1 = nb.add_cell(user("one"));
+        let a1 = nb.add_cell(reply("1"));
+        let q2 = nb.add_cell(user("two"));
+        let edited = nb.fork(q2, user("two, edited"));
+
+        assert!(nb.can_move_up(a1));
+        nb.move_up(a1);
+        assert_eq!(parent(&nb, a1), None);
+        assert_eq!(parent(&nb, q1), Some(a1));
+        assert_eq!(parent(&nb, q2), Some(q1));
+        assert_eq!(parent(&nb, edited), Some(q1));
+        assert_eq!(path(&nb), vec![a1, q1, edited]);
+    }
 }
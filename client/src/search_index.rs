@@ -0,0 +1,343 @@
+//! Full-text search over every cell of every stored conversation.
+//!
+//! The index lives in memory and is built from storage on the first query;
+//! after that `AppStorage` keeps it current on every save and delete, so
+//! chat saves, sync pulls and imports are all searchable right away.
+
+use crate::notebook::{CellContent, CellId};
+use crate::storage::{AppStorage, ConversationStore, StoredConversation};
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Terms shorter than this (in chars) are neither indexed nor searched.
+const MIN_TERM_CHARS: usize = 2;
+/// Context kept around the first match in a snippet, in bytes.
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_AFTER: usize = 120;
+/// Hits shown per conversation, so one long chat can't crowd out the rest.
+const HITS_PER_CONVERSATION: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub title: String,
+    pub cell: CellId,
+    pub snippet: String,
+    /// Byte ranges in `snippet` that matched a query term.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+struct IndexedCell {
+    id: CellId,
+    text: String,
+}
+
+struct IndexedConversation {
+    id: String,
+    title: String,
+    modified_at: DateTime<Utc>,
+    cells: Vec<IndexedCell>,
+}
+
+#[derive(Default)]
+struct SearchIndex {
+    docs: Vec<Option<IndexedConversation>>,
+    slots: HashMap<String, usize>,
+    free: Vec<usize>,
+    /// Term → (doc slot, position in that doc's `cells`).
+    postings: BTreeMap<String, HashSet<(usize, usize)>>,
+    /// Built from storage, or being built: saves update it from then on.
+    started: bool,
+    building: bool,
+    /// Ids saved or deleted while the initial build was still loading; the
+    /// build must not overwrite them with what it read earlier.
+    touched: HashSet<String>,
+}
+
+thread_local! {
+    static INDEX: RefCell<SearchIndex> = RefCell::new(SearchIndex::default());
+}
+
+/// Lowercase `text` char by char, with the original byte offset of every
+/// byte of the result (plus a trailing `text.len()`), so matches found in
+/// the folded text can be mapped back.
+fn fold(text: &str) -> (String, Vec<usize>) {
+    let mut lower = String::with_capacity(text.len());
+    let mut origin = Vec::with_capacity(text.len() + 1);
+    for (i, c) in text.char_indices() {
+        for l in c.to_lowercase() {
+            lower.push(l);
+            origin.resize(lower.len(), i);
+        }
+    }
+    origin.push(text.len());
+    (lower, origin)
+}
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    let (lower, _) = fold(text);
+    let mut seen = HashSet::new();
+    lower
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= MIN_TERM_CHARS)
+        .filter(|t| seen.insert(*t))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Byte ranges in `text` where any of `terms` (already lowercase) occurs,
+/// sorted and non-overlapping.
+pub fn find_matches(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let (lower, origin) = fold(text);
+    let mut ranges = terms
+        .iter()
+        .flat_map(|t| {
+            lower
+                .match_indices(t.as_str())
+                .map(|(i, m)| (origin[i], origin[i + m.len()]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    ranges.sort();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Text of the cells the thread actually renders, so every hit can be
+/// scrolled to.
+pub fn cell_text(content: &CellContent) -> Option<String> {
+    match content {
+        CellContent::UserInput { text } | CellContent::TextResponse { text, .. } => {
+            Some(text.clone())
+        }
+        CellContent::Code { source, .. } => Some(source.clone()),
+        CellContent::CodeOutput {
+            result: Some(run), ..
+        } => Some(format!("{}\n{}", run.stdout, run.stderr)),
+        CellContent::Error { message, .. } => Some(message.clone()),
+        _ => None,
+    }
+}
+
+fn floor_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn snippet(text: &str, terms: &[String]) -> (String, Vec<(usize, usize)>) {
+    let first = find_matches(text, terms).first().map_or(0, |m| m.0);
+    let mut start = floor_boundary(text, first.saturating_sub(SNIPPET_BEFORE));
+    // Start on a word boundary.
+    if start > 0 {
+        if let Some(space) = text[start..first].find(char::is_whitespace) {
+            start += space + 1;
+        }
+    }
+    let end = floor_boundary(text, (first + SNIPPET_AFTER).min(text.len()));
+    let mut out = String::new();
+    if start > 0 {
+        out.push('…');
+    }
+    let offset = out.len();
+    out.extend(
+        text[start..end]
+            .chars()
+            .map(|c| if c.is_whitespace() { ' ' } else { c }),
+    );
+    // Whitespace is replaced char for char but may change width ('\u{a0}'),
+    // so match again on the final text rather than shifting ranges.
+    let highlights = find_matches(&out[offset..], terms)
+        .into_iter()
+        .map(|(s, e)| (s + offset, e + offset))
+        .collect();
+    if end < text.len() {
+        out.push('…');
+    }
+    (out, highlights)
+}
+
+impl SearchIndex {
+    fn remove(&mut self, id: &str) {
+        let Some(slot) = self.slots.remove(id) else {
+            return;
+        };
+        if let Some(doc) = self.docs[slot].take() {
+            for (pos, cell) in doc.cells.iter().enumerate() {
+                for term in tokenize(&cell.text) {
+                    if let Some(set) = self.postings.get_mut(&term) {
+                        set.remove(&(slot, pos));
+                        if set.is_empty() {
+                            self.postings.remove(&term);
+                        }
+                    }
+                }
+            }
+        }
+        self.free.push(slot);
+    }
+
+    fn insert(&mut self, conv: &StoredConversation) {
+        self.remove(&conv.id);
+        let cells = conv
+            .notebook
+            .active_path()
+            .into_iter()
+            .filter_map(|c| cell_text(&c.content).map(|text| IndexedCell { id: c.id, text }))
+            .collect::<Vec<_>>();
+        let slot = self.free.pop().unwrap_or_else(|| {
+            self.docs.push(None);
+            self.docs.len() - 1
+        });
+        for (pos, cell) in cells.iter().enumerate() {
+            for term in tokenize(&cell.text) {
+                self.postings.entry(term).or_default().insert((slot, pos));
+            }
+        }
+        self.docs[slot] = Some(IndexedConversation {
+            id: conv.id.clone(),
+            title: conv.metadata.title.clone(),
+            modified_at: conv.metadata.modified_at,
+            cells,
+        });
+        self.slots.insert(conv.id.clone(), slot);
+    }
+
+    /// Cells containing every term, each as a prefix of some word.
+    fn matching(&self, terms: &[String]) -> HashSet<(usize, usize)> {
+        let mut result: Option<HashSet<(usize, usize)>> = None;
+        for term in terms {
+            let mut cells = HashSet::new();
+            for (_, set) in self
+                .postings
+                .range(term.clone()..)
+                .take_while(|(k, _)| k.starts_with(term.as_str()))
+            {
+                cells.extend(set.iter().copied());
+            }
+            result = Some(match result {
+                None => cells,
+                Some(acc) => acc.intersection(&cells).copied().collect(),
+            });
+        }
+        result.unwrap_or_default()
+    }
+
+    fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let terms = tokenize(query);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut found = self
+            .matching(&terms)
+            .into_iter()
+            .filter_map(|(slot, pos)| {
+                let doc = self.docs.get(slot)?.as_ref()?;
+                Some((doc, pos))
+            })
+            .collect::<Vec<_>>();
+        // Newest conversations first, then in reading order.
+        found.sort_by(|(a, ap), (b, bp)| b.modified_at.cmp(&a.modified_at).then(ap.cmp(bp)));
+
+        let mut per_doc: HashMap<&str, usize> = HashMap::new();
+        let mut hits = Vec::new();
+        for (doc, pos) in found {
+            let count = per_doc.entry(doc.id.as_str()).or_default();
+            if *count >= HITS_PER_CONVERSATION {
+                continue;
+            }
+            *count += 1;
+            let cell = &doc.cells[pos];
+            let (snippet, highlights) = snippet(&cell.text, &terms);
+            hits.push(SearchHit {
+                conversation_id: doc.id.clone(),
+                title: doc.title.clone(),
+                cell: cell.id,
+                snippet,
+                highlights,
+            });
+            if hits.len() >= limit {
+                break;
+            }
+        }
+        hits
+    }
+}
+
+/// Keep the index in step with a save. Before the first search there is
+/// nothing to update; the build will read the saved copy.
+pub fn note_saved(conv: &StoredConversation) {
+    INDEX.with(|idx| {
+        let mut idx = idx.borrow_mut();
+        if idx.started {
+            if idx.building {
+                idx.touched.insert(conv.id.clone());
+            }
+            idx.insert(conv);
+        }
+    });
+}
+
+pub fn note_deleted(id: &str) {
+    INDEX.with(|idx| {
+        let mut idx = idx.borrow_mut();
+        if idx.started {
+            if idx.building {
+                idx.touched.insert(id.to_string());
+            }
+            idx.remove(id);
+        }
+    });
+}
+
+/// Index every stored conversation, once. Later calls return immediately,
+/// including while the first build is still running.
+pub async fn ensure_built(storage: &AppStorage) {
+    let first = INDEX.with(|idx| {
+        let mut idx = idx.borrow_mut();
+        if idx.started {
+            return false;
+        }
+        idx.started = true;
+        idx.building = true;
+        true
+    });
+    if !first {
+        return;
+    }
+    let ids = storage
+        .list_conversations(u32::MAX)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|c| c.id)
+        .collect::<Vec<_>>();
+    for id in ids {
+        let Ok(Some(conv)) = storage.load_conversation(&id).await else {
+            continue;
+        };
+        INDEX.with(|idx| {
+            let mut idx = idx.borrow_mut();
+            if !idx.touched.contains(&conv.id) {
+                idx.insert(&conv);
+            }
+        });
+    }
+    INDEX.with(|idx| {
+        let mut idx = idx.borrow_mut();
+        idx.building = false;
+        idx.touched.clear();
+    });
+}
+
+pub fn search(query: &str, limit: usize) -> Vec<SearchHit> {
+    INDEX.with(|idx| idx.borrow().search(query, limit))
+}
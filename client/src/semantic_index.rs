@@ -0,0 +1,436 @@
+//! Semantic search over past conversations.
+//!
+//! Every finished exchange (a question and the reply to it) is embedded
+//! through `/api/embed` and the vectors stay in the browser: IndexedDB's
+//! `embeddings` store, or memory only when running on localStorage. Saves
+//! queue their conversation for a background pass; the first pass of a
+//! session also catches up on everything stored before.
+
+use crate::api::{ApiClient, ApiError};
+use crate::notebook::{CellContent, CellId};
+use crate::storage::{AppStorage, ConversationStore, StoredConversation};
+use gloo_timers::future::TimeoutFuture;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Exchanges embedded per request, well under the server's cap of 64.
+const BATCH: usize = 16;
+/// Embedded text is cut to this many chars; the start of a reply says
+/// enough about what it covers.
+const MAX_EMBED_CHARS: usize = 4000;
+/// Wait after a save before indexing, so a streaming reply isn't embedded
+/// over and over.
+const DEBOUNCE_MS: u32 = 2000;
+/// After a failed embed (server down, no model) hold off this long.
+const RETRY_AFTER_MS: f64 = 5.0 * 60.0 * 1000.0;
+const SNIPPET_CHARS: usize = 160;
+const MAX_CONVERSATIONS: usize = 10;
+const CELLS_PER_CONVERSATION: usize = 3;
+/// Below this cosine similarity an exchange isn't worth showing.
+const MIN_SCORE: f32 = 0.3;
+
+/// One embedded exchange, as stored.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExchangeEmbedding {
+    pub conversation_id: String,
+    /// The question's cell; hits jump here.
+    pub cell: CellId,
+    pub title: String,
+    pub snippet: String,
+    /// Hash of the embedded text, to spot exchanges edited since.
+    pub hash: String,
+    pub model: String,
+    pub vector: Vec<f32>,
+}
+
+impl ExchangeEmbedding {
+    pub fn key(&self) -> String {
+        record_key(&self.conversation_id, self.cell)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticCell {
+    pub cell: CellId,
+    pub snippet: String,
+    pub score: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SemanticHit {
+    pub conversation_id: String,
+    pub title: String,
+    /// Best score among `cells`.
+    pub score: f32,
+    pub cells: Vec<SemanticCell>,
+}
+
+struct Exchange {
+    cell: CellId,
+    snippet: String,
+    text: String,
+    hash: String,
+}
+
+#[derive(Default)]
+struct Indexer {
+    storage: Option<AppStorage>,
+    queue: VecDeque<String>,
+    running: bool,
+    backfilled: bool,
+    /// Every record by key, once read from storage.
+    records: Option<HashMap<String, ExchangeEmbedding>>,
+    /// Model the server embeds with, as of the last response.
+    model: Option<String>,
+    paused_until: f64,
+}
+
+thread_local! {
+    static INDEXER: RefCell<Indexer> = RefCell::new(Indexer::default());
+}
+
+fn record_key(conversation_id: &str, cell: CellId) -> String {
+    format!("{}\u{1f}{:08}", conversation_id, cell.0)
+}
+
+/// FNV-1a.
+pub fn hash(text: &str) -> u64 {
+    let mut h: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in text.bytes() {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x0100_0000_01b3);
+    }
+    h
+}
+
+fn snippet(text: &str) -> String {
+    let flat = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(SNIPPET_CHARS) {
+        Some((i, _)) => format!("{}…", &flat[..i]),
+        None => flat,
+    }
+}
+
+/// Each question with the first finished reply to it.
+fn exchanges(conv: &StoredConversation) -> Vec<Exchange> {
+    let mut out = Vec::new();
+    let mut question: Option<(CellId, &str)> = None;
+    for cell in conv.notebook.active_path() {
+        match &cell.content {
+            CellContent::UserInput { text } => question = Some((cell.id, text)),
+            CellContent::TextResponse {
+                text,
+                streaming: false,
+            } => {
+                if let Some((id, q)) = question.take() {
+                    let full = format!("User: {}\nAssistant: {}", q, text);
+                    let text = match full.char_indices().nth(MAX_EMBED_CHARS) {
+                        Some((i, _)) => full[..i].to_string(),
+                        None => full,
+                    };
+                    out.push(Exchange {
+                        cell: id,
+                        snippet: snippet(q),
+                        hash: format!("{:016x}", hash(&text)),
+                        text,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut na, mut nb) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        na += x * x;
+        nb += y * y;
+    }
+    if na == 0.0 || nb == 0.0 {
+        0.0
+    } else {
+        dot / (na.sqrt() * nb.sqrt())
+    }
+}
+
+fn describe(e: ApiError) -> String {
+    match e {
+        ApiError::Status(403) => "Semantic search is disabled on this server".to_string(),
+        e => e.to_string(),
+    }
+}
+
+async fn load_records(storage: &AppStorage) {
+    if INDEXER.with(|ix| ix.borrow().records.is_some()) {
+        return;
+    }
+    let loaded = match storage {
+        AppStorage::IndexedDb(s) => s.load_embeddings().await.unwrap_or_default(),
+        AppStorage::Local(_) => Vec::new(),
+    };
+    INDEXER.with(|ix| {
+        let mut ix = ix.borrow_mut();
+        if ix.records.is_none() {
+            ix.records = Some(loaded.into_iter().map(|r| (r.key(), r)).collect());
+        }
+    });
+}
+
+async fn put_records(storage: &AppStorage, records: Vec<ExchangeEmbedding>) {
+    if let AppStorage::IndexedDb(s) = storage {
+        if let Err(e) = s.save_embeddings(&records).await {
+            web_sys::console::warn_2(&"semantic index: save failed".into(), &e);
+        }
+    }
+    INDEXER.with(|ix| {
+        if let Some(map) = ix.borrow_mut().records.as_mut() {
+            map.extend(records.into_iter().map(|r| (r.key(), r)));
+        }
+    });
+}
+
+async fn remove_records(storage: &AppStorage, keys: Vec<String>) {
+    if keys.is_empty() {
+        return;
+    }
+    if let AppStorage::IndexedDb(s) = storage {
+        if let Err(e) = s.delete_embeddings(&keys).await {
+            web_sys::console::warn_2(&"semantic index: delete failed".into(), &e);
+        }
+    }
+    INDEXER.with(|ix| {
+        if let Some(map) = ix.borrow_mut().records.as_mut() {
+            for key in &keys {
+                map.remove(key);
+            }
+        }
+    });
+}
+
+/// Bring one conversation's records in line with what it holds now.
+async fn index_conversation(storage: &AppStorage, id: &str) -> Result<(), ApiError> {
+    let conv = storage.load_conversation(id).await.ok().flatten();
+    let (existing, model) = INDEXER.with(|ix| {
+        let ix = ix.borrow();
+        let existing = ix
+            .records
+            .iter()
+            .flat_map(|m| m.values())
+            .filter(|r| r.conversation_id == id)
+            .map(|r| (r.key(), r.clone()))
+            .collect::<HashMap<_, _>>();
+        (existing, ix.model.clone())
+    });
+    let title = conv
+        .as_ref()
+        .map(|c| c.metadata.title.clone())
+        .unwrap_or_default();
+    let wanted = conv.as_ref().map(exchanges).unwrap_or_default();
+
+    let wanted_keys = wanted
+        .iter()
+        .map(|e| record_key(id, e.cell))
+        .collect::<HashSet<_>>();
+    let mut retitled = Vec::new();
+    let mut todo = Vec::new();
+    for ex in wanted {
+        match existing.get(&record_key(id, ex.cell)) {
+            Some(r) if r.hash == ex.hash && model.as_ref().is_none_or(|m| *m == r.model) => {
+                if r.title != title {
+                    retitled.push(ExchangeEmbedding {
+                        title: title.clone(),
+                        ..r.clone()
+                    });
+                }
+            }
+            _ => todo.push(ex),
+        }
+    }
+    if !retitled.is_empty() {
+        put_records(storage, retitled).await;
+    }
+
+    for chunk in todo.chunks(BATCH) {
+        let texts = chunk.iter().map(|e| e.text.clone()).collect::<Vec<_>>();
+        let response = ApiClient::new().embed(&texts).await?;
+        INDEXER.with(|ix| ix.borrow_mut().model = Some(response.model.clone()));
+        let records = chunk
+            .iter()
+            .zip(response.embeddings)
+            .map(|(ex, vector)| ExchangeEmbedding {
+                conversation_id: id.to_string(),
+                cell: ex.cell,
+                title: title.clone(),
+                snippet: ex.snippet.clone(),
+                hash: ex.hash.clone(),
+                model: response.model.clone(),
+                vector,
+            })
+            .collect();
+        put_records(storage, records).await;
+    }
+
+    let stale = existing
+        .into_keys()
+        .filter(|k| !wanted_keys.contains(k))
+        .collect();
+    remove_records(storage, stale).await;
+    Ok(())
+}
+
+async fn run() {
+    TimeoutFuture::new(DEBOUNCE_MS).await;
+    let Some(storage) = INDEXER.with(|ix| ix.borrow().storage.clone()) else {
+        return;
+    };
+    load_records(&storage).await;
+
+    if !INDEXER.with(|ix| ix.borrow().backfilled) {
+        let mut ids = storage
+            .list_conversations(u32::MAX)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        INDEXER.with(|ix| {
+            let mut ix = ix.borrow_mut();
+            ix.backfilled = true;
+            // Records of conversations deleted while the app was closed.
+            let orphans = ix
+                .records
+                .iter()
+                .flat_map(|m| m.values())
+                .map(|r| r.conversation_id.clone())
+                .filter(|id| !ids.contains(id))
+                .collect::<HashSet<_>>();
+            ids.extend(orphans);
+            for id in ids {
+                if !ix.queue.contains(&id) {
+                    ix.queue.push_back(id);
+                }
+            }
+        });
+    }
+
+    while let Some(id) = INDEXER.with(|ix| ix.borrow_mut().queue.pop_front()) {
+        if let Err(e) = index_conversation(&storage, &id).await {
+            web_sys::console::warn_1(&format!("semantic index: {}", describe(e)).into());
+            INDEXER.with(|ix| {
+                let mut ix = ix.borrow_mut();
+                ix.queue.push_front(id);
+                ix.paused_until = js_sys::Date::now() + RETRY_AFTER_MS;
+            });
+            break;
+        }
+    }
+    INDEXER.with(|ix| ix.borrow_mut().running = false);
+}
+
+/// Start a background pass unless one is running or embedding recently
+/// failed.
+fn schedule(storage: &AppStorage) {
+    let start = INDEXER.with(|ix| {
+        let mut ix = ix.borrow_mut();
+        if ix.storage.is_none() {
+            ix.storage = Some(storage.clone());
+        }
+        if ix.running || js_sys::Date::now() < ix.paused_until {
+            return false;
+        }
+        ix.running = true;
+        true
+    });
+    if start {
+        leptos::spawn_local(run());
+    }
+}
+
+fn enqueue(storage: &AppStorage, id: &str) {
+    INDEXER.with(|ix| {
+        let mut ix = ix.borrow_mut();
+        if !ix.queue.iter().any(|q| q == id) {
+            ix.queue.push_back(id.to_string());
+        }
+    });
+    schedule(storage);
+}
+
+pub fn note_saved(storage: &AppStorage, conv: &StoredConversation) {
+    enqueue(storage, &conv.id);
+}
+
+pub fn note_deleted(storage: &AppStorage, id: &str) {
+    enqueue(storage, id);
+}
+
+/// Conversations whose exchanges are closest in meaning to `query`, best
+/// first. Only exchanges embedded with the server's current model count;
+/// the rest are queued to be embedded again.
+pub async fn search(storage: &AppStorage, query: &str) -> Result<Vec<SemanticHit>, String> {
+    let response = ApiClient::new()
+        .embed(&[query.to_string()])
+        .await
+        .map_err(describe)?;
+    let target = response
+        .embeddings
+        .into_iter()
+        .next()
+        .ok_or("The server returned no embedding")?;
+    load_records(storage).await;
+
+    let (scored, outdated) = INDEXER.with(|ix| {
+        let mut ix = ix.borrow_mut();
+        ix.model = Some(response.model.clone());
+        let mut scored = Vec::new();
+        let mut outdated = HashSet::new();
+        for r in ix.records.iter().flat_map(|m| m.values()) {
+            if r.model != response.model {
+                outdated.insert(r.conversation_id.clone());
+                continue;
+            }
+            let score = cosine(&target, &r.vector);
+            if score >= MIN_SCORE {
+                scored.push((score, r.clone()));
+            }
+        }
+        (scored, outdated)
+    });
+    for id in &outdated {
+        enqueue(storage, id);
+    }
+    schedule(storage);
+
+    let mut by_conv: HashMap<String, SemanticHit> = HashMap::new();
+    for (score, r) in scored {
+        let hit = by_conv
+            .entry(r.conversation_id.clone())
+            .or_insert_with(|| SemanticHit {
+                conversation_id: r.conversation_id.clone(),
+                title: r.title.clone(),
+                score,
+                cells: Vec::new(),
+            });
+        hit.score = hit.score.max(score);
+        hit.cells.push(SemanticCell {
+            cell: r.cell,
+            snippet: r.snippet,
+            score,
+        });
+    }
+    let mut hits = by_conv.into_values().collect::<Vec<_>>();
+    for hit in &mut hits {
+        hit.cells.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hit.cells.truncate(CELLS_PER_CONVERSATION);
+    }
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(MAX_CONVERSATIONS);
+    Ok(hits)
+}
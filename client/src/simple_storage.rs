@@ -1,134 +1,70 @@
-use crate::storage::{ConversationRef, StoredConversation};
+use crate::storage::{ConversationRef, ConversationStore, StoredConversation};
 use wasm_bindgen::prelude::*;
 use web_sys::{window, Storage};
 
-const MAX_CONVERSATIONS: usize = 25;
+const KEY_PREFIX: &str = "conversation_";
 
+/// Conversations under `conversation_{id}` keys in localStorage. Only used
+/// where IndexedDB can't be opened, and as the source of the one-time
+/// migration into it.
 #[derive(Clone)]
 pub struct SimpleStorage;
 
 impl SimpleStorage {
-    pub fn new() -> Self {
-        web_sys::console::log_1(&"Using localStorage for conversation storage".into());
-        Self
-    }
-
     fn get_storage() -> Option<Storage> {
         window()?.local_storage().ok()?
     }
 
-    pub fn save_conversation(&self, conversation: &StoredConversation) -> Result<(), JsValue> {
+    /// Ids of every conversation stored here.
+    pub fn stored_ids(&self) -> Result<Vec<String>, JsValue> {
         let storage = Self::get_storage().ok_or("No localStorage")?;
-
-        // Check if we're at the conversation limit
-        let all_conversations = self.list_conversations(100)?; // Get all conversations
-        if all_conversations.len() >= MAX_CONVERSATIONS {
-            // Check if this is a new conversation (not already in the list)
-            let is_new = !all_conversations.iter().any(|c| c.id == conversation.id);
-
-            if is_new {
-                web_sys::console::log_1(
-                    &format!(
-                        "At conversation limit ({}), removing oldest",
-                        MAX_CONVERSATIONS
-                    )
-                    .into(),
-                );
-
-                // Find and remove the oldest conversation
-                if let Some(oldest) = all_conversations.last() {
-                    web_sys::console::log_1(
-                        &format!(
-                            "Removing oldest conversation: {} ({})",
-                            oldest.title, oldest.modified_at
-                        )
-                        .into(),
-                    );
-                    self.delete_conversation(&oldest.id)?;
-                }
+        let mut ids = Vec::new();
+        for i in 0..storage.length()? {
+            if let Some(id) = storage
+                .key(i)?
+                .and_then(|k| k.strip_prefix(KEY_PREFIX).map(String::from))
+            {
+                ids.push(id);
             }
         }
+        Ok(ids)
+    }
+}
 
-        let key = format!("conversation_{}", conversation.id);
+impl ConversationStore for SimpleStorage {
+    async fn save_conversation(&self, conversation: &StoredConversation) -> Result<(), JsValue> {
+        let storage = Self::get_storage().ok_or("No localStorage")?;
         let value =
             serde_json::to_string(conversation).map_err(|e| JsValue::from_str(&e.to_string()))?;
-
-        web_sys::console::log_1(&format!("Saving to localStorage key: {}", key).into());
-        storage.set_item(&key, &value)?;
-        web_sys::console::log_1(&"Saved to localStorage".into());
-        Ok(())
+        storage.set_item(&format!("{KEY_PREFIX}{}", conversation.id), &value)
     }
 
-    pub fn load_conversation(&self, id: &str) -> Result<Option<StoredConversation>, JsValue> {
+    async fn load_conversation(&self, id: &str) -> Result<Option<StoredConversation>, JsValue> {
         let storage = Self::get_storage().ok_or("No localStorage")?;
-        let key = format!("conversation_{}", id);
-
-        web_sys::console::log_1(&format!("Loading from localStorage key: {}", key).into());
-
-        match storage.get_item(&key)? {
-            Some(value) => {
-                let conversation =
-                    serde_json::from_str(&value).map_err(|e| JsValue::from_str(&e.to_string()))?;
-                web_sys::console::log_1(&"Loaded from localStorage".into());
-                Ok(Some(conversation))
-            }
-            None => {
-                web_sys::console::log_1(&"No conversation found in localStorage".into());
-                Ok(None)
-            }
+        match storage.get_item(&format!("{KEY_PREFIX}{id}"))? {
+            Some(value) => serde_json::from_str(&value)
+                .map(Some)
+                .map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(None),
         }
     }
 
-    pub fn list_conversations(&self, limit: u32) -> Result<Vec<ConversationRef>, JsValue> {
-        let storage = Self::get_storage().ok_or("No localStorage")?;
+    async fn list_conversations(&self, limit: u32) -> Result<Vec<ConversationRef>, JsValue> {
         let mut conversations = Vec::new();
-
-        // Iterate through all keys in localStorage
-        let length = storage.length()?;
-        for i in 0..length {
-            if let Some(key) = storage.key(i)? {
-                if key.starts_with("conversation_") {
-                    if let Some(value) = storage.get_item(&key)? {
-                        if let Ok(conv) = serde_json::from_str::<StoredConversation>(&value) {
-                            // Extract first user message for preview
-                            let preview = conv
-                                .notebook
-                                .cells
-                                .iter()
-                                .find_map(|cell| match &cell.content {
-                                    crate::notebook::CellContent::UserInput { text } => {
-                                        Some(text.clone())
-                                    }
-                                    _ => None,
-                                })
-                                .unwrap_or_else(|| "Empty conversation".to_string());
-
-                            conversations.push(ConversationRef {
-                                id: conv.id,
-                                title: conv.metadata.title,
-                                modified_at: conv.metadata.modified_at,
-                                preview: preview.chars().take(50).collect::<String>()
-                                    + if preview.len() > 50 { "..." } else { "" },
-                            });
-                        }
-                    }
-                }
+        for id in self.stored_ids()? {
+            if let Ok(Some(conv)) = self.load_conversation(&id).await {
+                conversations.push(ConversationRef::from(&conv));
             }
         }
 
         // Sort by modified date (newest first)
         conversations.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
-
-        // Apply limit
         conversations.truncate(limit as usize);
-
         Ok(conversations)
     }
 
-    pub fn delete_conversation(&self, id: &str) -> Result<(), JsValue> {
+    async fn delete_conversation(&self, id: &str) -> Result<(), JsValue> {
         let storage = Self::get_storage().ok_or("No localStorage")?;
-        let key = format!("conversation_{}", id);
-        storage.remove_item(&key)?;
-        Ok(())
+        storage.remove_item(&format!("{KEY_PREFIX}{id}"))
     }
 }
@@ -1,6 +1,15 @@
-use crate::notebook::Notebook;
+use crate::api::{ApiClient, ApiError};
+use crate::components::context_manager::{ContextStrategy, Override};
+use crate::idb_storage::IdbStorage;
+use crate::notebook::{CellContent, Notebook};
+use crate::search_index;
+use crate::semantic_index;
+use crate::simple_storage::SimpleStorage;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::JsFuture;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoredConversation {
@@ -10,21 +19,59 @@ pub struct StoredConversation {
     pub metadata: ConversationMetadata,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl StoredConversation {
+    /// True when the two differ only in `modified_at`, so saving `self` over
+    /// `other` would change nothing but the timestamp sync resolves by.
+    pub fn same_content(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.notebook == other.notebook
+            && self.context_state == other.context_state
+            && ConversationMetadata {
+                modified_at: other.metadata.modified_at,
+                ..self.metadata.clone()
+            } == other.metadata
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ContextState {
     pub compressed_summaries: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compressed_spans: Vec<Vec<crate::api::ChatMessage>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<Override>,
     pub active_messages: Vec<crate::api::ChatMessage>,
     pub total_tokens: usize,
     pub compression_count: u32,
+    #[serde(default)]
+    pub strategy: ContextStrategy,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ConversationMetadata {
     pub created_at: DateTime<Utc>,
     pub modified_at: DateTime<Utc>,
     pub title: String,
     pub model: String,
     pub provider: String,
+    /// Persona the chat was held with, for exports.
+    #[serde(default)]
+    pub persona: String,
+    /// Values entered for the persona's `{{placeholders}}` in this chat.
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Set on a batch job's results, which are appended as they arrive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<JobLink>,
+}
+
+/// The server-side job a conversation mirrors, and how far it has been
+/// imported.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JobLink {
+    pub id: String,
+    /// `seq` of the last result appended.
+    pub last_seq: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,11 +80,242 @@ pub struct ConversationRef {
     pub title: String,
     pub modified_at: DateTime<Utc>,
     pub preview: String,
+    /// Evicted from this browser; opening it fetches it from the server.
+    #[serde(default)]
+    pub server_only: bool,
+}
+
+impl From<&StoredConversation> for ConversationRef {
+    fn from(conv: &StoredConversation) -> Self {
+        let preview = conv
+            .notebook
+            .active_path()
+            .into_iter()
+            .find_map(|cell| match &cell.content {
+                CellContent::UserInput { text } => Some(text.as_str()),
+                _ => None,
+            })
+            .unwrap_or("Empty conversation");
+        let mut short = preview.chars().take(50).collect::<String>();
+        if short.len() < preview.len() {
+            short.push_str("...");
+        }
+        Self {
+            id: conv.id.clone(),
+            title: conv.metadata.title.clone(),
+            modified_at: conv.metadata.modified_at,
+            preview: short,
+            server_only: false,
+        }
+    }
+}
+
+/// Where conversations live in the browser.
+// The client is single-threaded WASM, so the futures never need to be `Send`.
+#[allow(async_fn_in_trait)]
+pub trait ConversationStore {
+    async fn save_conversation(&self, conversation: &StoredConversation) -> Result<(), JsValue>;
+    async fn load_conversation(&self, id: &str) -> Result<Option<StoredConversation>, JsValue>;
+    /// Newest first.
+    async fn list_conversations(&self, limit: u32) -> Result<Vec<ConversationRef>, JsValue>;
+    async fn delete_conversation(&self, id: &str) -> Result<(), JsValue>;
+}
+
+/// The store the app runs on: IndexedDB, or localStorage in browsers that
+/// refuse to open it (some private-browsing modes).
+#[derive(Clone)]
+pub enum AppStorage {
+    IndexedDb(IdbStorage),
+    Local(SimpleStorage),
+}
+
+impl AppStorage {
+    pub async fn open() -> Self {
+        match IdbStorage::open().await {
+            Ok(idb) => Self::IndexedDb(idb),
+            Err(e) => {
+                web_sys::console::warn_2(
+                    &"storage: IndexedDB unavailable, using localStorage".into(),
+                    &e,
+                );
+                Self::Local(SimpleStorage)
+            }
+        }
+    }
+}
+
+impl ConversationStore for AppStorage {
+    async fn save_conversation(&self, conversation: &StoredConversation) -> Result<(), JsValue> {
+        match self {
+            Self::IndexedDb(s) => s.save_conversation(conversation).await,
+            Self::Local(s) => s.save_conversation(conversation).await,
+        }?;
+        search_index::note_saved(conversation);
+        semantic_index::note_saved(self, conversation);
+        Ok(())
+    }
+
+    async fn load_conversation(&self, id: &str) -> Result<Option<StoredConversation>, JsValue> {
+        match self {
+            Self::IndexedDb(s) => s.load_conversation(id).await,
+            Self::Local(s) => s.load_conversation(id).await,
+        }
+    }
+
+    async fn list_conversations(&self, limit: u32) -> Result<Vec<ConversationRef>, JsValue> {
+        match self {
+            Self::IndexedDb(s) => s.list_conversations(limit).await,
+            Self::Local(s) => s.list_conversations(limit).await,
+        }
+    }
+
+    async fn delete_conversation(&self, id: &str) -> Result<(), JsValue> {
+        match self {
+            Self::IndexedDb(s) => s.delete_conversation(id).await,
+            Self::Local(s) => s.delete_conversation(id).await,
+        }?;
+        search_index::note_deleted(id);
+        semantic_index::note_deleted(self, id);
+        Ok(())
+    }
+}
+
+/// Share of the origin's quota at which the user is warned.
+pub const WARN_USAGE: f64 = 0.8;
+/// Share at which the oldest conversations start being removed.
+pub const EVICT_USAGE: f64 = 0.95;
+/// localStorage key listing the conversations `check_quota` removed, so the
+/// sidebar keeps them until they are opened again or deleted.
+const EVICTED_KEY: &str = "evicted_conversations";
+
+/// Whether a write failed because the browser's storage is full.
+pub fn is_quota_error(err: &JsValue) -> bool {
+    use wasm_bindgen::JsCast;
+    err.dyn_ref::<web_sys::DomException>()
+        .is_some_and(|e| e.name() == "QuotaExceededError")
+}
+
+/// Fraction of the origin's storage quota in use, or `None` where the
+/// browser doesn't say.
+pub async fn storage_usage() -> Option<f64> {
+    let estimate = web_sys::window()?.navigator().storage().estimate().ok()?;
+    let estimate = JsFuture::from(estimate).await.ok()?;
+    let field = |name: &str| js_sys::Reflect::get(&estimate, &name.into()).ok()?.as_f64();
+    let (usage, quota) = (field("usage")?, field("quota")?);
+    (quota > 0.0).then(|| usage / quota)
+}
+
+pub enum QuotaCheck {
+    Fine,
+    /// Above `WARN_USAGE`; nothing was removed.
+    Warn(f64),
+    /// Above `EVICT_USAGE`; the conversation with this title was removed.
+    Evicted(String),
+}
+
+/// Check storage pressure after a save. Past `EVICT_USAGE`, and only once the
+/// user has already been warned, removes the single oldest conversation the
+/// server has acknowledged in its current version, so nothing unsynced is
+/// lost, and lists it as server-only (see `restore_evicted`). `keep` (the
+/// open conversation) is never removed.
+pub async fn check_quota(storage: &AppStorage, keep: &str, warned: bool) -> QuotaCheck {
+    let Some(usage) = storage_usage().await else {
+        return QuotaCheck::Fine;
+    };
+    if usage < WARN_USAGE {
+        return QuotaCheck::Fine;
+    }
+    if usage < EVICT_USAGE || !warned {
+        return QuotaCheck::Warn(usage);
+    }
+    let Ok(all) = storage.list_conversations(u32::MAX).await else {
+        return QuotaCheck::Warn(usage);
+    };
+    let oldest = all.into_iter().rev().find(|c| {
+        c.id != keep && crate::sync::is_acknowledged(&c.id, c.modified_at.timestamp_millis())
+    });
+    match oldest {
+        // Not `sync::mark_deleted`: the server copy must survive.
+        Some(c) if storage.delete_conversation(&c.id).await.is_ok() => {
+            let mut evicted = evicted_conversations();
+            evicted.retain(|e| e.id != c.id);
+            evicted.push(ConversationRef {
+                server_only: true,
+                ..c.clone()
+            });
+            save_evicted(&evicted);
+            QuotaCheck::Evicted(c.title)
+        }
+        _ => QuotaCheck::Warn(usage),
+    }
+}
+
+/// Conversations evicted from this browser that the server still holds.
+pub fn evicted_conversations() -> Vec<ConversationRef> {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|s| s.get_item(EVICTED_KEY).ok().flatten())
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_evicted(evicted: &[ConversationRef]) {
+    if let Some(s) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        if let Ok(v) = serde_json::to_string(evicted) {
+            let _ = s.set_item(EVICTED_KEY, &v);
+        }
+    }
+}
+
+/// Stop listing `id` as evicted: it is stored here again or was deleted.
+/// Returns whether it was listed.
+pub fn forget_evicted(id: &str) -> bool {
+    let mut evicted = evicted_conversations();
+    let before = evicted.len();
+    evicted.retain(|e| e.id != id);
+    if evicted.len() == before {
+        return false;
+    }
+    save_evicted(&evicted);
+    true
+}
+
+/// Fetch an evicted conversation back from the server and store it here
+/// again. `None` when `id` was never evicted or the server no longer has it.
+/// Sync can't bring it back: its cursor is already past the server's copy.
+pub async fn restore_evicted(
+    storage: &AppStorage,
+    id: &str,
+) -> Result<Option<StoredConversation>, ApiError> {
+    if !evicted_conversations().iter().any(|e| e.id == id) {
+        return Ok(None);
+    }
+    let record = match ApiClient::new().get_conversation(id).await {
+        Ok(record) => record,
+        Err(ApiError::Status(404)) => {
+            forget_evicted(id);
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    let Some(data) = record.data.filter(|_| !record.deleted) else {
+        forget_evicted(id);
+        return Ok(None);
+    };
+    let conv: StoredConversation =
+        serde_json::from_value(data).map_err(|e| ApiError::Network(e.to_string()))?;
+    // Still listed if it doesn't fit yet, so it can be fetched again.
+    if storage.save_conversation(&conv).await.is_ok() {
+        crate::sync::mark_acknowledged(id, record.modified_at);
+        forget_evicted(id);
+    }
+    Ok(Some(conv))
 }
 
-// Rough approximation: ~4 characters per token for English.
+/// Exact when the server has counted `text` with the model's tokenizer,
+/// estimated otherwise; see `token_count`.
 pub fn estimate_tokens(text: &str) -> usize {
-    text.len() / 4
+    crate::token_count::count(text)
 }
 
 pub fn estimate_message_tokens(message: &crate::api::ChatMessage) -> usize {
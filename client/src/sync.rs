@@ -0,0 +1,227 @@
+use crate::api::{ApiClient, ApiError, ConversationRecord, SyncRequest};
+use crate::storage::{ConversationStore, StoredConversation};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const STATE_KEY: &str = "sync_state";
+/// Conversations pushed per round, so a large import goes up over several
+/// syncs instead of one request past the server's body limit.
+const MAX_PUSH: usize = 25;
+
+/// Bookkeeping for syncing local conversations with the server, kept in
+/// localStorage next to the conversations themselves.
+#[derive(Serialize, Deserialize, Default)]
+struct SyncState {
+    /// Server revision we have pulled up to.
+    cursor: i64,
+    /// Conversations saved locally since they were last pushed.
+    dirty: HashSet<String>,
+    /// Local deletions not yet pushed, with when they happened (ms).
+    tombstones: HashMap<String, i64>,
+    /// `modified_at` (ms) of the copy of each conversation the server has
+    /// acknowledged, by accepting a push or sending it in a pull. Only a
+    /// conversation whose local copy matches may be evicted.
+    #[serde(default)]
+    acked: HashMap<String, i64>,
+}
+
+fn load_state() -> SyncState {
+    web_sys::window()
+        .and_then(|w| w.local_storage().ok().flatten())
+        .and_then(|s| s.get_item(STATE_KEY).ok().flatten())
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_state(state: &SyncState) {
+    if let Some(s) = web_sys::window().and_then(|w| w.local_storage().ok().flatten()) {
+        if let Ok(v) = serde_json::to_string(state) {
+            let _ = s.set_item(STATE_KEY, &v);
+        }
+    }
+}
+
+/// Queue a locally saved conversation for the next push.
+pub fn mark_dirty(id: &str) {
+    let mut state = load_state();
+    if state.dirty.insert(id.to_string()) {
+        save_state(&state);
+    }
+}
+
+/// Queue a user-initiated delete for the next push. Local eviction of old
+/// conversations must not call this, or the server copy would be lost too.
+pub fn mark_deleted(id: &str) {
+    let mut state = load_state();
+    state.dirty.remove(id);
+    state.acked.remove(id);
+    state
+        .tombstones
+        .insert(id.to_string(), chrono::Utc::now().timestamp_millis());
+    save_state(&state);
+}
+
+/// Whether the server holds this exact version of `id`, so dropping the
+/// local copy loses nothing.
+pub fn is_acknowledged(id: &str, modified_at: i64) -> bool {
+    load_state().acked.get(id) == Some(&modified_at)
+}
+
+/// Record that the server holds `id` at `modified_at`, for a copy fetched
+/// outside a sync round.
+pub fn mark_acknowledged(id: &str, modified_at: i64) {
+    let mut state = load_state();
+    state.acked.insert(id.to_string(), modified_at);
+    save_state(&state);
+}
+
+/// Conversations changed locally by a pull.
+#[derive(Default)]
+pub struct SyncOutcome {
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    /// Every deletion pulled, including of conversations this device never
+    /// had.
+    pub tombstones: Vec<String>,
+}
+
+/// Push pending local changes and apply the server's changes since the last
+/// cursor. Either side keeps whichever copy has the newer `modified_at`.
+/// Failures leave the pending state untouched for the next attempt.
+pub async fn sync(storage: &impl ConversationStore) -> Result<SyncOutcome, ApiError> {
+    let mut state = load_state();
+
+    // Anything the server hasn't acknowledged goes up too, even if it was
+    // never marked dirty: conversations from before sync, or written by code
+    // that doesn't mark them.
+    if let Ok(local) = storage.list_conversations(u32::MAX).await {
+        let ids: HashSet<&str> = local.iter().map(|c| c.id.as_str()).collect();
+        state.acked.retain(|id, _| ids.contains(id.as_str()));
+        for c in &local {
+            if state.acked.get(&c.id) != Some(&c.modified_at.timestamp_millis()) {
+                state.dirty.insert(c.id.clone());
+            }
+        }
+        save_state(&state);
+    }
+
+    let mut changes = Vec::new();
+    let mut pushed = HashMap::new();
+    let mut deferred = HashSet::new();
+    for id in &state.dirty {
+        if pushed.len() >= MAX_PUSH {
+            deferred.insert(id.clone());
+            continue;
+        }
+        let Ok(Some(conv)) = storage.load_conversation(id).await else {
+            continue;
+        };
+        let modified_at = conv.metadata.modified_at.timestamp_millis();
+        let data = serde_json::to_value(&conv).map_err(|e| ApiError::Network(e.to_string()))?;
+        changes.push(ConversationRecord {
+            id: id.clone(),
+            modified_at,
+            deleted: false,
+            data: Some(data),
+        });
+        pushed.insert(id.clone(), modified_at);
+    }
+    for (id, at) in &state.tombstones {
+        changes.push(ConversationRecord {
+            id: id.clone(),
+            modified_at: *at,
+            deleted: true,
+            data: None,
+        });
+    }
+
+    let response = ApiClient::new()
+        .sync_conversations(&SyncRequest {
+            since: state.cursor,
+            changes,
+        })
+        .await?;
+
+    let mut outcome = SyncOutcome::default();
+    let mut acked = HashMap::new();
+    let mut incoming = response.changes;
+    // Oldest first, so local eviction keeps the newest conversations.
+    incoming.sort_by_key(|c| c.modified_at);
+    for record in incoming {
+        if record.deleted {
+            outcome.tombstones.push(record.id.clone());
+            if crate::storage::forget_evicted(&record.id) {
+                outcome.deleted.push(record.id.clone());
+            }
+        }
+        let local = storage.load_conversation(&record.id).await.ok().flatten();
+        let local_at = local
+            .as_ref()
+            .map(|c| c.metadata.modified_at.timestamp_millis());
+        if local_at.is_some_and(|at| at >= record.modified_at) {
+            if local_at == Some(record.modified_at) && !record.deleted {
+                acked.insert(record.id, Some(record.modified_at));
+            }
+            continue;
+        }
+        if record.deleted {
+            acked.insert(record.id.clone(), None);
+            if local.is_some() && storage.delete_conversation(&record.id).await.is_ok() {
+                outcome.deleted.push(record.id);
+            }
+            continue;
+        }
+        let Some(data) = record.data else { continue };
+        match serde_json::from_value::<StoredConversation>(data) {
+            Ok(conv) => {
+                if storage.save_conversation(&conv).await.is_ok() {
+                    crate::storage::forget_evicted(&record.id);
+                    acked.insert(record.id.clone(), Some(record.modified_at));
+                    outcome.updated.push(record.id);
+                }
+            }
+            Err(e) => {
+                web_sys::console::error_1(
+                    &format!("sync: skipping conversation {}: {}", record.id, e).into(),
+                );
+            }
+        }
+    }
+
+    // A conversation saved again during the round trip stays dirty.
+    let mut resaved = HashSet::new();
+    for (id, at) in &pushed {
+        let current = storage.load_conversation(id).await.ok().flatten();
+        if current.is_some_and(|c| c.metadata.modified_at.timestamp_millis() != *at) {
+            resaved.insert(id.clone());
+        }
+    }
+    // Re-read state last, with no awaits after, so no mark_dirty is lost.
+    let mut next = load_state();
+    next.cursor = response.cursor;
+    next.dirty.retain(|id| match pushed.get(id) {
+        Some(_) => resaved.contains(id),
+        None => !state.dirty.contains(id) || deferred.contains(id),
+    });
+    // A push that lost to a newer server copy stays unacknowledged, so it
+    // is never evicted as if the server had it.
+    for id in &response.acked {
+        if let Some(at) = pushed.get(id) {
+            acked.entry(id.clone()).or_insert(Some(*at));
+        }
+    }
+    for (id, at) in acked {
+        match at {
+            Some(at) => next.acked.insert(id, at),
+            None => next.acked.remove(&id),
+        };
+    }
+    for (id, at) in &state.tombstones {
+        if next.tombstones.get(id) == Some(at) {
+            next.tombstones.remove(id);
+        }
+    }
+    save_state(&next);
+
+    Ok(outcome)
+}
@@ -0,0 +1,167 @@
+//! Token counts from the server's tokenizer for the selected model.
+//!
+//! `count` answers at once: the exact count when this text has been
+//! counted before, otherwise the `len / 4` estimate while the text is
+//! queued for `/api/tokenize`. When a batch comes back the listener runs so
+//! the context meter and compression thresholds can be recomputed. Models
+//! without a tokenizer on the server (or servers without any) stay on the
+//! estimate.
+
+use crate::api::{ApiClient, ApiError};
+use crate::semantic_index::hash;
+use gloo_timers::future::TimeoutFuture;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Texts per request, under the server's cap of 256.
+const BATCH: usize = 128;
+/// Collect the texts of one render before asking.
+const DEBOUNCE_MS: u32 = 150;
+/// After a failed request hold off this long.
+const RETRY_AFTER_MS: f64 = 60.0 * 1000.0;
+/// Shorter texts aren't worth a round trip.
+const MIN_CHARS: usize = 16;
+/// Forget everything past this many counts or queued texts.
+const MAX_ENTRIES: usize = 20_000;
+
+#[derive(Default)]
+struct Counter {
+    model: String,
+    counts: HashMap<u64, usize>,
+    queue: Vec<String>,
+    queued: HashSet<u64>,
+    running: bool,
+    /// The server has no tokenizer for `model`.
+    unavailable: bool,
+    paused_until: f64,
+    listener: Option<Rc<dyn Fn()>>,
+}
+
+thread_local! {
+    static COUNTER: RefCell<Counter> = RefCell::new(Counter::default());
+}
+
+/// The rough estimate used until (or unless) the server counts `text`.
+pub fn estimate(text: &str) -> usize {
+    text.len() / 4
+}
+
+/// Called whenever new exact counts arrive or the model changes.
+pub fn on_counted(listener: impl Fn() + 'static) {
+    COUNTER.with(|c| c.borrow_mut().listener = Some(Rc::new(listener)));
+}
+
+/// Counts belong to one model's tokenizer; switching starts over.
+pub fn set_model(model: &str) {
+    let listener = COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        if c.model == model {
+            return None;
+        }
+        let listener = c.listener.clone();
+        *c = Counter {
+            model: model.to_string(),
+            listener: listener.clone(),
+            ..Counter::default()
+        };
+        listener
+    });
+    if let Some(listener) = listener {
+        listener();
+    }
+}
+
+pub fn count(text: &str) -> usize {
+    if text.len() < MIN_CHARS {
+        return estimate(text);
+    }
+    let key = hash(text);
+    let (known, start) = COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        if let Some(n) = c.counts.get(&key) {
+            return (Some(*n), false);
+        }
+        if c.model.is_empty() || c.unavailable || c.queue.len() >= MAX_ENTRIES {
+            return (None, false);
+        }
+        if c.queued.insert(key) {
+            c.queue.push(text.to_string());
+        }
+        let start = !c.running && js_sys::Date::now() >= c.paused_until;
+        if start {
+            c.running = true;
+        }
+        (None, start)
+    });
+    if start {
+        leptos::spawn_local(run());
+    }
+    known.unwrap_or_else(|| estimate(text))
+}
+
+async fn run() {
+    TimeoutFuture::new(DEBOUNCE_MS).await;
+    let mut counted = false;
+    loop {
+        let (model, batch) = COUNTER.with(|c| {
+            let mut c = c.borrow_mut();
+            let take = c.queue.len().min(BATCH);
+            let batch: Vec<String> = c.queue.drain(..take).collect();
+            (c.model.clone(), batch)
+        });
+        if batch.is_empty() {
+            break;
+        }
+        let result = ApiClient::new().tokenize(&model, &batch).await;
+        let stop = COUNTER.with(|c| {
+            let mut c = c.borrow_mut();
+            for text in &batch {
+                c.queued.remove(&hash(text));
+            }
+            // The model changed while this was in flight.
+            if c.model != model {
+                return false;
+            }
+            match result {
+                Ok(counts) => {
+                    if c.counts.len() + counts.len() > MAX_ENTRIES {
+                        c.counts.clear();
+                    }
+                    for (text, n) in batch.iter().zip(counts) {
+                        c.counts.insert(hash(text), n);
+                    }
+                    counted = true;
+                    false
+                }
+                // 403: no tokenizers at all; 404: none for this model.
+                Err(ApiError::Status(403 | 404)) => {
+                    c.unavailable = true;
+                    c.queue.clear();
+                    c.queued.clear();
+                    true
+                }
+                Err(e) => {
+                    web_sys::console::warn_1(&format!("tokenize: {}", e).into());
+                    c.paused_until = js_sys::Date::now() + RETRY_AFTER_MS;
+                    c.queue.clear();
+                    c.queued.clear();
+                    true
+                }
+            }
+        });
+        if stop {
+            break;
+        }
+    }
+    let listener = COUNTER.with(|c| {
+        let mut c = c.borrow_mut();
+        c.running = false;
+        c.listener.clone()
+    });
+    if counted {
+        if let Some(listener) = listener {
+            listener();
+        }
+    }
+}
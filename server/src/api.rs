@@ -1,37 +1,46 @@
 use anyhow::anyhow;
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::{
-        header::{COOKIE, SET_COOKIE},
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE, COOKIE, SET_COOKIE},
         HeaderMap, HeaderValue, StatusCode,
     },
     middleware,
-    response::{sse::Event, AppendHeaders, IntoResponse, Redirect, Response, Sse},
-    routing::{get, post},
+    response::{
+        sse::{Event, KeepAlive},
+        AppendHeaders, IntoResponse, Redirect, Response, Sse,
+    },
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use cookie::Cookie;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::{
-    convert::Infallible,
-    sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
-};
+use std::{collections::HashMap, convert::Infallible, sync::Arc};
+use tokio::sync::broadcast::error::RecvError;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 use crate::{
     auth::{
         auth_middleware, clear_session_cookie, clear_tx_cookie,
         oidc::{pkce_challenge, random_b64_url},
+        optional_auth_middleware,
         session::{open, seal},
         session_cookie, tx_cookie, AuthUser, SessionPayload, TxPayload, SESSION_COOKIE, TX_COOKIE,
     },
+    clock::now_secs,
     error::AppError,
-    providers::ChatRequest,
+    jobs::{self, input::InputFormat, JobEvent},
+    prompts::{self, PromptOptions, PromptStatus, PromptVariable, SystemPrompt, TemplateContext},
+    providers::{strip_thinking, ChatMessage, ChatRequest},
+    sandbox::{Language, RunOutput},
+    store::{
+        conversations::{ConversationRecord, ConversationSummary},
+        jobs::{Job, JobSpec, JobStatus, NewJob},
+        shares::{NewShare, Share, SharedConversation},
+    },
     AppState,
 };
-use std::fs;
 
 pub fn routes(state: Arc<AppState>) -> Router {
     let public = Router::new()
@@ -41,18 +50,50 @@ pub fn routes(state: Arc<AppState>) -> Router {
         .route("/auth/logout", post(auth_logout))
         .with_state(state.clone());
 
+    // Readable anonymously unless the owner restricted the link.
+    let shared = Router::new()
+        .route("/shared/:token", get(get_shared))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            optional_auth_middleware,
+        ))
+        .with_state(state.clone());
+
     let protected = Router::new()
         .route("/me", get(me))
         .route("/providers", get(list_providers))
-        .route("/prompts", get(list_prompts))
+        .route("/prompts", get(list_prompts).post(create_persona))
+        .route("/prompts/:id", put(update_persona).delete(delete_persona))
+        .route("/conversations", get(list_conversations))
+        .route("/conversations/sync", post(sync_conversations))
+        .route(
+            "/conversations/:id",
+            get(get_conversation)
+                .put(put_conversation)
+                .delete(delete_conversation),
+        )
+        .route("/shares", get(list_shares).post(create_share))
+        .route("/shares/:token", delete(delete_share))
+        .route("/jobs", get(list_jobs).post(create_job))
+        .route("/jobs/events", get(job_events))
+        .route("/jobs/:id", delete(delete_job))
+        .route("/jobs/:id/cancel", post(cancel_job))
+        .route("/jobs/:id/results", get(job_results))
         .route("/chat", post(chat))
+        .route("/embed", post(embed))
+        .route("/summarize", post(summarize))
+        .route("/tokenize", post(tokenize))
+        .route("/feedback", post(post_feedback))
+        .route("/sandbox", get(sandbox_status))
+        .route("/sandbox/run", post(sandbox_run))
+        .route("/admin/health", get(admin_health))
         .route_layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
         ))
         .with_state(state);
 
-    public.merge(protected)
+    public.merge(shared).merge(protected)
 }
 
 #[derive(Serialize)]
@@ -83,7 +124,16 @@ async fn health(State(state): State<Arc<AppState>>) -> Result<Json<HealthRespons
     }))
 }
 
-async fn auth_login(State(state): State<Arc<AppState>>) -> Result<Response, AppError> {
+#[derive(Deserialize)]
+struct LoginQuery {
+    #[serde(default)]
+    return_to: Option<String>,
+}
+
+async fn auth_login(
+    State(state): State<Arc<AppState>>,
+    Query(q): Query<LoginQuery>,
+) -> Result<Response, AppError> {
     let code_verifier = random_b64_url(32);
     let code_challenge = pkce_challenge(&code_verifier);
     let state_tok = random_b64_url(16);
@@ -93,6 +143,7 @@ async fn auth_login(State(state): State<Arc<AppState>>) -> Result<Response, AppE
         code_verifier,
         state: state_tok.clone(),
         nonce: nonce.clone(),
+        return_to: q.return_to.as_deref().and_then(same_origin_path),
     };
     let sealed = seal(&state.config.auth.session_key, TX_COOKIE.as_bytes(), &tx)?;
     let url = state
@@ -103,6 +154,21 @@ async fn auth_login(State(state): State<Arc<AppState>>) -> Result<Response, AppE
     Ok((AppendHeaders([(SET_COOKIE, cookie)]), Redirect::to(&url)).into_response())
 }
 
+/// `return_to` as a same-origin path and query, or `None`. Browsers drop
+/// tabs and newlines and read `\` as `/`, so `/\t/evil.com` would become
+/// `//evil.com`; anything with those or other characters outside printable
+/// ASCII, or that isn't a bare absolute path, is refused rather than
+/// cleaned up. What passes is a valid `Location` header.
+fn same_origin_path(raw: &str) -> Option<String> {
+    if raw.chars().any(|c| !c.is_ascii_graphic() || c == '\\') {
+        return None;
+    }
+    let uri: axum::http::Uri = raw.parse().ok()?;
+    let path = uri.path_and_query()?.as_str();
+    let bare = uri.scheme().is_none() && uri.authority().is_none() && path == raw;
+    (bare && path.starts_with('/') && !path.starts_with("//")).then(|| path.to_string())
+}
+
 #[derive(Deserialize)]
 struct CallbackQuery {
     code: String,
@@ -162,7 +228,7 @@ async fn auth_callback(
     let clear_tx_h = header_value(&clear_tx_cookie().to_string())?;
     Ok((
         AppendHeaders([(SET_COOKIE, session_h), (SET_COOKIE, clear_tx_h)]),
-        Redirect::to("/"),
+        Redirect::to(tx.return_to.as_deref().unwrap_or("/")),
     )
         .into_response())
 }
@@ -180,12 +246,15 @@ async fn auth_logout() -> Result<Response, AppError> {
 struct MeResponse {
     username: String,
     sub: String,
+    is_admin: bool,
 }
 
-async fn me(auth: AuthUser) -> Json<MeResponse> {
+async fn me(auth: AuthUser, State(state): State<Arc<AppState>>) -> Json<MeResponse> {
+    let is_admin = auth.is_admin(&state.config.auth);
     Json(MeResponse {
         username: auth.username,
         sub: auth.sub,
+        is_admin,
     })
 }
 
@@ -218,76 +287,317 @@ async fn list_providers(
     Ok(Json(ProvidersResponse { providers }))
 }
 
-#[derive(Serialize, Deserialize)]
-struct SystemPrompt {
-    name: String,
-    prompt: String,
-    suggested_models: Vec<String>,
-}
-
 #[derive(Serialize)]
 struct PromptsResponse {
     prompts: Vec<SystemPrompt>,
 }
 
+/// Global prompts.toml entries first, then the caller's own personas.
 async fn list_prompts(
-    _auth: AuthUser,
-    State(_state): State<Arc<AppState>>,
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
 ) -> Result<Json<PromptsResponse>, AppError> {
-    let prompts_paths = vec![
-        "/usr/local/etc/gamecode-web/prompts.toml",
-        "config/prompts.toml",
-    ];
-
-    let mut prompts = None;
-    for path in prompts_paths {
-        if let Ok(content) = fs::read_to_string(path) {
-            tracing::info!("Loading prompts from: {}", path);
-
-            #[derive(Deserialize)]
-            struct PromptsConfig {
-                prompts: Vec<SystemPrompt>,
-            }
+    let mut prompts = state.prompts.prompts();
+    prompts.extend(state.store.list_personas(&auth.sub).await?);
+    Ok(Json(PromptsResponse { prompts }))
+}
 
-            match toml::from_str::<PromptsConfig>(&content) {
-                Ok(config) => {
-                    prompts = Some(config.prompts);
-                    break;
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to parse prompts.toml at {}: {}", path, e);
-                }
-            }
+#[derive(Deserialize)]
+struct PersonaBody {
+    name: String,
+    prompt: String,
+    #[serde(default)]
+    suggested_models: Vec<String>,
+    #[serde(default)]
+    options: PromptOptions,
+    #[serde(default)]
+    variables: Vec<PromptVariable>,
+}
+
+impl PersonaBody {
+    /// Validate against prompts.toml rules and reject names that would
+    /// shadow a global prompt or another of the user's personas.
+    async fn into_persona(
+        self,
+        state: &AppState,
+        sub: &str,
+        id: Option<&str>,
+    ) -> Result<SystemPrompt, AppError> {
+        let persona = SystemPrompt {
+            id: None,
+            name: self.name.trim().to_string(),
+            prompt: self.prompt,
+            suggested_models: self.suggested_models,
+            options: self.options,
+            variables: self.variables,
+        };
+        let errors = prompts::entry_errors(&persona);
+        if !errors.is_empty() {
+            return Err(AppError::BadRequest(errors.join("; ")));
         }
+        let taken = state
+            .prompts
+            .prompts()
+            .iter()
+            .any(|p| p.name == persona.name)
+            || state
+                .store
+                .list_personas(sub)
+                .await?
+                .iter()
+                .any(|p| p.name == persona.name && p.id.as_deref() != id);
+        if taken {
+            return Err(AppError::BadRequest(format!(
+                "a persona named \"{}\" already exists",
+                persona.name
+            )));
+        }
+        Ok(persona)
     }
+}
 
-    let prompts = prompts.unwrap_or_else(|| {
-        tracing::info!("Using default prompts (no prompts.toml found)");
-        get_default_prompts()
-    });
+async fn create_persona(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<PersonaBody>,
+) -> Result<Json<SystemPrompt>, AppError> {
+    let persona = body.into_persona(&state, &auth.sub, None).await?;
+    let created = state.store.create_persona(&auth.sub, persona).await?;
+    Ok(Json(created))
+}
 
-    Ok(Json(PromptsResponse { prompts }))
+async fn update_persona(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(body): Json<PersonaBody>,
+) -> Result<Json<SystemPrompt>, AppError> {
+    let persona = body.into_persona(&state, &auth.sub, Some(&id)).await?;
+    state
+        .store
+        .update_persona(&auth.sub, &id, persona)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("persona not found".into()))
 }
 
-fn get_default_prompts() -> Vec<SystemPrompt> {
-    vec![
-        SystemPrompt {
-            name: "General Assistant".to_string(),
-            prompt: "You are a helpful AI assistant.".to_string(),
-            suggested_models: vec!["qwen3:14b".to_string()],
-        },
-        SystemPrompt {
-            name: "Custom".to_string(),
-            prompt: String::new(),
-            suggested_models: vec![],
-        },
-    ]
+async fn delete_persona(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if state.store.delete_persona(&auth.sub, &id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound("persona not found".into()))
+    }
+}
+
+async fn list_conversations(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ConversationSummary>>, AppError> {
+    Ok(Json(state.store.list_conversations(&auth.sub).await?))
+}
+
+async fn get_conversation(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationRecord>, AppError> {
+    state
+        .store
+        .get_conversation(&auth.sub, &id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("conversation not found".into()))
+}
+
+#[derive(Deserialize)]
+struct PutConversationBody {
+    modified_at: i64,
+    data: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct DeleteConversationQuery {
+    modified_at: i64,
+}
+
+/// `applied` is false when the server already held a newer copy, which is
+/// returned instead so the client can adopt it.
+#[derive(Serialize)]
+struct PutConversationResponse {
+    applied: bool,
+    conversation: ConversationRecord,
+}
+
+async fn put_conversation(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Json(body): Json<PutConversationBody>,
+) -> Result<Json<PutConversationResponse>, AppError> {
+    let record = ConversationRecord {
+        id,
+        modified_at: body.modified_at,
+        deleted: false,
+        data: Some(body.data),
+    };
+    let (applied, conversation) = state.store.put_conversation(&auth.sub, record).await?;
+    Ok(Json(PutConversationResponse {
+        applied,
+        conversation,
+    }))
+}
+
+async fn delete_conversation(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<DeleteConversationQuery>,
+) -> Result<Json<PutConversationResponse>, AppError> {
+    let tombstone = ConversationRecord {
+        id,
+        modified_at: query.modified_at,
+        deleted: true,
+        data: None,
+    };
+    let (applied, conversation) = state.store.put_conversation(&auth.sub, tombstone).await?;
+    Ok(Json(PutConversationResponse {
+        applied,
+        conversation,
+    }))
+}
+
+#[derive(Deserialize)]
+struct SyncRequestBody {
+    #[serde(default)]
+    since: i64,
+    #[serde(default)]
+    changes: Vec<ConversationRecord>,
+}
+
+#[derive(Serialize)]
+struct SyncResponse {
+    cursor: i64,
+    /// Ids of pushed changes the server now holds; the rest lost to a newer
+    /// server copy.
+    acked: Vec<String>,
+    changes: Vec<ConversationRecord>,
+}
+
+/// Push local edits and pull everything changed after `since` in one round
+/// trip. Conflicts resolve by `modified_at`; the newer copy wins on both ends.
+async fn sync_conversations(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SyncRequestBody>,
+) -> Result<Json<SyncResponse>, AppError> {
+    let pushed = req.changes.len();
+    let (cursor, acked, changes) = state
+        .store
+        .sync_conversations(&auth.sub, req.since, req.changes)
+        .await?;
+    tracing::info!(
+        "Conversation sync: user={} pushed={} pulled={}",
+        auth.username,
+        pushed,
+        changes.len()
+    );
+    Ok(Json(SyncResponse {
+        cursor,
+        acked,
+        changes,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CreateShareBody {
+    conversation_id: String,
+    title: String,
+    /// Opaque to the server; the client renders it on the shared page.
+    snapshot: serde_json::Value,
+    #[serde(default)]
+    require_auth: bool,
+    #[serde(default)]
+    expires_in_secs: Option<i64>,
+}
+
+async fn create_share(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<CreateShareBody>,
+) -> Result<Json<Share>, AppError> {
+    let title = body.title.trim();
+    if title.is_empty() {
+        return Err(AppError::BadRequest("title must not be empty".into()));
+    }
+    if body.expires_in_secs.is_some_and(|s| s <= 0) {
+        return Err(AppError::BadRequest(
+            "expires_in_secs must be positive".into(),
+        ));
+    }
+    let share = NewShare {
+        conversation_id: body.conversation_id,
+        title: title.to_string(),
+        snapshot: body.snapshot,
+        require_auth: body.require_auth,
+        expires_at: body.expires_in_secs.map(|s| now_secs() + s),
+    };
+    let share = state
+        .store
+        .create_share(&auth.sub, &auth.username, share)
+        .await?;
+    tracing::info!(
+        "Share created: user={} conversation={}",
+        auth.username,
+        share.conversation_id
+    );
+    Ok(Json(share))
+}
+
+async fn list_shares(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Share>>, AppError> {
+    Ok(Json(state.store.list_shares(&auth.sub).await?))
+}
+
+async fn delete_share(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if state.store.delete_share(&auth.sub, &token).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(AppError::NotFound("share not found".into()))
+    }
+}
+
+async fn get_shared(
+    auth: Option<AuthUser>,
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedConversation>, AppError> {
+    let shared = state
+        .store
+        .get_share(&token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("this link has expired or been revoked".into()))?;
+    if shared.require_auth && auth.is_none() {
+        return Err(AppError::Unauthorized(
+            "sign in to view this conversation".into(),
+        ));
+    }
+    Ok(Json(shared))
 }
 
 #[derive(Deserialize)]
 struct ChatRequestBody {
     provider: String,
-    messages: Vec<crate::providers::ChatMessage>,
+    messages: Vec<ChatMessage>,
     #[serde(default)]
     model: Option<String>,
     #[serde(default)]
@@ -296,27 +606,85 @@ struct ChatRequestBody {
     temperature: Option<f32>,
     #[serde(default)]
     max_tokens: Option<usize>,
+    #[serde(default)]
+    top_p: Option<f32>,
+    #[serde(default)]
+    top_k: Option<u32>,
+    #[serde(default)]
+    min_p: Option<f32>,
+    #[serde(default)]
+    repeat_penalty: Option<f32>,
+    #[serde(default)]
+    seed: Option<i64>,
+    #[serde(default)]
+    num_ctx: Option<usize>,
+    #[serde(default)]
+    stop: Option<Vec<String>>,
+    /// Values for user-declared `{{placeholders}}` in `system_prompt`.
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    /// `system_prompt` was rendered by an earlier request (a replay) and is
+    /// sent as is.
+    #[serde(default)]
+    rendered: bool,
 }
 
 async fn chat(
-    _auth: AuthUser,
+    auth: AuthUser,
     State(state): State<Arc<AppState>>,
     Json(req): Json<ChatRequestBody>,
 ) -> Result<Sse<UnboundedReceiverStream<Result<Event, Infallible>>>, AppError> {
     tracing::info!("Chat endpoint hit with provider: {}", req.provider);
 
+    let system_prompt = match req.system_prompt {
+        Some(prompt) if req.rendered => Some(prompt),
+        template => template.map(|template| {
+            prompts::render(
+                &template,
+                &TemplateContext {
+                    username: &auth.username,
+                    model: req.model.as_deref().unwrap_or_default(),
+                    variables: &req.variables,
+                },
+            )
+        }),
+    };
+
     let chat_request = ChatRequest {
         messages: req.messages.clone(),
         model: req.model,
-        system_prompt: req.system_prompt,
+        system_prompt,
         temperature: req.temperature,
         max_tokens: req.max_tokens,
+        top_p: req.top_p,
+        top_k: req.top_k,
+        min_p: req.min_p,
+        repeat_penalty: req.repeat_penalty,
+        seed: req.seed,
+        num_ctx: req.num_ctx,
+        stop: req.stop,
     };
 
     tracing::info!("Messages: {:?}", req.messages);
 
+    // Resolved before streaming so the client can record which weights
+    // answered; a failed lookup only leaves the digest out.
+    let digest = match (state.providers.get(&req.provider), &chat_request.model) {
+        (Some(provider), Some(model)) => provider.model_digest(model).await.unwrap_or_else(|e| {
+            tracing::warn!("Could not resolve digest for {}: {}", model, e);
+            None
+        }),
+        _ => None,
+    };
+    let meta = serde_json::json!({
+        "model": chat_request.model,
+        "digest": digest,
+        "system_prompt": chat_request.system_prompt,
+    });
+
     let mut stream = state.providers.chat(&req.provider, chat_request).await?;
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let _ = tx.send(Ok(Event::default().event("meta").data(meta.to_string())));
 
     tokio::spawn(async move {
         while let Some(result) = stream.next().await {
@@ -348,6 +716,645 @@ async fn chat(
     Ok(Sse::new(UnboundedReceiverStream::new(rx)))
 }
 
+/// Inputs per `/api/embed` call; clients batch larger jobs.
+const MAX_EMBED_INPUTS: usize = 64;
+
+#[derive(Deserialize)]
+struct EmbedRequestBody {
+    texts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct EmbedResponse {
+    /// Vectors from different models aren't comparable; clients keep this
+    /// next to what they store.
+    model: String,
+    embeddings: Vec<Vec<f32>>,
+}
+
+async fn embed(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<EmbedRequestBody>,
+) -> Result<Json<EmbedResponse>, AppError> {
+    let Some((provider, model)) = state.providers.embedder() else {
+        return Err(AppError::Forbidden(
+            "semantic search is disabled: no embedding model is configured".into(),
+        ));
+    };
+    if req.texts.is_empty() || req.texts.len() > MAX_EMBED_INPUTS {
+        return Err(AppError::BadRequest(format!(
+            "expected 1 to {} texts",
+            MAX_EMBED_INPUTS
+        )));
+    }
+
+    tracing::debug!(
+        "Embed: user={} model={} inputs={}",
+        auth.username,
+        model,
+        req.texts.len()
+    );
+    let embeddings = provider.embed(&req.texts).await?;
+    Ok(Json(EmbedResponse {
+        model: model.to_string(),
+        embeddings,
+    }))
+}
+
+/// Texts per `/api/tokenize` call.
+const MAX_TOKENIZE_INPUTS: usize = 256;
+
+#[derive(Deserialize)]
+struct TokenizeRequestBody {
+    model: String,
+    texts: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct TokenizeResponse {
+    counts: Vec<usize>,
+}
+
+async fn tokenize(
+    _auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<TokenizeRequestBody>,
+) -> Result<Json<TokenizeResponse>, AppError> {
+    if !state.tokenizers.enabled() {
+        return Err(AppError::Forbidden(
+            "exact token counting is disabled: GAMECODE_TOKENIZER_DIR is unset".into(),
+        ));
+    }
+    if req.texts.is_empty() || req.texts.len() > MAX_TOKENIZE_INPUTS {
+        return Err(AppError::BadRequest(format!(
+            "expected 1 to {} texts",
+            MAX_TOKENIZE_INPUTS
+        )));
+    }
+    // Loading a tokenizer reads and parses a few MB; keep it off the runtime.
+    let state = state.clone();
+    let model = req.model.clone();
+    let counts = tokio::task::spawn_blocking(move || state.tokenizers.count(&model, &req.texts))
+        .await
+        .map_err(|e| anyhow!("tokenize task: {}", e))??;
+    match counts {
+        Some(counts) => Ok(Json(TokenizeResponse { counts })),
+        None => Err(AppError::NotFound(format!(
+            "no tokenizer for model '{}'",
+            req.model
+        ))),
+    }
+}
+
+/// Context messages per `/api/feedback` record.
+const MAX_FEEDBACK_MESSAGES: usize = 200;
+
+#[derive(Deserialize, Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum Rating {
+    Up,
+    Down,
+}
+
+/// A rated reply with what produced it. Stored as sent, plus who sent it
+/// and when.
+#[derive(Deserialize, Serialize)]
+struct FeedbackRecord {
+    conversation_id: String,
+    cell_id: usize,
+    rating: Option<Rating>,
+    #[serde(default)]
+    comment: String,
+    provider: Option<String>,
+    model: Option<String>,
+    #[serde(default)]
+    model_digest: Option<String>,
+    #[serde(default)]
+    persona: Option<String>,
+    /// The system prompt as sent, with variables filled in.
+    #[serde(default)]
+    system_prompt: Option<String>,
+    /// What the model was sent, oldest first.
+    messages: Vec<ChatMessage>,
+    reply: String,
+}
+
+#[derive(Serialize)]
+struct LoggedFeedback<'a> {
+    username: &'a str,
+    received_at: i64,
+    #[serde(flatten)]
+    record: &'a FeedbackRecord,
+}
+
+async fn post_feedback(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(record): Json<FeedbackRecord>,
+) -> Result<StatusCode, AppError> {
+    if !state.feedback.enabled() {
+        return Err(AppError::Forbidden(
+            "feedback logging is disabled: GAMECODE_FEEDBACK_FILE is unset".into(),
+        ));
+    }
+    if record.messages.len() > MAX_FEEDBACK_MESSAGES {
+        return Err(AppError::BadRequest(format!(
+            "expected at most {} messages",
+            MAX_FEEDBACK_MESSAGES
+        )));
+    }
+    state
+        .feedback
+        .append(&LoggedFeedback {
+            username: &auth.username,
+            received_at: now_secs(),
+            record: &record,
+        })
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Messages per `/api/summarize` call.
+const MAX_SUMMARIZE_MESSAGES: usize = 200;
+
+const SUMMARY_INSTRUCTIONS: &str = "You condense conversations so they can be continued \
+later without the full transcript. Summarize the transcript you are given in at most 200 \
+words of plain prose. Keep the substance: facts, decisions, constraints, names, file and \
+code identifiers, numbers, and open questions. Drop greetings and filler. Reply with the \
+summary only.";
+
+#[derive(Deserialize)]
+struct SummarizeRequestBody {
+    provider: String,
+    /// The conversation's model; the provider's summary model wins if set.
+    #[serde(default)]
+    model: Option<String>,
+    messages: Vec<ChatMessage>,
+}
+
+#[derive(Serialize)]
+struct SummarizeResponse {
+    summary: String,
+    model: Option<String>,
+}
+
+async fn summarize(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<SummarizeRequestBody>,
+) -> Result<Json<SummarizeResponse>, AppError> {
+    let Some(provider) = state.providers.get(&req.provider) else {
+        return Err(AppError::BadRequest(format!(
+            "unknown provider '{}'",
+            req.provider
+        )));
+    };
+    if req.messages.is_empty() || req.messages.len() > MAX_SUMMARIZE_MESSAGES {
+        return Err(AppError::BadRequest(format!(
+            "expected 1 to {} messages",
+            MAX_SUMMARIZE_MESSAGES
+        )));
+    }
+    let model = provider
+        .summary_model()
+        .map(str::to_string)
+        .or(req.model)
+        .filter(|m| !m.is_empty());
+
+    // One transcript message, so the model summarizes it instead of
+    // carrying on the conversation.
+    let transcript = req
+        .messages
+        .iter()
+        .map(|m| {
+            let speaker = match m.role.as_str() {
+                "user" => "User",
+                "assistant" => "Assistant",
+                _ => "System",
+            };
+            format!("{}: {}", speaker, m.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    tracing::debug!(
+        "Summarize: user={} model={:?} messages={}",
+        auth.username,
+        model,
+        req.messages.len()
+    );
+    let summary = state
+        .providers
+        .complete(
+            &req.provider,
+            ChatRequest {
+                messages: vec![ChatMessage {
+                    role: "user".into(),
+                    content: transcript,
+                }],
+                model: model.clone(),
+                system_prompt: Some(SUMMARY_INSTRUCTIONS.into()),
+                temperature: Some(0.2),
+                max_tokens: Some(400),
+                ..Default::default()
+            },
+        )
+        .await?;
+    // Reasoning models think out loud first; only the summary belongs in
+    // the compacted context.
+    let summary = strip_thinking(&summary).trim().to_string();
+    if summary.is_empty() {
+        return Err(AppError::Internal(anyhow!(
+            "the model returned an empty summary"
+        )));
+    }
+    Ok(Json(SummarizeResponse { summary, model }))
+}
+
+#[derive(Serialize)]
+struct SandboxStatus {
+    enabled: bool,
+    languages: Vec<String>,
+    /// Why code execution is off although configured on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unavailable: Option<String>,
+}
+
+async fn sandbox_status(
+    _auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Json<SandboxStatus> {
+    let enabled = state.sandbox.enabled();
+    let languages = if enabled {
+        Language::ALIASES
+            .iter()
+            .map(|(alias, _)| alias.to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Json(SandboxStatus {
+        enabled,
+        languages,
+        unavailable: state.sandbox.unavailable().map(str::to_string),
+    })
+}
+
+#[derive(Deserialize)]
+struct RunRequestBody {
+    language: String,
+    source: String,
+}
+
+async fn sandbox_run(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RunRequestBody>,
+) -> Result<Json<RunOutput>, AppError> {
+    if !state.sandbox.enabled() {
+        return Err(AppError::Forbidden("code execution is disabled".into()));
+    }
+    let language = Language::parse(&req.language)
+        .ok_or_else(|| AppError::BadRequest(format!("unsupported language: {}", req.language)))?;
+
+    tracing::info!(
+        "Sandbox run: user={} language={:?} bytes={}",
+        auth.username,
+        language,
+        req.source.len()
+    );
+    let output = state.sandbox.run(language, &req.source).await?;
+    Ok(Json(output))
+}
+
+#[derive(Serialize)]
+struct AdminHealthResponse {
+    providers: Vec<ProviderStatus>,
+    prompts: PromptStatus,
+    sandbox_enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sandbox_unavailable: Option<String>,
+}
+
+async fn admin_health(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<AdminHealthResponse>, AppError> {
+    if !auth.is_admin(&state.config.auth) {
+        return Err(AppError::Forbidden("admin only".into()));
+    }
+    let Json(health) = health(State(state.clone())).await?;
+    Ok(Json(AdminHealthResponse {
+        providers: health.providers,
+        prompts: state.prompts.status(),
+        sandbox_enabled: state.sandbox.enabled(),
+        sandbox_unavailable: state.sandbox.unavailable().map(str::to_string),
+    }))
+}
+
+/// Rows per uploaded batch job.
+const MAX_JOB_ROWS: usize = 10_000;
+/// Results per `/api/jobs/:id/results` page; downloads have no limit.
+const JOB_RESULTS_PAGE: i64 = 500;
+
+fn require_jobs(state: &AppState) -> Result<(), AppError> {
+    if state.jobs.enabled() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "batch jobs are disabled: GAMECODE_JOBS_CONCURRENCY is 0".into(),
+        ))
+    }
+}
+
+#[derive(Deserialize)]
+struct JobInputBody {
+    format: InputFormat,
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct CreateJobBody {
+    name: String,
+    provider: String,
+    model: String,
+    #[serde(default)]
+    persona: Option<String>,
+    /// The persona's system prompt template; rendered per row like `template`.
+    #[serde(default)]
+    system_prompt: Option<String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    template: String,
+    #[serde(default)]
+    options: PromptOptions,
+    /// Rows sent at once; capped at the server's GAMECODE_JOBS_CONCURRENCY.
+    #[serde(default)]
+    concurrency: Option<usize>,
+    /// Cron expression (UTC) to rerun the job on.
+    #[serde(default)]
+    schedule: Option<String>,
+    #[serde(default)]
+    input: Option<JobInputBody>,
+}
+
+async fn list_jobs(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<Job>>, AppError> {
+    require_jobs(&state)?;
+    Ok(Json(state.store.list_jobs(&auth.sub).await?))
+}
+
+async fn create_job(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<CreateJobBody>,
+) -> Result<Json<Job>, AppError> {
+    require_jobs(&state)?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("name must not be empty".into()));
+    }
+    if body.template.trim().is_empty() {
+        return Err(AppError::BadRequest("template must not be empty".into()));
+    }
+    if state.providers.get(&body.provider).is_none() {
+        return Err(AppError::BadRequest(format!(
+            "unknown provider \"{}\"",
+            body.provider
+        )));
+    }
+    let schedule = body
+        .schedule
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    if let Some(schedule) = schedule {
+        schedule
+            .parse::<jobs::cron::Schedule>()
+            .map_err(|e| AppError::BadRequest(format!("schedule: {:#}", e)))?;
+    }
+    let table = match &body.input {
+        Some(input) => {
+            let table = jobs::input::parse(input.format, &input.text)
+                .map_err(|e| AppError::BadRequest(format!("input: {:#}", e)))?;
+            if table.rows.is_empty() {
+                return Err(AppError::BadRequest("input has no rows".into()));
+            }
+            if table.rows.len() > MAX_JOB_ROWS {
+                return Err(AppError::BadRequest(format!(
+                    "expected at most {} rows",
+                    MAX_JOB_ROWS
+                )));
+            }
+            Some(table)
+        }
+        None if schedule.is_some() => None,
+        None => {
+            return Err(AppError::BadRequest(
+                "upload rows to run, or set a schedule".into(),
+            ))
+        }
+    };
+    let columns = table
+        .as_ref()
+        .map(|t| t.columns.clone())
+        .unwrap_or_default();
+    if let Some(missing) = prompts::placeholders(&body.template)
+        .into_iter()
+        .find(|p| !columns.contains(p) && !body.variables.contains_key(p))
+    {
+        return Err(AppError::BadRequest(format!(
+            "template uses {{{{{}}}}}, which is neither an input column nor a variable",
+            missing
+        )));
+    }
+
+    let job = NewJob {
+        name: name.to_string(),
+        spec: JobSpec {
+            provider: body.provider,
+            model: body.model,
+            persona: body.persona,
+            system_prompt: body.system_prompt.filter(|p| !p.trim().is_empty()),
+            variables: body.variables,
+            template: body.template,
+            options: body.options,
+            concurrency: body
+                .concurrency
+                .unwrap_or(1)
+                .clamp(1, state.jobs.max_concurrency()),
+            columns,
+        },
+        schedule: schedule.map(str::to_string),
+        rows: table.map(|t| t.rows).unwrap_or_default(),
+        next_run_at: schedule.and_then(jobs::next_run),
+    };
+    let job = state
+        .store
+        .create_job(&auth.sub, &auth.username, job)
+        .await?;
+    tracing::info!(
+        "Job created: user={} job={} rows={} schedule={:?}",
+        auth.username,
+        job.id,
+        job.total,
+        job.schedule
+    );
+    // Scheduled jobs wait for their first due time (see `jobs::watch`).
+    if job.status == JobStatus::Queued {
+        jobs::start(&state, &job);
+    }
+    state.jobs.notify(JobEvent::Updated(Box::new(job.clone())));
+    Ok(Json(job))
+}
+
+/// Progress of the caller's jobs: a `job` event with the whole job on every
+/// change, `deleted` with the id, and `lagged` when updates were dropped and
+/// the list should be fetched again.
+async fn job_events(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, Infallible>>>, AppError> {
+    require_jobs(&state)?;
+    let mut rx = state.jobs.subscribe();
+    let stream = async_stream::stream! {
+        loop {
+            match rx.recv().await {
+                Ok(event) if event.sub() == auth.sub => match event {
+                    JobEvent::Updated(job) => {
+                        let data = serde_json::to_string(&job).unwrap_or_default();
+                        yield Ok(Event::default().event("job").data(data));
+                    }
+                    JobEvent::Deleted { id, .. } => {
+                        yield Ok(Event::default().event("deleted").data(id));
+                    }
+                },
+                Ok(_) => {}
+                Err(RecvError::Lagged(_)) => {
+                    yield Ok(Event::default().event("lagged").data(""));
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    };
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Deserialize)]
+struct JobResultsQuery {
+    /// Only results with a larger `seq`.
+    #[serde(default)]
+    after: i64,
+    /// `csv` or `jsonl` for a download of every result; a JSON page otherwise.
+    #[serde(default)]
+    format: Option<String>,
+}
+
+async fn job_results(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    Query(query): Query<JobResultsQuery>,
+) -> Result<Response, AppError> {
+    require_jobs(&state)?;
+    let job = state
+        .store
+        .get_job(&auth.sub, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("job not found".into()))?;
+    let (content_type, extension, body) = match query.format.as_deref() {
+        None | Some("json") => {
+            let page = state
+                .store
+                .job_results(&id, query.after, JOB_RESULTS_PAGE)
+                .await?;
+            return Ok(Json(page).into_response());
+        }
+        Some("csv") => {
+            let results = state.store.job_results(&id, query.after, i64::MAX).await?;
+            let body = jobs::input::to_csv(&job.spec.columns, &results, job.schedule.is_some())?;
+            ("text/csv; charset=utf-8", "csv", body)
+        }
+        Some("jsonl") => {
+            let results = state.store.job_results(&id, query.after, i64::MAX).await?;
+            let body = jobs::input::to_jsonl(&results)?;
+            ("application/x-ndjson", "jsonl", body)
+        }
+        Some(other) => {
+            return Err(AppError::BadRequest(format!(
+                "unknown format \"{}\"; use json, csv or jsonl",
+                other
+            )))
+        }
+    };
+    let filename: String = job
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let disposition = format!("attachment; filename=\"{}.{}\"", filename, extension);
+    Ok((
+        [
+            (CONTENT_TYPE, HeaderValue::from_static(content_type)),
+            (CONTENT_DISPOSITION, header_value(&disposition)?),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Stop a run after the rows in flight, or a scheduled job before its next
+/// run. Results so far are kept.
+async fn cancel_job(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>, AppError> {
+    require_jobs(&state)?;
+    let job = state
+        .store
+        .get_job(&auth.sub, &id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("job not found".into()))?;
+    if state.jobs.stop(&id) {
+        // The run records `cancelled` itself once its rows in flight finish.
+        return Ok(Json(job));
+    }
+    if !matches!(job.status, JobStatus::Queued | JobStatus::Scheduled) {
+        return Err(AppError::BadRequest("job is not running".into()));
+    }
+    let job = state
+        .store
+        .finish_job_run(&id, JobStatus::Cancelled, None, None)
+        .await?
+        .ok_or_else(|| AppError::NotFound("job not found".into()))?;
+    state.jobs.notify(JobEvent::Updated(Box::new(job.clone())));
+    Ok(Json(job))
+}
+
+async fn delete_job(
+    auth: AuthUser,
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    require_jobs(&state)?;
+    if !state.store.delete_job(&auth.sub, &id).await? {
+        return Err(AppError::NotFound("job not found".into()));
+    }
+    state.jobs.stop(&id);
+    state.jobs.notify(JobEvent::Deleted { sub: auth.sub, id });
+    Ok(StatusCode::NO_CONTENT)
+}
+
 fn read_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
     for header in headers.get_all(COOKIE).iter() {
         let Ok(text) = header.to_str() else { continue };
@@ -375,9 +1382,44 @@ fn ct_eq(a: &[u8], b: &[u8]) -> bool {
     diff == 0
 }
 
-fn now_secs() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn return_to_keeps_same_origin_paths() {
+        assert_eq!(
+            same_origin_path("/s/3f9a0c2e7b"),
+            Some("/s/3f9a0c2e7b".to_string())
+        );
+        assert_eq!(
+            same_origin_path("/s/3f9a0c2e7b?from=mail"),
+            Some("/s/3f9a0c2e7b?from=mail".to_string())
+        );
+        // Still a path on this origin: nothing decodes it a second time.
+        assert_eq!(
+            same_origin_path("/%2F%2Fevil.com"),
+            Some("/%2F%2Fevil.com".to_string())
+        );
+    }
+
+    #[test]
+    fn return_to_refuses_other_origins() {
+        for raw in [
+            "//evil.com",
+            "/\\evil.com",
+            "\\\\evil.com",
+            "/\t/evil.com",
+            "/\n/evil.com",
+            "https://evil.com",
+            "https://evil.com/s/abc",
+            "evil.com",
+            "%2F%2Fevil.com",
+            "javascript:alert(1)",
+            "/s/abc def",
+            "",
+        ] {
+            assert_eq!(same_origin_path(raw), None, "{:?}", raw);
+        }
+    }
 }
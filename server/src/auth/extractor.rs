@@ -19,7 +19,7 @@ use serde_json::json;
 use super::session::{
     clear_session_cookie, open, seal, session_cookie, SessionPayload, SESSION_COOKIE,
 };
-use crate::AppState;
+use crate::{clock::now_secs, config::AuthConfig, AppState};
 
 #[derive(Debug, Clone)]
 pub struct AuthUser {
@@ -27,6 +27,14 @@ pub struct AuthUser {
     pub sub: String,
 }
 
+impl AuthUser {
+    /// Only the `sub` claim counts: `preferred_username` is neither unique
+    /// nor stable, and users can often change it themselves.
+    pub fn is_admin(&self, config: &AuthConfig) -> bool {
+        config.admin_users.contains(&self.sub)
+    }
+}
+
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthUser
 where
@@ -62,6 +70,23 @@ pub async fn auth_middleware(
     }
 }
 
+/// Like `auth_middleware`, but anonymous or expired sessions pass through
+/// without an `AuthUser`, for endpoints that serve some content publicly.
+pub async fn optional_auth_middleware(
+    State(state): State<Arc<AppState>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let refreshed_cookie = try_auth(&state, &mut req).await.ok().flatten();
+    let mut resp = next.run(req).await;
+    if let Some(cookie) = refreshed_cookie {
+        if let Ok(val) = HeaderValue::from_str(&cookie) {
+            resp.headers_mut().append(SET_COOKIE, val);
+        }
+    }
+    resp
+}
+
 async fn try_auth(state: &AppState, req: &mut Request<Body>) -> Result<Option<String>, AuthFail> {
     let sealed = read_session_cookie(req).ok_or(AuthFail)?;
     let key = &state.config.auth.session_key;
@@ -118,14 +143,6 @@ fn read_session_cookie(req: &Request<Body>) -> Option<String> {
     None
 }
 
-fn now_secs() -> i64 {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs() as i64)
-        .unwrap_or(0)
-}
-
 struct AuthFail;
 
 fn unauthorized() -> Response {
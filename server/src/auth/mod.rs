@@ -2,7 +2,7 @@ pub mod extractor;
 pub mod oidc;
 pub mod session;
 
-pub use extractor::{auth_middleware, AuthUser};
+pub use extractor::{auth_middleware, optional_auth_middleware, AuthUser};
 pub use oidc::OidcClient;
 pub use session::{
     clear_session_cookie, clear_tx_cookie, session_cookie, tx_cookie, SessionPayload, TxPayload,
@@ -29,6 +29,9 @@ pub struct TxPayload {
     pub code_verifier: String,
     pub state: String,
     pub nonce: String,
+    /// Local path to land on after login.
+    #[serde(default)]
+    pub return_to: Option<String>,
 }
 
 pub fn seal<T: Serialize>(key: &[u8; 32], aad: &[u8], value: &T) -> Result<String> {
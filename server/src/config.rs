@@ -7,6 +7,11 @@ pub struct Config {
     pub server: ServerConfig,
     pub auth: AuthConfig,
     pub providers: ProvidersConfig,
+    pub sandbox: SandboxConfig,
+    pub store: StoreConfig,
+    pub tokenizer: TokenizerConfig,
+    pub feedback: FeedbackConfig,
+    pub jobs: JobsConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -20,6 +25,8 @@ pub struct ServerConfig {
 pub struct AuthConfig {
     pub oidc: OidcConfig,
     pub session_key: [u8; 32],
+    /// OIDC subjects (`sub` claims) allowed to see the admin views.
+    pub admin_users: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,9 +48,57 @@ pub struct OllamaConfig {
     pub enabled: bool,
     pub base_url: String,
     pub default_model: Option<String>,
+    /// Enables semantic search when set (e.g. `nomic-embed-text`).
+    pub embedding_model: Option<String>,
+    /// Cheaper model for context summaries; the chat's model when unset.
+    pub summary_model: Option<String>,
     pub timeout_seconds: u64,
 }
 
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    pub enabled: bool,
+    pub timeout_seconds: u64,
+    pub memory_mb: u64,
+    /// Address space for compile steps (rustc).
+    pub compile_memory_mb: u64,
+    pub max_output_bytes: usize,
+    /// The user and group snippets see inside their namespace. Outside it
+    /// they are the server's own, unless the server runs as root.
+    pub uid: u32,
+    pub gid: u32,
+    /// Processes and threads at once per snippet (RLIMIT_NPROC).
+    pub max_processes: u64,
+    pub max_concurrent: usize,
+    /// Extra host paths bound read-only into the snippet's root, e.g. a
+    /// toolchain outside `/usr`.
+    pub mounts: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TokenizerConfig {
+    /// Holds `<model>.json` tokenizer files; exact counting is off when unset.
+    pub dir: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedbackConfig {
+    /// JSONL file rated replies are appended to; posting feedback is off
+    /// when unset.
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobsConfig {
+    /// Rows answered at once across all batch jobs; 0 turns jobs off.
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoreConfig {
+    pub db_path: String,
+}
+
 impl Config {
     pub fn load() -> Result<Self> {
         let oidc = OidcConfig {
@@ -54,7 +109,68 @@ impl Config {
             scopes: require("GAMECODE_AUTH_OIDC_SCOPES")?,
         };
         let session_key = decode_session_key(&require("GAMECODE_AUTH_SESSION_KEY")?)?;
+        let admin_users = env::var("GAMECODE_AUTH_ADMIN_USERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .collect();
+
+        Ok(Config {
+            server: ServerConfig {
+                port: parse_env("GAMECODE_SERVER_PORT", 8080u16),
+                static_dir: env::var("GAMECODE_SERVER_STATIC_DIR")
+                    .unwrap_or_else(|_| "dist".to_string()),
+                max_request_size: parse_env("GAMECODE_SERVER_MAX_REQUEST_SIZE", 10 * 1024 * 1024),
+            },
+            auth: AuthConfig {
+                oidc,
+                session_key,
+                admin_users,
+            },
+            providers: ProvidersConfig::from_env()?,
+            sandbox: SandboxConfig {
+                enabled: parse_env("GAMECODE_SANDBOX_ENABLED", false),
+                timeout_seconds: parse_env("GAMECODE_SANDBOX_TIMEOUT_SECONDS", 10u64),
+                memory_mb: parse_env("GAMECODE_SANDBOX_MEMORY_MB", 512u64),
+                compile_memory_mb: parse_env("GAMECODE_SANDBOX_COMPILE_MEMORY_MB", 4096u64),
+                max_output_bytes: parse_env("GAMECODE_SANDBOX_MAX_OUTPUT_BYTES", 64 * 1024),
+                uid: parse_env("GAMECODE_SANDBOX_UID", 65534u32),
+                gid: parse_env("GAMECODE_SANDBOX_GID", 65534u32),
+                max_processes: parse_env("GAMECODE_SANDBOX_MAX_PROCESSES", 128u64),
+                max_concurrent: parse_env("GAMECODE_SANDBOX_MAX_CONCURRENT", 2usize),
+                mounts: env::var("GAMECODE_SANDBOX_MOUNTS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect(),
+            },
+            store: StoreConfig {
+                db_path: env::var("GAMECODE_DB_PATH")
+                    .unwrap_or_else(|_| "data/gamecode.db".to_string()),
+            },
+            tokenizer: TokenizerConfig {
+                dir: env::var("GAMECODE_TOKENIZER_DIR")
+                    .ok()
+                    .filter(|v| !v.is_empty()),
+            },
+            feedback: FeedbackConfig {
+                file: env::var("GAMECODE_FEEDBACK_FILE")
+                    .ok()
+                    .filter(|v| !v.is_empty()),
+            },
+            jobs: JobsConfig {
+                concurrency: parse_env("GAMECODE_JOBS_CONCURRENCY", 2usize),
+            },
+        })
+    }
+}
 
+impl ProvidersConfig {
+    /// Just the provider settings, for commands that talk to models
+    /// without serving the app (see `eval`).
+    pub fn from_env() -> Result<Self> {
         let ollama_enabled = parse_env("GAMECODE_OLLAMA_ENABLED", true);
         let ollama = if ollama_enabled {
             Some(OllamaConfig {
@@ -64,22 +180,19 @@ impl Config {
                 default_model: env::var("GAMECODE_OLLAMA_DEFAULT_MODEL")
                     .ok()
                     .filter(|v| !v.is_empty()),
+                embedding_model: env::var("GAMECODE_OLLAMA_EMBEDDING_MODEL")
+                    .ok()
+                    .filter(|v| !v.is_empty()),
+                summary_model: env::var("GAMECODE_OLLAMA_SUMMARY_MODEL")
+                    .ok()
+                    .filter(|v| !v.is_empty()),
                 timeout_seconds: parse_env("GAMECODE_OLLAMA_TIMEOUT_SECONDS", 60u64),
             })
         } else {
             None
         };
 
-        Ok(Config {
-            server: ServerConfig {
-                port: parse_env("GAMECODE_SERVER_PORT", 8080u16),
-                static_dir: env::var("GAMECODE_SERVER_STATIC_DIR")
-                    .unwrap_or_else(|_| "dist".to_string()),
-                max_request_size: parse_env("GAMECODE_SERVER_MAX_REQUEST_SIZE", 10 * 1024 * 1024),
-            },
-            auth: AuthConfig { oidc, session_key },
-            providers: ProvidersConfig { ollama },
-        })
+        Ok(ProvidersConfig { ollama })
     }
 }
 
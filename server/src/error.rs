@@ -9,6 +9,9 @@ use serde_json::json;
 pub enum AppError {
     Internal(anyhow::Error),
     BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
 }
 
 impl IntoResponse for AppError {
@@ -22,6 +25,9 @@ impl IntoResponse for AppError {
                 )
             }
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
         };
 
         let body = Json(json!({
@@ -0,0 +1,264 @@
+use regex::RegexBuilder;
+use serde::{Deserialize, Serialize};
+
+use super::{schema, Target};
+use crate::providers::{strip_thinking, ChatMessage, ChatRequest, ProviderManager};
+
+/// One check on a reply, declared as `[[cases.assert]]` with a `type` key.
+/// All of them look at the visible answer, with any `<think>` block removed.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Assertion {
+    Contains {
+        value: String,
+        #[serde(default)]
+        ignore_case: bool,
+    },
+    NotContains {
+        value: String,
+        #[serde(default)]
+        ignore_case: bool,
+    },
+    Regex {
+        pattern: String,
+    },
+    /// The reply, or the first fenced or bracketed JSON in it, parses and
+    /// satisfies `schema` (see `schema::validate` for the keywords checked).
+    JsonSchema {
+        schema: serde_json::Value,
+    },
+    /// Another model grades the reply against `rubric` and answers PASS or
+    /// FAIL. `model` is `provider/model`, defaulting to the suite's `judge`.
+    LlmJudge {
+        rubric: String,
+        #[serde(default)]
+        model: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssertionResult {
+    pub kind: &'static str,
+    /// What was asserted, e.g. `contains "lightning"`.
+    pub label: String,
+    pub passed: bool,
+    /// Why it failed, or the judge's explanation.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub detail: String,
+}
+
+const JUDGE_SYSTEM_PROMPT: &str = "You grade another assistant's answer against a rubric. \
+Reply with PASS or FAIL on the first line, then one sentence saying why.";
+
+impl Assertion {
+    pub async fn evaluate(
+        &self,
+        prompt: &str,
+        reply: &str,
+        providers: &ProviderManager,
+        judge: Option<&Target>,
+    ) -> AssertionResult {
+        let answer = strip_thinking(reply);
+        let answer = answer.trim();
+        match self {
+            Assertion::Contains { value, ignore_case } => {
+                let found = contains(answer, value, *ignore_case);
+                result(
+                    "contains",
+                    format!("contains {:?}", value),
+                    found,
+                    "not found",
+                )
+            }
+            Assertion::NotContains { value, ignore_case } => {
+                let found = contains(answer, value, *ignore_case);
+                result(
+                    "not_contains",
+                    format!("does not contain {:?}", value),
+                    !found,
+                    "found",
+                )
+            }
+            Assertion::Regex { pattern } => {
+                let label = format!("matches /{}/", pattern);
+                match RegexBuilder::new(pattern).multi_line(true).build() {
+                    Ok(re) => result("regex", label, re.is_match(answer), "no match"),
+                    Err(e) => result("regex", label, false, &format!("invalid pattern: {}", e)),
+                }
+            }
+            Assertion::JsonSchema { schema } => {
+                let label = "matches the JSON schema".to_string();
+                let Some(value) = extract_json(answer) else {
+                    return result("json_schema", label, false, "no JSON found in the reply");
+                };
+                let errors = schema::validate(schema, &value);
+                result("json_schema", label, errors.is_empty(), &errors.join("; "))
+            }
+            Assertion::LlmJudge { rubric, model } => {
+                let label = format!("judge: {}", rubric.trim());
+                let target = match model {
+                    Some(spec) => match Target::parse(spec) {
+                        Ok(t) => Some(t),
+                        Err(e) => return result("llm_judge", label, false, &e.to_string()),
+                    },
+                    None => judge.cloned(),
+                };
+                let Some(target) = target else {
+                    return result(
+                        "llm_judge",
+                        label,
+                        false,
+                        "no judge model: set `judge` in the suite or pass --judge",
+                    );
+                };
+                match ask_judge(providers, &target, rubric, prompt, answer).await {
+                    Ok((passed, why)) => AssertionResult {
+                        kind: "llm_judge",
+                        label,
+                        passed,
+                        detail: why,
+                    },
+                    Err(e) => result("llm_judge", label, false, &format!("judge failed: {:#}", e)),
+                }
+            }
+        }
+    }
+}
+
+fn result(kind: &'static str, label: String, passed: bool, failure: &str) -> AssertionResult {
+    AssertionResult {
+        kind,
+        label,
+        passed,
+        detail: if passed {
+            String::new()
+        } else {
+            failure.to_string()
+        },
+    }
+}
+
+fn contains(haystack: &str, needle: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        haystack.to_lowercase().contains(&needle.to_lowercase())
+    } else {
+        haystack.contains(needle)
+    }
+}
+
+/// The whole reply if it is JSON, else the first ```json fence, else the
+/// span from the first `{` or `[` to the last `}` or `]`.
+fn extract_json(text: &str) -> Option<serde_json::Value> {
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    if let Some(start) = text.find("```") {
+        let body = &text[start + 3..];
+        let body = body.find('\n').map_or(body, |nl| &body[nl + 1..]);
+        if let Some(end) = body.find("```") {
+            if let Ok(value) = serde_json::from_str(body[..end].trim()) {
+                return Some(value);
+            }
+        }
+    }
+    let start = text.find(['{', '['])?;
+    let end = text.rfind(['}', ']'])?;
+    (end > start)
+        .then(|| serde_json::from_str(&text[start..=end]).ok())
+        .flatten()
+}
+
+async fn ask_judge(
+    providers: &ProviderManager,
+    judge: &Target,
+    rubric: &str,
+    prompt: &str,
+    answer: &str,
+) -> anyhow::Result<(bool, String)> {
+    let request = ChatRequest {
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: format!(
+                "Rubric:\n{}\n\nQuestion:\n{}\n\nAnswer:\n{}",
+                rubric, prompt, answer
+            ),
+        }],
+        model: Some(judge.model.clone()),
+        system_prompt: Some(JUDGE_SYSTEM_PROMPT.to_string()),
+        temperature: Some(0.0),
+        seed: Some(0),
+        ..Default::default()
+    };
+    let verdict = providers.complete(&judge.provider, request).await?;
+    parse_verdict(&strip_thinking(&verdict))
+}
+
+/// PASS or FAIL, in any case and possibly in markdown emphasis, then the
+/// judge's reason, which may follow on the same line ("PASS - ...") or the
+/// next.
+fn parse_verdict(verdict: &str) -> anyhow::Result<(bool, String)> {
+    let verdict = verdict.trim().trim_start_matches(['*', '#', ' ']);
+    let word = verdict.get(..4).unwrap_or_default();
+    let passed = if word.eq_ignore_ascii_case("PASS") {
+        true
+    } else if word.eq_ignore_ascii_case("FAIL") {
+        false
+    } else {
+        let first = verdict.lines().next().unwrap_or_default();
+        anyhow::bail!("no PASS/FAIL verdict in {:?}", first)
+    };
+    let why = verdict
+        .get(4..)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_start_matches(['*', ':', '-', '.', ' '])
+        .to_string();
+    Ok((passed, why))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn verdicts_parse_in_any_case_and_markup() {
+        assert_eq!(
+            parse_verdict("PASS - cites the source").unwrap(),
+            (true, "cites the source".to_string())
+        );
+        assert_eq!(
+            parse_verdict("**fail**\nNever mentions the date.").unwrap(),
+            (false, "Never mentions the date.".to_string())
+        );
+        assert_eq!(parse_verdict("## Pass").unwrap(), (true, String::new()));
+    }
+
+    #[test]
+    fn verdicts_without_pass_or_fail_are_errors() {
+        assert!(parse_verdict("The answer is fine.").is_err());
+        assert!(parse_verdict("").is_err());
+        assert!(parse_verdict("ok").is_err());
+        // Multi-byte text where the fourth byte isn't a char boundary.
+        assert!(parse_verdict("ßßß").is_err());
+        assert!(parse_verdict("İpass").is_err());
+    }
+
+    #[test]
+    fn json_is_found_whole_fenced_or_bracketed() {
+        assert_eq!(extract_json(r#"{"a": 1}"#), Some(json!({"a": 1})));
+        assert_eq!(
+            extract_json("Here you go:\n```json\n[1, 2]\n```\nDone."),
+            Some(json!([1, 2]))
+        );
+        assert_eq!(
+            extract_json(r#"The result is {"ok": true} as requested."#),
+            Some(json!({"ok": true}))
+        );
+        assert_eq!(extract_json("no json here"), None);
+        assert_eq!(extract_json("} backwards {"), None);
+        assert_eq!(extract_json("{broken"), None);
+    }
+}
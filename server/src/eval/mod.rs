@@ -0,0 +1,358 @@
+//! `gamecode-server eval`: run a suite of prompts against personas and
+//! models and check every reply, as a regression check before a new model
+//! build replaces the old one.
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, path::PathBuf, time::Instant};
+use tracing::info;
+
+mod assertions;
+mod report;
+mod schema;
+
+use crate::clock::now_secs;
+use crate::config::ProvidersConfig;
+use crate::prompts::{self, PromptOptions, PromptStore, SystemPrompt, TemplateContext};
+use crate::providers::{ChatMessage, ChatRequest, ProviderManager};
+use assertions::{Assertion, AssertionResult};
+use report::{CaseResult, Report, TargetReport};
+
+const USAGE: &str = "\
+usage: gamecode-server eval <suite.toml> [options]
+
+  --model PROVIDER/MODEL   model to test; repeat to compare several
+                           (default: the suite's `models`)
+  --persona NAME           persona from prompts.toml; repeat for several
+                           (default: the suite's `personas`, else none)
+  --judge PROVIDER/MODEL   model that grades llm_judge assertions
+  --format json|html       report format (default: from --out, else json)
+  --out PATH               write the report here instead of stdout
+
+Exits with status 1 when any assertion fails.";
+
+/// A suite file: the cases to run and, optionally, what to run them on.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Suite {
+    #[serde(default)]
+    name: Option<String>,
+    /// `provider/model` entries, used when no `--model` is given.
+    #[serde(default)]
+    models: Vec<String>,
+    /// Persona names, used when no `--persona` is given.
+    #[serde(default)]
+    personas: Vec<String>,
+    /// Default grader for `llm_judge` assertions.
+    #[serde(default)]
+    judge: Option<String>,
+    /// Sampling settings for every case; each field overrides the persona's.
+    #[serde(default)]
+    options: PromptOptions,
+    cases: Vec<Case>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    name: String,
+    prompt: String,
+    /// Values for the persona's `{{name}}` placeholders.
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    #[serde(default, rename = "assert")]
+    assertions: Vec<Assertion>,
+}
+
+/// A model on a named provider, written `provider/model`. Only the first
+/// slash separates them, so `ollama/hf.co/org/model` works.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub provider: String,
+    pub model: String,
+}
+
+impl Target {
+    pub fn parse(spec: &str) -> Result<Self> {
+        match spec.split_once('/') {
+            Some((provider, model)) if !provider.is_empty() && !model.is_empty() => Ok(Self {
+                provider: provider.to_string(),
+                model: model.to_string(),
+            }),
+            _ => bail!("expected provider/model, got {:?}", spec),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Json,
+    Html,
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    suite: PathBuf,
+    models: Vec<String>,
+    personas: Vec<String>,
+    judge: Option<String>,
+    format: Option<Format>,
+    out: Option<PathBuf>,
+}
+
+fn parse_args(args: Vec<String>) -> Result<Args> {
+    let mut parsed = Args::default();
+    let mut suite = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| {
+            args.next()
+                .ok_or_else(|| anyhow!("{} needs a value\n\n{}", flag, USAGE))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "--model" => parsed.models.push(value("--model")?),
+            "--persona" => parsed.personas.push(value("--persona")?),
+            "--judge" => parsed.judge = Some(value("--judge")?),
+            "--out" => parsed.out = Some(PathBuf::from(value("--out")?)),
+            "--format" => {
+                parsed.format = Some(match value("--format")?.as_str() {
+                    "json" => Format::Json,
+                    "html" => Format::Html,
+                    other => bail!("unknown format {:?}; use json or html", other),
+                })
+            }
+            flag if flag.starts_with('-') => bail!("unknown option {}\n\n{}", flag, USAGE),
+            path if suite.is_none() => suite = Some(PathBuf::from(path)),
+            extra => bail!("unexpected argument {:?}\n\n{}", extra, USAGE),
+        }
+    }
+    parsed.suite = suite.ok_or_else(|| anyhow!("no suite file given\n\n{}", USAGE))?;
+    Ok(parsed)
+}
+
+pub async fn main(args: Vec<String>) -> Result<()> {
+    let args = parse_args(args)?;
+    let text = std::fs::read_to_string(&args.suite)
+        .with_context(|| format!("reading {}", args.suite.display()))?;
+    let suite: Suite =
+        toml::from_str(&text).with_context(|| format!("parsing {}", args.suite.display()))?;
+    if suite.cases.is_empty() {
+        bail!("{} has no [[cases]]", args.suite.display());
+    }
+
+    let models = if args.models.is_empty() {
+        &suite.models
+    } else {
+        &args.models
+    };
+    let models = models
+        .iter()
+        .map(|m| Target::parse(m))
+        .collect::<Result<Vec<_>>>()?;
+    if models.is_empty() {
+        bail!("no models to test: pass --model or set `models` in the suite");
+    }
+    let judge = args
+        .judge
+        .as_ref()
+        .or(suite.judge.as_ref())
+        .map(|j| Target::parse(j))
+        .transpose()?;
+
+    let providers = ProviderManager::new(&ProvidersConfig::from_env()?).await?;
+    for target in models.iter().chain(judge.as_ref()) {
+        if providers.get(&target.provider).is_none() {
+            bail!("provider {:?} is not available", target.provider);
+        }
+    }
+
+    let persona_names = if args.personas.is_empty() {
+        &suite.personas
+    } else {
+        &args.personas
+    };
+    let personas: Vec<Option<SystemPrompt>> = if persona_names.is_empty() {
+        vec![None]
+    } else {
+        let store = PromptStore::load(&providers.all_models().await);
+        let known = store.prompts();
+        persona_names
+            .iter()
+            .map(|name| {
+                known
+                    .iter()
+                    .find(|p| p.name == *name)
+                    .cloned()
+                    .map(Some)
+                    .ok_or_else(|| anyhow!("no persona named {:?} in prompts.toml", name))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let started_at = now_secs();
+    let mut targets = Vec::new();
+    for persona in &personas {
+        for target in &models {
+            targets.push(
+                run_target(&providers, &suite, persona.as_ref(), target, judge.as_ref()).await,
+            );
+        }
+    }
+
+    let report = Report {
+        suite: suite
+            .name
+            .clone()
+            .unwrap_or_else(|| args.suite.display().to_string()),
+        started_at,
+        finished_at: now_secs(),
+        cases: suite.cases.iter().map(|c| c.name.clone()).collect(),
+        targets,
+    };
+
+    let format = args.format.unwrap_or(match &args.out {
+        Some(path) if path.extension().is_some_and(|e| e == "html") => Format::Html,
+        _ => Format::Json,
+    });
+    let rendered = match format {
+        Format::Json => serde_json::to_string_pretty(&report)?,
+        Format::Html => report::to_html(&report),
+    };
+    match &args.out {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("writing {}", path.display()))?;
+            info!("Report written to {}", path.display());
+        }
+        None => println!("{}", rendered),
+    }
+
+    if report.targets.iter().any(|t| t.failed > 0) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn run_target(
+    providers: &ProviderManager,
+    suite: &Suite,
+    persona: Option<&SystemPrompt>,
+    target: &Target,
+    judge: Option<&Target>,
+) -> TargetReport {
+    let digest = match providers.get(&target.provider) {
+        Some(p) => p.model_digest(&target.model).await.ok().flatten(),
+        None => None,
+    };
+    let mut results = Vec::new();
+    for case in &suite.cases {
+        let request = build_request(suite, case, persona, &target.model);
+        let start = Instant::now();
+        let reply = providers.complete(&target.provider, request).await;
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        let result = match reply {
+            Ok(reply) => {
+                let mut checks: Vec<AssertionResult> = Vec::new();
+                for assertion in &case.assertions {
+                    checks.push(
+                        assertion
+                            .evaluate(&case.prompt, &reply, providers, judge)
+                            .await,
+                    );
+                }
+                CaseResult {
+                    case: case.name.clone(),
+                    passed: checks.iter().all(|c| c.passed),
+                    reply,
+                    error: None,
+                    duration_ms,
+                    assertions: checks,
+                }
+            }
+            Err(e) => CaseResult {
+                case: case.name.clone(),
+                passed: false,
+                reply: String::new(),
+                error: Some(format!("{:#}", e)),
+                duration_ms,
+                assertions: Vec::new(),
+            },
+        };
+        info!(
+            "{} · {}/{} · {}: {}",
+            persona.map_or("no persona", |p| p.name.as_str()),
+            target.provider,
+            target.model,
+            case.name,
+            if result.passed { "pass" } else { "FAIL" }
+        );
+        results.push(result);
+    }
+
+    let passed = results.iter().filter(|r| r.passed).count();
+    TargetReport {
+        persona: persona.map(|p| p.name.clone()),
+        provider: target.provider.clone(),
+        model: target.model.clone(),
+        digest,
+        passed,
+        failed: results.len() - passed,
+        results,
+    }
+}
+
+/// The request the chat UI would send for `case`: the persona's rendered
+/// system prompt, its sampling options with the suite's layered on top.
+fn build_request(
+    suite: &Suite,
+    case: &Case,
+    persona: Option<&SystemPrompt>,
+    model: &str,
+) -> ChatRequest {
+    let defaults = persona.map(|p| p.options.clone()).unwrap_or_default();
+    let o = &suite.options;
+    let system_prompt = persona.map(|p| {
+        let mut variables: HashMap<String, String> = p
+            .variables
+            .iter()
+            .filter_map(|v| v.default.clone().map(|d| (v.name.clone(), d)))
+            .collect();
+        variables.extend(case.variables.clone());
+        prompts::render(
+            &p.prompt,
+            &TemplateContext {
+                username: "eval",
+                model,
+                variables: &variables,
+            },
+        )
+    });
+    let stop = if o.stop.is_empty() {
+        defaults.stop
+    } else {
+        o.stop.clone()
+    };
+
+    ChatRequest {
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: case.prompt.clone(),
+        }],
+        model: Some(model.to_string()),
+        system_prompt,
+        temperature: o.temperature.or(defaults.temperature),
+        max_tokens: o.max_tokens.or(defaults.max_tokens),
+        top_p: o.top_p.or(defaults.top_p),
+        top_k: o.top_k.or(defaults.top_k),
+        min_p: o.min_p.or(defaults.min_p),
+        repeat_penalty: o.repeat_penalty.or(defaults.repeat_penalty),
+        seed: o.seed.or(defaults.seed),
+        num_ctx: o.num_ctx.or(defaults.num_ctx),
+        stop: (!stop.is_empty()).then_some(stop),
+    }
+}
@@ -0,0 +1,169 @@
+use serde::Serialize;
+use std::fmt::Write;
+
+use super::assertions::AssertionResult;
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub suite: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    /// Case names in suite order; every target has one result per case.
+    pub cases: Vec<String>,
+    pub targets: Vec<TargetReport>,
+}
+
+/// One persona on one model.
+#[derive(Debug, Serialize)]
+pub struct TargetReport {
+    pub persona: Option<String>,
+    pub provider: String,
+    pub model: String,
+    /// Digest of the installed weights, to tell two builds under the same
+    /// tag apart.
+    pub digest: Option<String>,
+    pub passed: usize,
+    pub failed: usize,
+    pub results: Vec<CaseResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaseResult {
+    pub case: String,
+    pub passed: bool,
+    pub reply: String,
+    /// Set when the model couldn't be reached or the request failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    pub assertions: Vec<AssertionResult>,
+}
+
+/// A standalone page with cases down the side and targets across the top,
+/// each cell expanding to the reply and the failed checks.
+pub fn to_html(report: &Report) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+         <style>{STYLE}</style></head><body>\n<h1>{title}</h1>\n\
+         <p class=\"meta\">{cases} cases × {targets} persona/model pairs · {secs}s</p>\n<table>\n<tr><th>case</th>",
+        title = escape(&report.suite),
+        cases = report.cases.len(),
+        targets = report.targets.len(),
+        secs = report.finished_at - report.started_at,
+    );
+    for target in &report.targets {
+        let _ = write!(
+            html,
+            "<th>{}<div class=\"model\">{}/{}</div><div class=\"digest\" title=\"{}\">{}</div></th>",
+            escape(target.persona.as_deref().unwrap_or("no persona")),
+            escape(&target.provider),
+            escape(&target.model),
+            escape(target.digest.as_deref().unwrap_or_default()),
+            escape(&short_digest(target.digest.as_deref())),
+        );
+    }
+    html.push_str("</tr>\n<tr class=\"totals\"><td>passed</td>");
+    for target in &report.targets {
+        let total = target.passed + target.failed;
+        let _ = write!(
+            html,
+            "<td class=\"{}\">{}/{}</td>",
+            if target.failed == 0 { "pass" } else { "fail" },
+            target.passed,
+            total
+        );
+    }
+    html.push_str("</tr>\n");
+
+    for (i, case) in report.cases.iter().enumerate() {
+        let _ = write!(html, "<tr><td class=\"case\">{}</td>", escape(case));
+        for target in &report.targets {
+            match target.results.get(i) {
+                Some(result) => cell(&mut html, result),
+                None => html.push_str("<td></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n</body></html>\n");
+    html
+}
+
+fn cell(html: &mut String, result: &CaseResult) {
+    let checks = result.assertions.len();
+    let passed = result.assertions.iter().filter(|a| a.passed).count();
+    let _ = write!(
+        html,
+        "<td class=\"{}\"><details><summary>{} · {:.1}s</summary>",
+        if result.passed { "pass" } else { "fail" },
+        match &result.error {
+            Some(_) => "error".to_string(),
+            None => format!("{}/{}", passed, checks),
+        },
+        result.duration_ms as f64 / 1000.0,
+    );
+    if let Some(error) = &result.error {
+        let _ = write!(html, "<p class=\"error\">{}</p>", escape(error));
+    }
+    if !result.assertions.is_empty() {
+        html.push_str("<ul>");
+        for a in &result.assertions {
+            let _ = write!(
+                html,
+                "<li class=\"{}\">{}{}</li>",
+                if a.passed { "pass" } else { "fail" },
+                escape(&a.label),
+                if a.detail.is_empty() {
+                    String::new()
+                } else {
+                    format!(" — {}", escape(&a.detail))
+                },
+            );
+        }
+        html.push_str("</ul>");
+    }
+    let _ = write!(html, "<pre>{}</pre></details></td>", escape(&result.reply));
+}
+
+fn short_digest(digest: Option<&str>) -> String {
+    match digest {
+        Some(d) => d
+            .strip_prefix("sha256:")
+            .unwrap_or(d)
+            .chars()
+            .take(12)
+            .collect(),
+        None => "digest not reported".to_string(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "\
+body{font:14px system-ui,sans-serif;margin:2rem;color:#222}\
+table{border-collapse:collapse}\
+th,td{border:1px solid #ddd;padding:.4rem .6rem;vertical-align:top;text-align:left}\
+th .model,th .digest{font-weight:normal;color:#666;font-size:12px}\
+.meta{color:#666}\
+.case{font-weight:600}\
+td.pass{background:#eefbea}\
+td.fail{background:#fdecea}\
+li.pass{color:#2a7a2a}\
+li.fail{color:#b3261e}\
+.error{color:#b3261e}\
+pre{white-space:pre-wrap;max-width:40rem;max-height:24rem;overflow:auto;font-size:12px}\
+summary{cursor:pointer}";
@@ -0,0 +1,312 @@
+use regex::Regex;
+use serde_json::Value;
+
+/// Check `value` against the commonly used core of JSON Schema: `type`,
+/// `enum`, `const`, `properties`, `required`, `additionalProperties: false`,
+/// `items`, `minItems`/`maxItems`, `minLength`/`maxLength`, `pattern`,
+/// `minimum`/`maximum`, and `anyOf`/`oneOf`/`allOf`. Other keywords are
+/// ignored. Returns one message per violation, each prefixed with a JSON
+/// pointer to the offending value.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    validate_at(schema, value, "")
+}
+
+fn check(schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        // `true`/`false` schemas accept anything / nothing.
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{}: no value is allowed here", pointer(at)));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let names: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(list) => list.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !names.is_empty() && !names.iter().any(|name| is_type(value, name)) {
+            errors.push(format!(
+                "{}: expected {}, got {}",
+                pointer(at),
+                names.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!(
+                "{}: {} is not one of {:?}",
+                pointer(at),
+                value,
+                allowed
+            ));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            errors.push(format!("{}: expected {}", pointer(at), constant));
+        }
+    }
+
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !object.contains_key(key) {
+                        errors.push(format!("{}: missing required \"{}\"", pointer(at), key));
+                    }
+                }
+            }
+            for (key, child) in object {
+                let path = format!("{}/{}", at, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(sub) => check(sub, child, &path, errors),
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        errors.push(format!("{}: unexpected property", pointer(&path)))
+                    }
+                    None => {
+                        if let Some(extra @ Value::Object(_)) = schema.get("additionalProperties") {
+                            check(extra, child, &path, errors);
+                        }
+                    }
+                }
+            }
+        }
+        Value::Array(items) => {
+            bounds(
+                schema,
+                "minItems",
+                "maxItems",
+                items.len(),
+                "items",
+                at,
+                errors,
+            );
+            if let Some(sub) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    check(sub, item, &format!("{}/{}", at, i), errors);
+                }
+            }
+        }
+        Value::String(text) => {
+            let len = text.chars().count();
+            bounds(
+                schema,
+                "minLength",
+                "maxLength",
+                len,
+                "characters",
+                at,
+                errors,
+            );
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                match Regex::new(pattern) {
+                    Ok(re) if !re.is_match(text) => {
+                        errors.push(format!("{}: does not match /{}/", pointer(at), pattern))
+                    }
+                    Ok(_) => {}
+                    Err(e) => errors.push(format!("schema pattern /{}/: {}", pattern, e)),
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!(
+                        "{}: {} is below the minimum {}",
+                        pointer(at),
+                        n,
+                        min
+                    ));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!(
+                        "{}: {} is above the maximum {}",
+                        pointer(at),
+                        n,
+                        max
+                    ));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            check(sub, value, at, errors);
+        }
+    }
+    for (keyword, exactly_one) in [("anyOf", false), ("oneOf", true)] {
+        if let Some(Value::Array(options)) = schema.get(keyword) {
+            let matching = options
+                .iter()
+                .filter(|sub| validate_at(sub, value, at).is_empty())
+                .count();
+            if matching == 0 || (exactly_one && matching > 1) {
+                errors.push(format!(
+                    "{}: matches {} of the {} {} schemas",
+                    pointer(at),
+                    matching,
+                    options.len(),
+                    keyword
+                ));
+            }
+        }
+    }
+}
+
+fn validate_at(schema: &Value, value: &Value, at: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, value, at, &mut errors);
+    errors
+}
+
+fn bounds(
+    schema: &serde_json::Map<String, Value>,
+    min_key: &str,
+    max_key: &str,
+    len: usize,
+    unit: &str,
+    at: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(min) = schema.get(min_key).and_then(Value::as_u64) {
+        if (len as u64) < min {
+            errors.push(format!(
+                "{}: {} {}, fewer than {}",
+                pointer(at),
+                len,
+                unit,
+                min
+            ));
+        }
+    }
+    if let Some(max) = schema.get(max_key).and_then(Value::as_u64) {
+        if len as u64 > max {
+            errors.push(format!(
+                "{}: {} {}, more than {}",
+                pointer(at),
+                len,
+                unit,
+                max
+            ));
+        }
+    }
+}
+
+fn is_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some(),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn pointer(at: &str) -> &str {
+    if at.is_empty() {
+        "/"
+    } else {
+        at
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn objects_check_types_required_and_extra_properties() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "minLength": 1},
+                "age": {"type": "integer", "minimum": 0},
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+        });
+        assert!(validate(&schema, &json!({"name": "Ada", "age": 36})).is_empty());
+        assert_eq!(
+            validate(&schema, &json!({"age": 1.5, "x": 1})),
+            [
+                "/: missing required \"name\"",
+                "/age: expected integer, got number",
+                "/x: unexpected property",
+            ]
+        );
+        assert_eq!(
+            validate(&schema, &json!([])),
+            ["/: expected object, got array"]
+        );
+    }
+
+    #[test]
+    fn arrays_strings_and_numbers_check_their_bounds() {
+        let schema = json!({
+            "type": "array",
+            "minItems": 1,
+            "maxItems": 2,
+            "items": {"type": "string", "pattern": "^[a-z]+$", "maxLength": 3},
+        });
+        assert!(validate(&schema, &json!(["ab"])).is_empty());
+        assert_eq!(validate(&schema, &json!([])), ["/: 0 items, fewer than 1"]);
+        assert_eq!(
+            validate(&schema, &json!(["abcd", "A", "x"])),
+            [
+                "/: 3 items, more than 2",
+                "/0: 4 characters, more than 3",
+                "/1: does not match /^[a-z]+$/",
+            ]
+        );
+        let range = json!({"type": "number", "minimum": 0, "maximum": 1});
+        assert_eq!(validate(&range, &json!(2)), ["/: 2 is above the maximum 1"]);
+        // Lengths count characters, not bytes.
+        assert!(validate(&json!({"maxLength": 2}), &json!("éé")).is_empty());
+    }
+
+    #[test]
+    fn enum_const_and_combinators() {
+        assert!(validate(&json!({"enum": ["a", "b"]}), &json!("a")).is_empty());
+        assert_eq!(validate(&json!({"const": 3}), &json!(4)), ["/: expected 3"]);
+        assert!(validate(&json!({"type": ["string", "null"]}), &json!(null)).is_empty());
+
+        let one_of = json!({"oneOf": [{"type": "integer"}, {"type": "number"}]});
+        assert!(validate(&one_of, &json!(1.5)).is_empty());
+        assert_eq!(
+            validate(&one_of, &json!(1)),
+            ["/: matches 2 of the 2 oneOf schemas"]
+        );
+        let any_of = json!({"anyOf": [{"type": "string"}, {"minimum": 5}]});
+        assert!(validate(&any_of, &json!(7)).is_empty());
+        assert_eq!(
+            validate(&any_of, &json!(1)),
+            ["/: matches 0 of the 2 anyOf schemas"]
+        );
+        assert_eq!(
+            validate(&json!({"properties": {"a": false}}), &json!({"a": 1})),
+            ["/a: no value is allowed here"]
+        );
+    }
+}
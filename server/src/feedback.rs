@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::Mutex};
+
+use crate::config::FeedbackConfig;
+
+/// Appends rated replies to a JSONL file, one record per line, for
+/// building fine-tuning and evaluation sets outside the app.
+pub struct FeedbackLog {
+    path: Option<PathBuf>,
+    /// Keeps concurrent records from interleaving.
+    write: Mutex<()>,
+}
+
+impl FeedbackLog {
+    pub fn new(config: FeedbackConfig) -> Self {
+        Self {
+            path: config.file.map(PathBuf::from),
+            write: Mutex::new(()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub async fn append(&self, record: &impl Serialize) -> Result<()> {
+        let Some(path) = &self.path else {
+            anyhow::bail!("feedback log is disabled");
+        };
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let _guard = self.write.lock().await;
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("opening {}", path.display()))?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+}
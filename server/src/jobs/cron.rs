@@ -0,0 +1,281 @@
+use anyhow::{bail, Context, Result};
+use std::str::FromStr;
+use time::{Date, Duration, Month, OffsetDateTime, Time};
+
+/// A standard five-field cron expression (`minute hour day-of-month month
+/// day-of-week`), evaluated in UTC. Fields take `*`, numbers, `a-b` ranges,
+/// `,` lists and `/step`; months and weekdays also take three-letter names.
+/// As in cron, when both day fields are restricted a day matching either
+/// one fires. `@hourly`, `@daily`, `@weekly` and `@monthly` are shorthands.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// How far ahead `next_after` looks before deciding a schedule never fires
+/// (e.g. `0 0 30 2 *`).
+const SEARCH_DAYS: i64 = 366 * 5;
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expr: &str) -> Result<Self> {
+        let expanded = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            );
+        };
+        let mut weekdays = field(weekday, 0, 7, &WEEKDAYS).context("weekday")?;
+        // 7 is another name for Sunday.
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        let schedule = Self {
+            minutes: field(minute, 0, 59, &[]).context("minute")?,
+            hours: field(hour, 0, 23, &[]).context("hour")?,
+            days: field(day, 1, 31, &[]).context("day of month")?,
+            months: field(month, 1, 12, &MONTHS).context("month")?,
+            weekdays,
+            any_day: day.starts_with('*'),
+            any_weekday: weekday.starts_with('*'),
+        };
+        if schedule.next_after(OffsetDateTime::now_utc()).is_none() {
+            bail!("never fires");
+        }
+        Ok(schedule)
+    }
+}
+
+impl Schedule {
+    /// The first matching minute strictly after `after`.
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let start = after.replace_time(Time::from_hms(after.hour(), after.minute(), 0).ok()?)
+            + Duration::minutes(1);
+        let limit = start + Duration::days(SEARCH_DAYS);
+        let mut t = start;
+        while t < limit {
+            if !bit(self.months, u8::from(t.month())) {
+                t = first_of_next_month(t.date())?;
+            } else if !self.day_matches(t.date()) {
+                t = t.date().next_day()?.midnight().assume_utc();
+            } else if !bit(self.hours, t.hour()) {
+                t = t.replace_time(Time::from_hms(t.hour(), 0, 0).ok()?) + Duration::hours(1);
+            } else if !bit(self.minutes, t.minute()) {
+                t += Duration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+
+    fn day_matches(&self, date: Date) -> bool {
+        let day = bit(self.days, date.day());
+        let weekday = bit(self.weekdays, date.weekday().number_days_from_sunday());
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+}
+
+fn bit(set: u64, n: u8) -> bool {
+    set & (1 << n) != 0
+}
+
+fn first_of_next_month(date: Date) -> Option<OffsetDateTime> {
+    let (year, month) = match date.month() {
+        Month::December => (date.year() + 1, Month::January),
+        m => (date.year(), m.next()),
+    };
+    Some(
+        Date::from_calendar_date(year, month, 1)
+            .ok()?
+            .midnight()
+            .assume_utc(),
+    )
+}
+
+/// Parse one field into a bit set over `min..=max`.
+fn field(spec: &str, min: u8, max: u8, names: &[&str]) -> Result<u64> {
+    let mut set = 0u64;
+    for part in spec.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (
+                range,
+                step.parse::<u8>()
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .with_context(|| format!("bad step in {:?}", part))?,
+            ),
+            None => (part, 1),
+        };
+        let (lo, hi) = if range == "*" {
+            (min, max)
+        } else if let Some((lo, hi)) = range.split_once('-') {
+            (value(lo, min, names)?, value(hi, min, names)?)
+        } else {
+            let v = value(range, min, names)?;
+            // `5/15` means from 5 to the end in steps of 15.
+            (v, if part.contains('/') { max } else { v })
+        };
+        if lo < min || hi > max || lo > hi {
+            bail!("{:?} is outside {}-{}", part, min, max);
+        }
+        for n in (lo..=hi).step_by(step as usize) {
+            set |= 1 << n;
+        }
+    }
+    Ok(set)
+}
+
+fn value(text: &str, min: u8, names: &[&str]) -> Result<u8> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(i) = names.iter().position(|n| *n == lower) {
+        return Ok(min + i as u8);
+    }
+    text.parse()
+        .with_context(|| format!("{:?} is not a number", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u8, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        Date::from_calendar_date(year, Month::try_from(month).unwrap(), day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    fn next(expr: &str, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        expr.parse::<Schedule>().unwrap().next_after(after)
+    }
+
+    fn bits(set: u64) -> Vec<u8> {
+        (0..64).filter(|n| bit(set, *n)).collect()
+    }
+
+    #[test]
+    fn ranges_lists_and_steps() {
+        assert_eq!(bits(field("*/15", 0, 59, &[]).unwrap()), [0, 15, 30, 45]);
+        assert_eq!(bits(field("5/20", 0, 59, &[]).unwrap()), [5, 25, 45]);
+        assert_eq!(bits(field("1-10/3", 0, 59, &[]).unwrap()), [1, 4, 7, 10]);
+        assert_eq!(bits(field("1,3-4,9", 0, 59, &[]).unwrap()), [1, 3, 4, 9]);
+
+        let expr = "*/15 9-17/4 * * *";
+        assert_eq!(
+            next(expr, at(2026, 10, 17, 8, 59)),
+            Some(at(2026, 10, 17, 9, 0))
+        );
+        assert_eq!(
+            next(expr, at(2026, 10, 17, 9, 50)),
+            Some(at(2026, 10, 17, 13, 0))
+        );
+        assert_eq!(
+            next(expr, at(2026, 10, 17, 17, 45)),
+            Some(at(2026, 10, 18, 9, 0))
+        );
+    }
+
+    #[test]
+    fn month_and_weekday_names() {
+        assert_eq!(
+            bits(field("jan-mar,Dec", 1, 12, &MONTHS).unwrap()),
+            [1, 2, 3, 12]
+        );
+        assert_eq!(
+            bits(field("MON-fri", 0, 7, &WEEKDAYS).unwrap()),
+            [1, 2, 3, 4, 5]
+        );
+        // Saturday in October; December 1st is the next weekday in the set.
+        assert_eq!(
+            next("0 12 * jan-mar,dec mon-fri", at(2026, 10, 17, 12, 0)),
+            Some(at(2026, 12, 1, 12, 0))
+        );
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 20th is a Tuesday, the 23rd a Friday.
+        let expr = "0 0 20 * fri";
+        assert_eq!(
+            next(expr, at(2026, 10, 18, 0, 0)),
+            Some(at(2026, 10, 20, 0, 0))
+        );
+        assert_eq!(
+            next(expr, at(2026, 10, 20, 0, 0)),
+            Some(at(2026, 10, 23, 0, 0))
+        );
+        // With the day of month left open, only the weekday counts.
+        assert_eq!(
+            next("0 0 * * fri", at(2026, 10, 18, 0, 0)),
+            Some(at(2026, 10, 23, 0, 0))
+        );
+        assert_eq!(
+            next("0 0 20 * *", at(2026, 10, 20, 0, 0)),
+            Some(at(2026, 11, 20, 0, 0))
+        );
+    }
+
+    #[test]
+    fn seven_is_sunday() {
+        // From Saturday noon.
+        let saturday = at(2026, 10, 17, 12, 0);
+        assert_eq!(next("0 0 * * 7", saturday), Some(at(2026, 10, 18, 0, 0)));
+        assert_eq!(next("0 0 * * 0", saturday), Some(at(2026, 10, 18, 0, 0)));
+        assert_eq!(next("@weekly", saturday), Some(at(2026, 10, 18, 0, 0)));
+        let schedule: Schedule = "0 0 * * 5-7".parse().unwrap();
+        assert_eq!(bits(schedule.weekdays), [0, 5, 6]);
+    }
+
+    #[test]
+    fn schedules_that_never_fire_are_rejected() {
+        for expr in ["0 0 30 2 *", "0 0 31 4,6,9,11 *"] {
+            let err = expr.parse::<Schedule>().unwrap_err();
+            assert!(err.to_string().contains("never fires"), "{}: {}", expr, err);
+        }
+        // Leap days are rare but within the search window.
+        assert_eq!(
+            next("0 0 29 2 *", at(2026, 10, 18, 0, 0)),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn malformed_fields_are_rejected() {
+        for expr in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "0 0 * * fri-sun",
+            "0 0 * foo *",
+        ] {
+            assert!(expr.parse::<Schedule>().is_err(), "{}", expr);
+        }
+    }
+}
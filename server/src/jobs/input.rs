@@ -0,0 +1,195 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::store::jobs::JobResult;
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    Csv,
+    Jsonl,
+}
+
+/// Uploaded rows, each a column → value map. `columns` lays out downloads:
+/// the header's order for CSV, every key seen (alphabetically) for JSONL.
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Map<String, Value>>,
+}
+
+pub fn parse(format: InputFormat, text: &str) -> Result<Table> {
+    match format {
+        InputFormat::Csv => parse_csv(text),
+        InputFormat::Jsonl => parse_jsonl(text),
+    }
+}
+
+/// The first line is the header; every row must have as many fields.
+fn parse_csv(text: &str) -> Result<Table> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(text.as_bytes());
+    let columns: Vec<String> = reader
+        .headers()
+        .context("reading the header line")?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    if let Some(i) = columns.iter().position(String::is_empty) {
+        bail!("column {} has no name in the header line", i + 1);
+    }
+    for (i, column) in columns.iter().enumerate() {
+        if columns[..i].contains(column) {
+            bail!("column {:?} appears twice in the header line", column);
+        }
+    }
+    let mut rows = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.with_context(|| format!("row {}", i + 1))?;
+        rows.push(
+            columns
+                .iter()
+                .zip(record.iter())
+                .map(|(c, v)| (c.clone(), Value::String(v.to_string())))
+                .collect(),
+        );
+    }
+    Ok(Table { columns, rows })
+}
+
+/// One JSON object per line. Strings are used as they are; other values
+/// are inserted as their JSON text.
+fn parse_jsonl(text: &str) -> Result<Table> {
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = serde_json::from_str(line).with_context(|| format!("line {}", i + 1))?;
+        let Value::Object(object) = value else {
+            bail!("line {}: expected a JSON object", i + 1);
+        };
+        for key in object.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+        rows.push(object);
+    }
+    columns.sort();
+    Ok(Table { columns, rows })
+}
+
+/// A row value as text for the prompt template.
+pub fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// The input columns followed by `prompt`, `reply` and `error`, plus `run`
+/// for scheduled jobs.
+pub fn to_csv(columns: &[String], results: &[JobResult], with_run: bool) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut header: Vec<&str> = Vec::new();
+    if with_run {
+        header.push("run");
+    }
+    header.extend(columns.iter().map(String::as_str));
+    header.extend(["prompt", "reply", "error"]);
+    writer.write_record(&header)?;
+    for result in results {
+        let mut record: Vec<String> = Vec::new();
+        if with_run {
+            record.push(result.run.to_string());
+        }
+        record.extend(
+            columns
+                .iter()
+                .map(|c| result.input.get(c).map(cell_text).unwrap_or_default()),
+        );
+        record.push(result.prompt.clone());
+        record.push(result.reply.clone().unwrap_or_default());
+        record.push(result.error.clone().unwrap_or_default());
+        writer.write_record(&record)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// One object per result: the input row plus `prompt`, `reply`, `error`
+/// and `run`.
+pub fn to_jsonl(results: &[JobResult]) -> Result<String> {
+    let mut out = String::new();
+    for result in results {
+        let mut object = result.input.clone();
+        object.insert("run".into(), result.run.into());
+        object.insert("prompt".into(), result.prompt.clone().into());
+        object.insert("reply".into(), result.reply.clone().into());
+        object.insert("error".into(), result.error.clone().into());
+        out.push_str(&serde_json::to_string(&Value::Object(object))?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(row: &Map<String, Value>, column: &str) -> String {
+        row.get(column).map(cell_text).unwrap_or_default()
+    }
+
+    fn error(format: InputFormat, text: &str) -> String {
+        match parse(format, text) {
+            Ok(_) => panic!("parsed {:?}", text),
+            Err(e) => format!("{:#}", e),
+        }
+    }
+
+    #[test]
+    fn csv_quoted_fields_keep_newlines_commas_and_quotes() {
+        let table = parse(
+            InputFormat::Csv,
+            " name , note\nalice,\"line one\nline two\"\nbob,\"a, \"\"quoted\"\" b\"\n",
+        )
+        .unwrap();
+        assert_eq!(table.columns, ["name", "note"]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(text(&table.rows[0], "note"), "line one\nline two");
+        assert_eq!(text(&table.rows[1], "note"), "a, \"quoted\" b");
+    }
+
+    #[test]
+    fn csv_rejects_bad_headers_and_ragged_rows() {
+        assert!(error(InputFormat::Csv, "a,,c\n1,2,3\n").contains("no name"));
+        assert!(error(InputFormat::Csv, "a,b,a\n1,2,3\n").contains("twice"));
+        let err = error(InputFormat::Csv, "a,b\n1,2\n3\n");
+        assert!(err.contains("row 2"), "{}", err);
+    }
+
+    #[test]
+    fn jsonl_skips_blank_lines_and_collects_columns() {
+        let table = parse(
+            InputFormat::Jsonl,
+            "{\"b\": \"x\", \"n\": 2}\n\n   \n{\"a\": null, \"b\": \"y\"}\n",
+        )
+        .unwrap();
+        assert_eq!(table.columns, ["a", "b", "n"]);
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(text(&table.rows[0], "n"), "2");
+        assert_eq!(text(&table.rows[1], "a"), "");
+    }
+
+    #[test]
+    fn jsonl_reports_the_bad_line() {
+        let err = error(InputFormat::Jsonl, "{\"a\": 1}\n\n{oops\n");
+        assert!(err.contains("line 3"), "{}", err);
+        let err = error(InputFormat::Jsonl, "[1, 2]\n");
+        assert!(err.contains("expected a JSON object"), "{}", err);
+    }
+}
@@ -0,0 +1,252 @@
+//! Batch and scheduled prompt jobs: a persona and a prompt template applied
+//! to every row of an uploaded CSV/JSONL file, once or on a cron schedule.
+//! Rows, progress and replies live in the store (see `store::jobs`), so a
+//! run interrupted by a restart picks up where it stopped.
+
+use anyhow::Result;
+use futures::StreamExt;
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use time::OffsetDateTime;
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{info, warn};
+
+pub mod cron;
+pub mod input;
+
+use crate::config::JobsConfig;
+use crate::prompts::{self, TemplateContext};
+use crate::providers::{ChatMessage, ChatRequest};
+use crate::store::jobs::{Job, JobStatus};
+use crate::AppState;
+
+/// How often scheduled jobs are checked for a due run.
+const WATCH_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Updates pushed to the owner's open `/api/jobs/events` streams.
+#[derive(Debug, Clone)]
+pub enum JobEvent {
+    Updated(Box<Job>),
+    Deleted { sub: String, id: String },
+}
+
+impl JobEvent {
+    pub fn sub(&self) -> &str {
+        match self {
+            Self::Updated(job) => &job.sub,
+            Self::Deleted { sub, .. } => sub,
+        }
+    }
+}
+
+pub struct JobRunner {
+    config: JobsConfig,
+    /// Shared by all jobs, so the configured concurrency is a server-wide cap
+    /// on top of each job's own.
+    permits: Arc<Semaphore>,
+    events: broadcast::Sender<JobEvent>,
+    /// Stop flags of the runs in progress, by job id.
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl JobRunner {
+    pub fn new(config: JobsConfig) -> Self {
+        let (events, _) = broadcast::channel(256);
+        Self {
+            permits: Arc::new(Semaphore::new(config.concurrency)),
+            config,
+            events,
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.concurrency > 0
+    }
+
+    pub fn max_concurrency(&self) -> usize {
+        self.config.concurrency
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<JobEvent> {
+        self.events.subscribe()
+    }
+
+    pub fn notify(&self, event: JobEvent) {
+        // No receivers just means nobody has the jobs page open.
+        let _ = self.events.send(event);
+    }
+
+    /// Ask a running job to stop after the rows already in flight. Returns
+    /// false when the job isn't running.
+    pub fn stop(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Start (or, for a job left `running` by a restart, resume) a run of `job`
+/// in the background. Does nothing if it is already running.
+pub fn start(state: &Arc<AppState>, job: &Job) {
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let mut running = state.jobs.running.lock().unwrap();
+        if running.contains_key(&job.id) {
+            return;
+        }
+        running.insert(job.id.clone(), stop.clone());
+    }
+    let state = state.clone();
+    let (id, resume) = (job.id.clone(), job.status == JobStatus::Running);
+    tokio::spawn(async move {
+        if let Err(e) = run(&state, &id, resume, &stop).await {
+            warn!("Job {} failed: {:#}", id, e);
+            match state
+                .store
+                .finish_job_run(&id, JobStatus::Failed, None, Some(format!("{:#}", e)))
+                .await
+            {
+                Ok(Some(job)) => state.jobs.notify(JobEvent::Updated(Box::new(job))),
+                Ok(None) => {}
+                Err(e) => warn!("Failed to record job {} failure: {:#}", id, e),
+            }
+        }
+        state.jobs.running.lock().unwrap().remove(&id);
+    });
+}
+
+async fn run(state: &AppState, id: &str, resume: bool, stop: &AtomicBool) -> Result<()> {
+    let Some(job) = state.store.start_job_run(id, resume).await? else {
+        return Ok(());
+    };
+    info!("Job {} ({}) run {} started", job.id, job.name, job.run);
+    state.jobs.notify(JobEvent::Updated(Box::new(job.clone())));
+
+    let pending = state.store.pending_job_inputs(&job.id, job.run).await?;
+    let job = &job;
+    futures::stream::iter(pending)
+        .for_each_concurrent(job.spec.concurrency.max(1), |(index, row)| async move {
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            let Ok(_permit) = state.jobs.permits.acquire().await else {
+                return;
+            };
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+            let request = build_request(job, &row);
+            let prompt = request.messages[0].content.clone();
+            let outcome = state
+                .providers
+                .complete(&job.spec.provider, request)
+                .await
+                .map_err(|e| format!("{:#}", e));
+            match state
+                .store
+                .record_job_result(&job.id, job.run, index, prompt, outcome)
+                .await
+            {
+                Ok(Some(updated)) => state.jobs.notify(JobEvent::Updated(Box::new(updated))),
+                // Deleted while running.
+                Ok(None) => stop.store(true, Ordering::Relaxed),
+                Err(e) => warn!("Failed to record job {} row {}: {:#}", job.id, index, e),
+            }
+        })
+        .await;
+
+    let (status, next_run_at) = if stop.load(Ordering::Relaxed) {
+        (JobStatus::Cancelled, None)
+    } else {
+        match job.schedule.as_deref().and_then(next_run) {
+            Some(at) => (JobStatus::Scheduled, Some(at)),
+            None => (JobStatus::Done, None),
+        }
+    };
+    if let Some(job) = state
+        .store
+        .finish_job_run(&job.id, status, next_run_at, None)
+        .await?
+    {
+        info!(
+            "Job {} ({}) run {} {:?}",
+            job.id, job.name, job.run, job.status
+        );
+        state.jobs.notify(JobEvent::Updated(Box::new(job)));
+    }
+    Ok(())
+}
+
+/// The row's columns fill the template's placeholders, over the job's own
+/// variables; the persona's system prompt sees both.
+fn build_request(job: &Job, row: &Map<String, Value>) -> ChatRequest {
+    let spec = &job.spec;
+    let mut variables = spec.variables.clone();
+    variables.extend(row.iter().map(|(k, v)| (k.clone(), input::cell_text(v))));
+    let ctx = TemplateContext {
+        username: &job.username,
+        model: &spec.model,
+        variables: &variables,
+    };
+    let o = &spec.options;
+    ChatRequest {
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: prompts::render(&spec.template, &ctx),
+        }],
+        model: Some(spec.model.clone()),
+        system_prompt: spec
+            .system_prompt
+            .as_deref()
+            .map(|p| prompts::render(p, &ctx)),
+        temperature: o.temperature,
+        max_tokens: o.max_tokens,
+        top_p: o.top_p,
+        top_k: o.top_k,
+        min_p: o.min_p,
+        repeat_penalty: o.repeat_penalty,
+        seed: o.seed,
+        num_ctx: o.num_ctx,
+        stop: (!o.stop.is_empty()).then(|| o.stop.clone()),
+    }
+}
+
+/// When a job with cron `schedule` should next run, as a unix timestamp.
+pub fn next_run(schedule: &str) -> Option<i64> {
+    let schedule: cron::Schedule = schedule.parse().ok()?;
+    schedule
+        .next_after(OffsetDateTime::now_utc())
+        .map(|t| t.unix_timestamp())
+}
+
+/// Resume runs interrupted by a restart, then start scheduled jobs as they
+/// come due.
+pub async fn watch(state: Arc<AppState>) {
+    if !state.jobs.enabled() {
+        return;
+    }
+    match state.store.unfinished_jobs().await {
+        Ok(jobs) => jobs.iter().for_each(|job| start(&state, job)),
+        Err(e) => warn!("Failed to load unfinished jobs: {:#}", e),
+    }
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        match state.store.due_jobs().await {
+            Ok(jobs) => jobs.iter().for_each(|job| start(&state, job)),
+            Err(e) => warn!("Failed to load due jobs: {:#}", e),
+        }
+    }
+}
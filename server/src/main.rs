@@ -1,32 +1,62 @@
 use anyhow::Result;
-use axum::{http::Method, Router};
+use axum::{extract::DefaultBodyLimit, http::Method, Router};
 use std::{net::SocketAddr, sync::Arc};
 use tower_http::{
     cors::{Any, CorsLayer},
-    services::ServeDir,
+    services::{ServeDir, ServeFile},
     trace::TraceLayer,
 };
-use tracing::{info, Level};
+use tracing::{info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
 mod api;
 mod auth;
+mod clock;
 mod config;
 mod error;
+mod eval;
+mod feedback;
+mod jobs;
+mod prompts;
 mod providers;
+mod sandbox;
+mod store;
+mod tokenizer;
 
 use auth::OidcClient;
 use config::Config;
+use feedback::FeedbackLog;
+use jobs::JobRunner;
+use prompts::PromptStore;
 use providers::ProviderManager;
+use sandbox::Sandbox;
+use store::Store;
+use tokenizer::TokenizerStore;
 
 pub struct AppState {
     pub config: Config,
     pub providers: ProviderManager,
     pub oidc: OidcClient,
+    pub sandbox: Sandbox,
+    pub prompts: PromptStore,
+    pub store: Store,
+    pub tokenizers: TokenizerStore,
+    pub feedback: FeedbackLog,
+    pub jobs: JobRunner,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("eval") {
+        // Progress goes to stderr so a report on stdout stays clean.
+        FmtSubscriber::builder()
+            .with_max_level(Level::INFO)
+            .with_writer(std::io::stderr)
+            .init();
+        return eval::main(args.collect()).await;
+    }
+
     FmtSubscriber::builder().with_max_level(Level::INFO).init();
 
     info!("Starting GameCode Web server...");
@@ -37,22 +67,70 @@ async fn main() -> Result<()> {
     let oidc = OidcClient::discover(config.auth.oidc.clone()).await?;
     info!("OIDC metadata discovered: issuer={}", oidc.config.issuer);
 
-    let providers = ProviderManager::new(&config).await?;
+    let providers = ProviderManager::new(&config.providers).await?;
     info!("Providers initialized: {:?}", providers.list_available());
 
+    let mut sandbox = Sandbox::new(config.sandbox.clone());
+    sandbox.probe().await;
+    if sandbox.enabled() {
+        info!("Sandboxed code execution enabled");
+    } else if let Some(reason) = sandbox.unavailable() {
+        warn!("Sandboxed code execution unavailable: {}", reason);
+    }
+
+    let prompts = PromptStore::load(&providers.all_models().await);
+
+    let store = Store::open(&config.store.db_path)?;
+    info!("Database opened at {}", config.store.db_path);
+
+    let tokenizers = TokenizerStore::new(config.tokenizer.clone());
+    if tokenizers.enabled() {
+        info!("Exact token counting enabled");
+    }
+
+    let feedback = FeedbackLog::new(config.feedback.clone());
+    if feedback.enabled() {
+        info!("Reply feedback is logged to a JSONL file");
+    }
+
+    let jobs = JobRunner::new(config.jobs.clone());
+    if jobs.enabled() {
+        info!(
+            "Batch jobs enabled: {} rows at a time",
+            jobs.max_concurrency()
+        );
+    }
+
     let state = Arc::new(AppState {
         config: config.clone(),
         providers,
         oidc,
+        sandbox,
+        prompts,
+        store,
+        tokenizers,
+        feedback,
+        jobs,
     });
+    tokio::spawn(prompts::watch(state.clone()));
+    tokio::spawn(jobs::watch(state.clone()));
 
     let app = Router::new()
-        .nest("/api", api::routes(state.clone()))
-        .fallback_service(ServeDir::new(&config.server.static_dir))
+        .nest(
+            "/api",
+            api::routes(state.clone()).layer(DefaultBodyLimit::max(config.server.max_request_size)),
+        )
+        .fallback_service(
+            // Client-side routes (e.g. /admin) are served the SPA shell.
+            ServeDir::new(&config.server.static_dir).not_found_service(ServeFile::new(format!(
+                "{}/index.html",
+                config.server.static_dir
+            ))),
+        )
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
-                .allow_methods([Method::GET, Method::POST])
+                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
                 .allow_headers(Any),
         )
         .layer(TraceLayer::new_for_http());
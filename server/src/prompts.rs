@@ -0,0 +1,359 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use time::OffsetDateTime;
+
+use crate::{clock::now_secs, AppState};
+
+const PROMPTS_PATHS: &[&str] = &[
+    "/usr/local/etc/gamecode-web/prompts.toml",
+    "config/prompts.toml",
+];
+
+/// How often the watcher checks prompts.toml for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Older prompts.toml files end with an empty "Custom" entry for ad-hoc
+/// prompts; users now keep those as their own personas.
+const LEGACY_CUSTOM_PROMPT: &str = "Custom";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemPrompt {
+    /// Set only on a user's own personas (see `store::personas`); entries from
+    /// prompts.toml have no id and can't be edited from the UI.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub name: String,
+    pub prompt: String,
+    pub suggested_models: Vec<String>,
+    #[serde(default)]
+    pub options: PromptOptions,
+    #[serde(default)]
+    pub variables: Vec<PromptVariable>,
+}
+
+/// Sampling defaults a persona declares under `[prompts.options]`. The client
+/// applies them when the persona is selected; unset fields keep the provider
+/// default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PromptOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+/// A `{{name}}` placeholder the persona asks the user to fill in, declared
+/// under `[[prompts.variables]]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptVariable {
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PromptsConfig {
+    prompts: Vec<SystemPrompt>,
+}
+
+/// Outcome of the most recent (re)load, for the admin health view.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptStatus {
+    /// File the current prompts came from; `None` means the built-in defaults.
+    pub source: Option<String>,
+    pub loaded_at: i64,
+    pub count: usize,
+    /// Why the last attempt was rejected. The previous good set stays active.
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+    pub warnings: Vec<String>,
+}
+
+struct Loaded {
+    prompts: Vec<SystemPrompt>,
+    status: PromptStatus,
+}
+
+impl Loaded {
+    fn defaults() -> Self {
+        let prompts = default_prompts();
+        Self {
+            status: PromptStatus {
+                source: None,
+                loaded_at: now_secs(),
+                count: prompts.len(),
+                last_error: None,
+                last_error_at: None,
+                warnings: Vec::new(),
+            },
+            prompts,
+        }
+    }
+}
+
+/// Prompts parsed once at startup and swapped in place when prompts.toml
+/// changes. A file that fails to parse or validate never replaces a good set.
+pub struct PromptStore {
+    current: RwLock<Loaded>,
+}
+
+impl PromptStore {
+    pub fn load(known_models: &[String]) -> Self {
+        let store = Self {
+            current: RwLock::new(Loaded::defaults()),
+        };
+        store.reload(known_models);
+        store
+    }
+
+    pub fn prompts(&self) -> Vec<SystemPrompt> {
+        self.current.read().unwrap().prompts.clone()
+    }
+
+    pub fn status(&self) -> PromptStatus {
+        self.current.read().unwrap().status.clone()
+    }
+
+    pub fn reload(&self, known_models: &[String]) {
+        let Some(path) = locate() else {
+            tracing::info!("Using default prompts (no prompts.toml found)");
+            *self.current.write().unwrap() = Loaded::defaults();
+            return;
+        };
+
+        match read(path) {
+            Ok((prompts, mut warnings)) => {
+                warnings.extend(check_models(&prompts, known_models));
+                for warning in &warnings {
+                    tracing::warn!("prompts.toml: {}", warning);
+                }
+                tracing::info!("Loaded {} prompts from: {}", prompts.len(), path);
+                *self.current.write().unwrap() = Loaded {
+                    status: PromptStatus {
+                        source: Some(path.to_string()),
+                        loaded_at: now_secs(),
+                        count: prompts.len(),
+                        last_error: None,
+                        last_error_at: None,
+                        warnings,
+                    },
+                    prompts,
+                };
+            }
+            Err(e) => {
+                let message = format!("{path}: {e:#}");
+                tracing::warn!("Keeping previous prompts; rejected {}", message);
+                let mut current = self.current.write().unwrap();
+                current.status.last_error = Some(message);
+                current.status.last_error_at = Some(now_secs());
+            }
+        }
+    }
+}
+
+/// Poll prompts.toml and reload it whenever its path or mtime changes.
+/// Polling rather than inotify keeps editors that save via rename working.
+pub async fn watch(state: Arc<AppState>) {
+    let mut seen = fingerprint();
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        interval.tick().await;
+        let current = fingerprint();
+        if current != seen {
+            seen = current;
+            let models = state.providers.all_models().await;
+            state.prompts.reload(&models);
+        }
+    }
+}
+
+fn locate() -> Option<&'static str> {
+    PROMPTS_PATHS
+        .iter()
+        .copied()
+        .find(|path| fs::metadata(path).is_ok())
+}
+
+fn fingerprint() -> Option<(&'static str, Option<SystemTime>)> {
+    locate().map(|path| (path, fs::metadata(path).and_then(|m| m.modified()).ok()))
+}
+
+/// The file's prompts and warnings about entries that were skipped.
+fn read(path: &str) -> Result<(Vec<SystemPrompt>, Vec<String>)> {
+    let content = fs::read_to_string(path).context("read file")?;
+    let mut config: PromptsConfig = toml::from_str(&content).context("parse TOML")?;
+    let mut warnings = Vec::new();
+    config.prompts.retain(|p| {
+        let legacy = p.name.trim() == LEGACY_CUSTOM_PROMPT && p.prompt.trim().is_empty();
+        if legacy {
+            warnings.push(format!(
+                "skipped the empty \"{LEGACY_CUSTOM_PROMPT}\" entry; ad-hoc prompts are now \
+                 personas each user creates in the persona picker, so it can be removed"
+            ));
+        }
+        !legacy
+    });
+    validate(&config.prompts)?;
+    Ok((config.prompts, warnings))
+}
+
+fn validate(prompts: &[SystemPrompt]) -> Result<()> {
+    let mut errors = Vec::new();
+    if prompts.is_empty() {
+        errors.push("no [[prompts]] entries".to_string());
+    }
+    let mut names = HashSet::new();
+    for (i, p) in prompts.iter().enumerate() {
+        if p.name.trim().is_empty() {
+            errors.push(format!("prompt #{} has an empty name", i + 1));
+            continue;
+        }
+        if !names.insert(p.name.trim()) {
+            errors.push(format!("duplicate prompt name \"{}\"", p.name.trim()));
+        }
+        errors.extend(entry_errors(p));
+    }
+    if !errors.is_empty() {
+        bail!("{}", errors.join("; "));
+    }
+    Ok(())
+}
+
+/// Problems with a single persona, shared by prompts.toml and user personas.
+pub fn entry_errors(p: &SystemPrompt) -> Vec<String> {
+    let name = p.name.trim();
+    let mut errors = Vec::new();
+    if name.is_empty() {
+        errors.push("name is empty".to_string());
+    }
+    if p.prompt.trim().is_empty() {
+        errors.push(format!("prompt \"{name}\" is empty"));
+    }
+    let mut vars = HashSet::new();
+    for var in &p.variables {
+        if var.name.trim().is_empty() {
+            errors.push(format!(
+                "prompt \"{name}\" declares a variable with no name"
+            ));
+        } else if !vars.insert(var.name.as_str()) {
+            errors.push(format!(
+                "prompt \"{name}\" declares variable \"{}\" twice",
+                var.name
+            ));
+        }
+    }
+    errors
+}
+
+/// Suggested models the providers don't report. Only a warning: the model may
+/// simply not be pulled yet. Skipped when no provider listed any models.
+fn check_models(prompts: &[SystemPrompt], known_models: &[String]) -> Vec<String> {
+    if known_models.is_empty() {
+        return Vec::new();
+    }
+    // Ollama treats `name` and `name:latest` as the same model.
+    let base = |m: &str| m.strip_suffix(":latest").unwrap_or(m).to_string();
+    let known = |model: &str| known_models.iter().any(|k| base(k) == base(model));
+    prompts
+        .iter()
+        .flat_map(|p| {
+            p.suggested_models
+                .iter()
+                .filter(|m| !known(m))
+                .map(move |m| format!("prompt \"{}\" suggests unknown model \"{}\"", p.name, m))
+        })
+        .collect()
+}
+
+fn default_prompts() -> Vec<SystemPrompt> {
+    vec![SystemPrompt {
+        id: None,
+        name: "General Assistant".to_string(),
+        prompt: "You are a helpful AI assistant.".to_string(),
+        suggested_models: vec!["qwen3:14b".to_string()],
+        options: PromptOptions::default(),
+        variables: vec![],
+    }]
+}
+
+pub struct TemplateContext<'a> {
+    pub username: &'a str,
+    pub model: &'a str,
+    pub variables: &'a HashMap<String, String>,
+}
+
+/// Replace `{{name}}` placeholders in a system prompt. The built-ins
+/// `{{date}}`, `{{username}}` and `{{model}}` win over user-supplied values;
+/// unknown placeholders are left untouched so a typo is visible in the
+/// model's behaviour rather than silently dropped.
+pub fn render(template: &str, ctx: &TemplateContext) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let name = after[..end].trim();
+        match resolve(name, ctx) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn resolve(name: &str, ctx: &TemplateContext) -> Option<String> {
+    match name {
+        "date" => Some(OffsetDateTime::now_utc().date().to_string()),
+        "username" => Some(ctx.username.to_string()),
+        "model" => Some(ctx.model.to_string()),
+        _ => ctx.variables.get(name).cloned(),
+    }
+}
+
+/// Names of the `{{name}}` placeholders in `template` that aren't built-ins,
+/// in order of first use.
+pub fn placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        let name = after[..end].trim();
+        if !matches!(name, "date" | "username" | "model") && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        rest = &after[end + 2..];
+    }
+    names
+}
@@ -7,7 +7,7 @@ use std::pin::Pin;
 
 pub mod ollama;
 
-use crate::config::Config;
+use crate::config::ProvidersConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -15,13 +15,20 @@ pub struct ChatMessage {
     pub content: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ChatRequest {
     pub messages: Vec<ChatMessage>,
     pub model: Option<String>,
     pub system_prompt: Option<String>,
     pub temperature: Option<f32>,
     pub max_tokens: Option<usize>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub min_p: Option<f32>,
+    pub repeat_penalty: Option<f32>,
+    pub seed: Option<i64>,
+    pub num_ctx: Option<usize>,
+    pub stop: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,6 +52,27 @@ pub trait InferenceProvider: Send + Sync {
 
     /// Stream a chat response
     async fn chat(&self, request: ChatRequest) -> Result<ChatStream>;
+
+    /// Model used by `embed`, if this provider is configured for embeddings
+    fn embedding_model(&self) -> Option<&str> {
+        None
+    }
+
+    /// Model to summarize conversations with instead of the chat's own
+    fn summary_model(&self) -> Option<&str> {
+        None
+    }
+
+    /// Content digest of the installed `model`, so a reply can be traced to
+    /// the exact weights that wrote it
+    async fn model_digest(&self, _model: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// Embed each input with `embedding_model`, one vector per input
+    async fn embed(&self, _inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        anyhow::bail!("{} does not support embeddings", self.name())
+    }
 }
 
 pub struct ProviderManager {
@@ -52,11 +80,11 @@ pub struct ProviderManager {
 }
 
 impl ProviderManager {
-    pub async fn new(config: &Config) -> Result<Self> {
+    pub async fn new(config: &ProvidersConfig) -> Result<Self> {
         let mut providers = HashMap::new();
 
         // Initialize Ollama provider if configured
-        if let Some(ollama_config) = &config.providers.ollama {
+        if let Some(ollama_config) = &config.ollama {
             if ollama_config.enabled {
                 let ollama = ollama::OllamaProvider::new(ollama_config.clone());
                 if ollama.available().await {
@@ -88,6 +116,25 @@ impl ProviderManager {
         self.providers.keys().cloned().collect()
     }
 
+    /// Every model reported by the available providers; providers that fail
+    /// to answer are skipped.
+    pub async fn all_models(&self) -> Vec<String> {
+        let mut models = Vec::new();
+        for provider in self.providers.values() {
+            if let Ok(list) = provider.list_models().await {
+                models.extend(list);
+            }
+        }
+        models
+    }
+
+    /// The first provider with an embedding model, and that model's name.
+    pub fn embedder(&self) -> Option<(&dyn InferenceProvider, &str)> {
+        self.providers
+            .values()
+            .find_map(|p| p.embedding_model().map(|m| (p.as_ref(), m)))
+    }
+
     pub async fn chat(&self, provider_name: &str, request: ChatRequest) -> Result<ChatStream> {
         let provider = self
             .get(provider_name)
@@ -95,4 +142,36 @@ impl ProviderManager {
 
         provider.chat(request).await
     }
+
+    /// Run `request` to completion and return the whole reply.
+    pub async fn complete(&self, provider_name: &str, request: ChatRequest) -> Result<String> {
+        use futures::StreamExt;
+
+        let mut stream = self.chat(provider_name, request).await?;
+        let mut text = String::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            text.push_str(&chunk.text);
+            if chunk.done {
+                break;
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Drop `<think>…</think>` blocks that reasoning models put before their
+/// answer. An unclosed block hides everything after it.
+pub fn strip_thinking(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("<think>") {
+        out.push_str(&rest[..start]);
+        match rest[start..].find("</think>") {
+            Some(end) => rest = &rest[start + end + "</think>".len()..],
+            None => return out,
+        }
+    }
+    out.push_str(rest);
+    out
 }
@@ -36,6 +36,20 @@ struct OllamaOptions {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     num_predict: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
 }
 
 #[derive(Deserialize)]
@@ -49,6 +63,17 @@ struct OllamaChatResponseMessage {
     content: String,
 }
 
+#[derive(Serialize)]
+struct OllamaEmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct OllamaEmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
 #[derive(Deserialize)]
 struct OllamaModelResponse {
     models: Vec<OllamaModel>,
@@ -57,6 +82,8 @@ struct OllamaModelResponse {
 #[derive(Deserialize)]
 struct OllamaModel {
     name: String,
+    #[serde(default)]
+    digest: String,
 }
 
 impl OllamaProvider {
@@ -93,6 +120,65 @@ impl InferenceProvider for OllamaProvider {
         Ok(models.models.into_iter().map(|m| m.name).collect())
     }
 
+    fn embedding_model(&self) -> Option<&str> {
+        self.config.embedding_model.as_deref()
+    }
+
+    async fn model_digest(&self, model: &str) -> Result<Option<String>> {
+        let url = format!("{}/api/tags", self.config.base_url);
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to list Ollama models: {}", response.status());
+        }
+
+        // Ollama treats a bare name as `name:latest`.
+        let tagged = format!("{}:latest", model);
+        let models: OllamaModelResponse = response.json().await?;
+        Ok(models
+            .models
+            .into_iter()
+            .find(|m| m.name == model || m.name == tagged)
+            .map(|m| m.digest)
+            .filter(|d| !d.is_empty()))
+    }
+
+    fn summary_model(&self) -> Option<&str> {
+        self.config.summary_model.as_deref()
+    }
+
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let model = self
+            .config
+            .embedding_model
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("GAMECODE_OLLAMA_EMBEDDING_MODEL is unset"))?;
+        let url = format!("{}/api/embed", self.config.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&OllamaEmbedRequest {
+                model,
+                input: inputs,
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama embed request failed: {}", response.status());
+        }
+
+        let body: OllamaEmbedResponse = response.json().await?;
+        if body.embeddings.len() != inputs.len() {
+            anyhow::bail!(
+                "Ollama returned {} embeddings for {} inputs",
+                body.embeddings.len(),
+                inputs.len()
+            );
+        }
+        Ok(body.embeddings)
+    }
+
     async fn chat(&self, request: ChatRequest) -> Result<ChatStream> {
         let model = request
             .model
@@ -132,6 +218,13 @@ impl InferenceProvider for OllamaProvider {
             options: Some(OllamaOptions {
                 temperature: request.temperature,
                 num_predict: request.max_tokens,
+                top_p: request.top_p,
+                top_k: request.top_k,
+                min_p: request.min_p,
+                repeat_penalty: request.repeat_penalty,
+                seed: request.seed,
+                num_ctx: request.num_ctx,
+                stop: request.stop.clone().filter(|s| !s.is_empty()),
             }),
         };
 
@@ -0,0 +1,621 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::{
+    ffi::CString,
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::{Duration, Instant},
+};
+use tempfile::TempDir;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    process::Command,
+    sync::Semaphore,
+};
+
+use crate::config::SandboxConfig;
+
+/// Largest file a snippet may write inside its temp directory.
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+/// How long to wait for output pipes to close after the process group is killed.
+const DRAIN_GRACE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    Shell,
+    Rust,
+}
+
+impl Language {
+    /// Fence tags accepted for each language, as written after ``` in markdown.
+    pub const ALIASES: &'static [(&'static str, Language)] = &[
+        ("python", Language::Python),
+        ("python3", Language::Python),
+        ("py", Language::Python),
+        ("sh", Language::Shell),
+        ("bash", Language::Shell),
+        ("shell", Language::Shell),
+        ("rust", Language::Rust),
+        ("rs", Language::Rust),
+    ];
+
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_ascii_lowercase();
+        Self::ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == tag)
+            .map(|(_, lang)| *lang)
+    }
+
+    fn source_file(self) -> &'static str {
+        match self {
+            Language::Python => "main.py",
+            Language::Shell => "main.sh",
+            Language::Rust => "main.rs",
+        }
+    }
+
+    /// The commands a snippet runs, in order; a failing step ends the run.
+    fn steps(self) -> &'static [Step] {
+        match self {
+            Language::Python => &[Step {
+                argv: &["python3", "main.py"],
+                compile: false,
+            }],
+            Language::Shell => &[Step {
+                argv: &["sh", "main.sh"],
+                compile: false,
+            }],
+            Language::Rust => &[
+                Step {
+                    argv: &["rustc", "--edition", "2021", "-o", "main", "main.rs"],
+                    compile: true,
+                },
+                Step {
+                    argv: &["./main"],
+                    compile: false,
+                },
+            ],
+        }
+    }
+}
+
+struct Step {
+    argv: &'static [&'static str],
+    /// Compilers get `compile_memory_mb`; rustc needs far more address space
+    /// than the snippets it builds.
+    compile: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub truncated: bool,
+    pub duration_ms: u64,
+}
+
+pub struct Sandbox {
+    config: SandboxConfig,
+    /// Caps concurrent runs; each may hold up to `max_processes` processes.
+    permits: Semaphore,
+    /// Why snippets can't be confined on this host, from `probe`.
+    unavailable: Option<String>,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig) -> Self {
+        Self {
+            permits: Semaphore::new(config.max_concurrent.max(1)),
+            config,
+            unavailable: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled && self.unavailable.is_none()
+    }
+
+    /// Set when the sandbox is configured on but this host can't run it
+    /// (user namespaces disabled, or unshare and mount blocked by seccomp and
+    /// AppArmor as under Docker's defaults).
+    pub fn unavailable(&self) -> Option<&str> {
+        self.unavailable.as_deref()
+    }
+
+    /// Confine a trivial command once at startup, so a host that can't
+    /// isolate snippets turns the feature off with a reason instead of
+    /// failing every run.
+    pub async fn probe(&mut self) {
+        if !self.config.enabled {
+            return;
+        }
+        let result = async {
+            let jail = Jail::prepare(&self.config)?;
+            let deadline = Instant::now() + Duration::from_secs(self.config.timeout_seconds);
+            let output = self
+                .exec(
+                    &jail,
+                    &["sh", "-c", "exit 0"],
+                    self.config.memory_mb,
+                    deadline,
+                )
+                .await?;
+            if output.exit_code != Some(0) {
+                bail!("test command failed: {}", output.stderr.trim());
+            }
+            Ok(())
+        }
+        .await
+        .context(
+            "cannot confine snippets; the sandbox needs unprivileged user namespaces \
+             and, in a container, seccomp and AppArmor profiles that allow unshare \
+             and mount",
+        );
+        if let Err(e) = result {
+            self.unavailable = Some(format!("{:#}", e));
+        }
+    }
+
+    /// Run `source` in its own user and PID namespaces, chrooted into a
+    /// fresh tree holding only read-only system directories and its own
+    /// work directory, with no network, capped memory, processes and file
+    /// sizes, and a wall-clock timeout. The whole process group is killed
+    /// when the timeout fires or the snippet exits.
+    pub async fn run(&self, language: Language, source: &str) -> Result<RunOutput> {
+        if !self.enabled() {
+            bail!("code execution is disabled");
+        }
+        let _permit = self.permits.acquire().await?;
+
+        let jail = Jail::prepare(&self.config)?;
+        tokio::fs::write(jail.work().join(language.source_file()), source)
+            .await
+            .context("write snippet")?;
+
+        let started = Instant::now();
+        let deadline = started + Duration::from_secs(self.config.timeout_seconds);
+        let mut output = RunOutput::default();
+        for step in language.steps() {
+            let memory_mb = if step.compile {
+                self.config.compile_memory_mb
+            } else {
+                self.config.memory_mb
+            };
+            let step = self.exec(&jail, step.argv, memory_mb, deadline).await?;
+            output.stdout.push_str(&step.stdout);
+            output.stderr.push_str(&step.stderr);
+            output.exit_code = step.exit_code;
+            output.timed_out = step.timed_out;
+            output.truncated |= step.truncated;
+            if step.timed_out || step.exit_code != Some(0) {
+                break;
+            }
+        }
+        output.duration_ms = started.elapsed().as_millis() as u64;
+        Ok(output)
+    }
+
+    async fn exec(
+        &self,
+        jail: &Jail,
+        argv: &[&str],
+        memory_mb: u64,
+        deadline: Instant,
+    ) -> Result<RunOutput> {
+        let mut cmd = Command::new(argv[0]);
+        cmd.args(&argv[1..])
+            .env_clear()
+            .env("PATH", "/usr/local/bin:/usr/bin:/bin")
+            .env("HOME", "/work")
+            .env("TMPDIR", "/work")
+            .env("LANG", "C.UTF-8")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .process_group(0);
+        confine(&mut cmd, &self.config, jail, memory_mb)?;
+
+        let started = Instant::now();
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("start {} in the sandbox", argv[0]))?;
+        let pid = child.id();
+        let limit = self.config.max_output_bytes;
+        let stdout = tokio::spawn(read_capped(child.stdout.take(), limit));
+        let stderr = tokio::spawn(read_capped(child.stderr.take(), limit));
+
+        let timeout = deadline.saturating_duration_since(started);
+        let (exit_code, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => (status.context("wait for sandboxed process")?.code(), false),
+            Err(_) => (None, true),
+        };
+        // Reap anything the snippet left running in its group, then collect output.
+        kill_group(pid);
+        let _ = child.wait().await;
+
+        let (stdout, out_truncated) = collect(stdout).await;
+        let (stderr, err_truncated) = collect(stderr).await;
+
+        Ok(RunOutput {
+            stdout,
+            stderr,
+            exit_code,
+            timed_out,
+            truncated: out_truncated || err_truncated,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+/// Host paths bound read-only into every snippet's root when they exist:
+/// the shell, python and the C linker rustc calls. `/bin` and friends are
+/// often symlinks into `/usr` and are recreated as such.
+const SYSTEM_MOUNTS: &[&str] = &[
+    "/usr",
+    "/bin",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/etc/alternatives",
+    "/etc/ld.so.cache",
+];
+/// Device nodes a snippet may open; everything else in `/dev` is absent.
+const DEVICES: &[&str] = &["/dev/null", "/dev/zero", "/dev/random", "/dev/urandom"];
+
+/// A snippet's root directory. The mount points are empty until the child
+/// binds the host paths over them in its own mount namespace, so the host
+/// never sees those mounts and dropping the temp dir cleans up.
+struct Jail {
+    root: TempDir,
+    mounts: Vec<Mount>,
+    proc: CString,
+}
+
+#[derive(Clone)]
+struct Mount {
+    source: CString,
+    target: CString,
+    /// Flags for the read-only remount, or `None` to leave the bind as is.
+    /// They carry over the source's nosuid/noexec/atime flags, which a user
+    /// namespace may not clear.
+    remount: Option<libc::c_ulong>,
+}
+
+impl Jail {
+    fn prepare(config: &SandboxConfig) -> Result<Self> {
+        let root = tempfile::Builder::new()
+            .prefix("gamecode-run-")
+            .tempdir()
+            .context("create sandbox root")?;
+        // The sandbox user has to be able to walk the tree.
+        std::fs::set_permissions(root.path(), std::fs::Permissions::from_mode(0o755))?;
+
+        let system = SYSTEM_MOUNTS
+            .iter()
+            .copied()
+            .chain(config.mounts.iter().map(String::as_str))
+            .map(|path| (path, true));
+        let devices = DEVICES.iter().map(|path| (*path, false));
+        let mut mounts = Vec::new();
+        for (path, read_only) in system.chain(devices) {
+            let host = Path::new(path);
+            if !host.is_absolute() {
+                bail!("sandbox mount {} must be an absolute path", path);
+            }
+            let Ok(meta) = std::fs::symlink_metadata(host) else {
+                continue;
+            };
+            let inside = root.path().join(host.strip_prefix("/")?);
+            if let Some(parent) = inside.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if meta.is_symlink() {
+                std::os::unix::fs::symlink(std::fs::read_link(host)?, &inside)?;
+                continue;
+            }
+            if meta.is_dir() {
+                std::fs::create_dir(&inside)?;
+            } else {
+                std::fs::File::create(&inside)?;
+            }
+            let source = CString::new(host.as_os_str().as_bytes())?;
+            let remount = read_only
+                .then(|| remount_flags(&source))
+                .transpose()
+                .with_context(|| format!("stat sandbox mount {}", path))?;
+            mounts.push(Mount {
+                source,
+                target: CString::new(inside.as_os_str().as_bytes())?,
+                remount,
+            });
+        }
+
+        let proc = root.path().join("proc");
+        std::fs::create_dir(&proc)?;
+        let proc = CString::new(proc.as_os_str().as_bytes())?;
+
+        let work = root.path().join("work");
+        std::fs::create_dir(&work)?;
+        // Unprivileged, snippets run as the server's own uid outside their
+        // namespace and already own the dir.
+        if running_as_root() {
+            std::os::unix::fs::chown(&work, Some(config.uid), Some(config.gid))
+                .context("hand the work dir to the sandbox user")?;
+        }
+        Ok(Self { root, mounts, proc })
+    }
+
+    fn work(&self) -> PathBuf {
+        self.root.path().join("work")
+    }
+}
+
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, limit: usize) -> (Vec<u8>, bool) {
+    let Some(mut reader) = reader else {
+        return (Vec::new(), false);
+    };
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let room = limit.saturating_sub(kept.len());
+                if n > room {
+                    truncated = true;
+                }
+                kept.extend_from_slice(&buf[..n.min(room)]);
+            }
+        }
+    }
+    (kept, truncated)
+}
+
+async fn collect(task: tokio::task::JoinHandle<(Vec<u8>, bool)>) -> (String, bool) {
+    match tokio::time::timeout(DRAIN_GRACE, task).await {
+        Ok(Ok((bytes, truncated))) => (String::from_utf8_lossy(&bytes).into_owned(), truncated),
+        _ => (String::new(), true),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn confine(cmd: &mut Command, config: &SandboxConfig, jail: &Jail, memory_mb: u64) -> Result<()> {
+    if config.uid == 0 {
+        bail!("GAMECODE_SANDBOX_UID must not be 0");
+    }
+    let memory_bytes = memory_mb.saturating_mul(1024 * 1024);
+    let cpu_seconds = config.timeout_seconds + 1;
+    let max_processes = config.max_processes;
+    let (uid, gid) = (config.uid, config.gid);
+    // Map the sandbox ids inside the namespace onto the server's own ids
+    // outside, which needs no privilege. A root server first becomes the
+    // sandbox user, so snippets never run as root on the host.
+    let privileged = running_as_root();
+    // SAFETY: geteuid and getegid can't fail.
+    let (outer_uid, outer_gid) = if privileged {
+        (uid, gid)
+    } else {
+        unsafe { (libc::geteuid(), libc::getegid()) }
+    };
+    let uid_map = format!("{} {} 1\n", uid, outer_uid).into_bytes();
+    let gid_map = format!("{} {} 1\n", gid, outer_gid).into_bytes();
+    let root = CString::new(jail.root.path().as_os_str().as_bytes())?;
+    let mounts = jail.mounts.clone();
+    let proc = jail.proc.clone();
+    // SAFETY: the closure runs in the forked child before exec. Everything it
+    // needs is allocated up front; it only makes async-signal-safe syscalls.
+    unsafe {
+        cmd.pre_exec(move || {
+            if privileged {
+                check(libc::setgroups(0, std::ptr::null()))?;
+                check(libc::setgid(gid))?;
+                check(libc::setuid(uid))?;
+                // Changing ids left /proc/self owned by root; the maps below
+                // are written through it.
+                check(libc::prctl(libc::PR_SET_DUMPABLE, 1, 0, 0, 0))?;
+            }
+            // New user, mount, network, IPC, UTS and PID namespaces: the binds
+            // below stay private to the snippet, which sees only a downed
+            // loopback and none of the server's processes.
+            check(libc::unshare(
+                libc::CLONE_NEWUSER
+                    | libc::CLONE_NEWNS
+                    | libc::CLONE_NEWNET
+                    | libc::CLONE_NEWIPC
+                    | libc::CLONE_NEWUTS
+                    | libc::CLONE_NEWPID,
+            ))?;
+            // Required before an unprivileged process may write gid_map.
+            write_proc(c"/proc/self/setgroups", b"deny")?;
+            write_proc(c"/proc/self/uid_map", &uid_map)?;
+            write_proc(c"/proc/self/gid_map", &gid_map)?;
+
+            // Only children enter the new PID namespace. This process stays
+            // behind as the one the server waits on and passes on the
+            // snippet's exit; the snippet becomes pid 1, so everything it
+            // leaves running dies with it.
+            let pid = libc::fork();
+            if pid < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            if pid > 0 {
+                relay_exit(pid);
+            }
+
+            check(libc::mount(
+                std::ptr::null(),
+                c"/".as_ptr(),
+                std::ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            ))?;
+            for m in &mounts {
+                check(libc::mount(
+                    m.source.as_ptr(),
+                    m.target.as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_BIND | libc::MS_REC,
+                    std::ptr::null(),
+                ))?;
+                if let Some(flags) = m.remount {
+                    check(libc::mount(
+                        std::ptr::null(),
+                        m.target.as_ptr(),
+                        std::ptr::null(),
+                        libc::MS_REMOUNT | libc::MS_BIND | flags,
+                        std::ptr::null(),
+                    ))?;
+                }
+            }
+            // A procfs of the snippet's own PID namespace (rustc's linker
+            // reads /proc/self/exe), with everything but the pid entries
+            // hidden.
+            check(libc::mount(
+                c"proc".as_ptr(),
+                proc.as_ptr(),
+                c"proc".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                c"hidepid=invisible,subset=pid".as_ptr().cast(),
+            ))?;
+            // No data directory, no server binary: only the jail. The kernel
+            // refuses new user namespaces inside a chroot, so the snippet
+            // can't regain the capability to leave it.
+            check(libc::chroot(root.as_ptr()))?;
+            check(libc::chdir(c"/work".as_ptr()))?;
+            check(libc::setgid(gid))?;
+            check(libc::setuid(uid))?;
+            // With a non-zero uid, exec drops the namespace's capabilities.
+            check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+            set_limit(libc::RLIMIT_AS, memory_bytes)?;
+            set_limit(libc::RLIMIT_CPU, cpu_seconds)?;
+            set_limit(libc::RLIMIT_FSIZE, MAX_FILE_BYTES)?;
+            // Counted per user namespace, so per snippet.
+            set_limit(libc::RLIMIT_NPROC, max_processes)?;
+            set_limit(libc::RLIMIT_CORE, 0)?;
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// Wait for the snippet in the parent left outside its PID namespace, then
+/// exit the same way. Never returns.
+#[cfg(target_os = "linux")]
+unsafe fn relay_exit(pid: libc::pid_t) -> ! {
+    // Drop our copy of std's exec-error pipe (and anything else inherited),
+    // or spawn would wait for this process to exit.
+    if libc::syscall(libc::SYS_close_range, 3u32, u32::MAX, 0u32) != 0 {
+        for fd in 3..1024 {
+            libc::close(fd);
+        }
+    }
+    let mut status = 0;
+    while libc::waitpid(pid, &mut status, 0) < 0 {
+        if std::io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+            libc::_exit(127);
+        }
+    }
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        libc::signal(signal, libc::SIG_DFL);
+        libc::kill(libc::getpid(), signal);
+        libc::_exit(128 + signal);
+    }
+    libc::_exit(libc::WEXITSTATUS(status))
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn write_proc(path: &std::ffi::CStr, data: &[u8]) -> std::io::Result<()> {
+    let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let written = libc::write(fd, data.as_ptr().cast(), data.len());
+    let result = if written == data.len() as isize {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    };
+    libc::close(fd);
+    result
+}
+
+/// Read-only remount flags for a bind of `source`, keeping the flags its
+/// mount already has.
+#[cfg(target_os = "linux")]
+fn remount_flags(source: &CString) -> std::io::Result<libc::c_ulong> {
+    // SAFETY: `stat` is written by statvfs before it is read.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    check(unsafe { libc::statvfs(source.as_ptr(), &mut stat) })?;
+    let kept = [
+        (libc::ST_NOSUID, libc::MS_NOSUID),
+        (libc::ST_NODEV, libc::MS_NODEV),
+        (libc::ST_NOEXEC, libc::MS_NOEXEC),
+        (libc::ST_NOATIME, libc::MS_NOATIME),
+        (libc::ST_NODIRATIME, libc::MS_NODIRATIME),
+        (libc::ST_RELATIME, libc::MS_RELATIME),
+    ];
+    Ok(kept.iter().filter(|(st, _)| stat.f_flag & st != 0).fold(
+        libc::MS_RDONLY | libc::MS_NOSUID | libc::MS_NODEV,
+        |flags, (_, ms)| flags | ms,
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn remount_flags(_source: &CString) -> std::io::Result<libc::c_ulong> {
+    Ok(0)
+}
+
+fn running_as_root() -> bool {
+    // SAFETY: geteuid can't fail.
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(target_os = "linux")]
+fn check(ret: libc::c_int) -> std::io::Result<()> {
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(target_os = "linux", not(target_env = "gnu")))]
+type Resource = libc::c_int;
+
+#[cfg(target_os = "linux")]
+fn set_limit(resource: Resource, value: u64) -> std::io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value,
+        rlim_max: value,
+    };
+    // SAFETY: `limit` is a valid rlimit for the duration of the call.
+    check(unsafe { libc::setrlimit(resource, &limit) })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn confine(
+    _cmd: &mut Command,
+    _config: &SandboxConfig,
+    _jail: &Jail,
+    _memory_mb: u64,
+) -> Result<()> {
+    bail!("sandboxed code execution is only supported on Linux")
+}
+
+fn kill_group(pid: Option<u32>) {
+    if let Some(pid) = pid {
+        // SAFETY: signalling a process group we created; a stale id is harmless.
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use super::Store;
+
+/// One conversation as the sync protocol sees it. `data` is the client's
+/// `StoredConversation` JSON, kept opaque here; it is `None` on tombstones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationRecord {
+    pub id: String,
+    /// Client-side `metadata.modified_at` in milliseconds; the newer write wins.
+    pub modified_at: i64,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConversationSummary {
+    pub id: String,
+    pub modified_at: i64,
+}
+
+const COLUMNS: &str = "id, modified_at, deleted, data";
+
+/// A `COLUMNS` row; `data` is JSON.
+type ConversationRow = (String, i64, bool, Option<String>);
+
+fn from_row(row: &Row) -> rusqlite::Result<ConversationRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+}
+
+fn decode((id, modified_at, deleted, data): ConversationRow) -> Result<ConversationRecord> {
+    Ok(ConversationRecord {
+        id,
+        modified_at,
+        deleted,
+        data: data.map(|d| serde_json::from_str(&d)).transpose()?,
+    })
+}
+
+/// Whether the stored copy is `record`'s version.
+fn holds(conn: &Connection, sub: &str, record: &ConversationRecord) -> Result<bool> {
+    let stored: Option<(i64, bool)> = conn
+        .query_row(
+            "SELECT modified_at, deleted FROM conversations WHERE sub = ?1 AND id = ?2",
+            params![sub, record.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(stored == Some((record.modified_at, record.deleted)))
+}
+
+/// Insert or replace `record` unless the stored copy is at least as new.
+/// Returns whether it was written. Every write takes the next revision so
+/// clients can ask for "everything after rev N".
+fn apply(conn: &Connection, sub: &str, record: &ConversationRecord) -> Result<bool> {
+    let current: Option<i64> = conn
+        .query_row(
+            "SELECT modified_at FROM conversations WHERE sub = ?1 AND id = ?2",
+            params![sub, record.id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if current.is_some_and(|stored| stored >= record.modified_at) {
+        return Ok(false);
+    }
+    let data = if record.deleted {
+        None
+    } else {
+        record
+            .data
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?
+    };
+    conn.execute(
+        "INSERT INTO conversations (sub, id, modified_at, deleted, data, rev)
+         VALUES (?1, ?2, ?3, ?4, ?5, (SELECT COALESCE(MAX(rev), 0) + 1 FROM conversations))
+         ON CONFLICT (sub, id) DO UPDATE SET
+             modified_at = excluded.modified_at,
+             deleted = excluded.deleted,
+             data = excluded.data,
+             rev = excluded.rev",
+        params![sub, record.id, record.modified_at, record.deleted, data],
+    )?;
+    Ok(true)
+}
+
+fn current_rev(conn: &Connection) -> Result<i64> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(MAX(rev), 0) FROM conversations",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+// Conversations synced from the browser, stored per OIDC subject.
+impl Store {
+    pub async fn list_conversations(&self, sub: &str) -> Result<Vec<ConversationSummary>> {
+        let sub = sub.to_string();
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id, modified_at FROM conversations
+                 WHERE sub = ?1 AND deleted = 0 ORDER BY modified_at DESC",
+            )?;
+            let rows = stmt.query_map(params![sub], |row| {
+                Ok(ConversationSummary {
+                    id: row.get(0)?,
+                    modified_at: row.get(1)?,
+                })
+            })?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        })
+        .await
+    }
+
+    pub async fn get_conversation(
+        &self,
+        sub: &str,
+        id: &str,
+    ) -> Result<Option<ConversationRecord>> {
+        let (sub, id) = (sub.to_string(), id.to_string());
+        self.call(move |conn| {
+            conn.query_row(
+                &format!("SELECT {COLUMNS} FROM conversations WHERE sub = ?1 AND id = ?2"),
+                params![sub, id],
+                from_row,
+            )
+            .optional()?
+            .map(decode)
+            .transpose()
+        })
+        .await
+    }
+
+    /// Last-write-wins upsert (or tombstone). Returns the record now stored,
+    /// which is the caller's when it won and the newer server copy when not.
+    pub async fn put_conversation(
+        &self,
+        sub: &str,
+        record: ConversationRecord,
+    ) -> Result<(bool, ConversationRecord)> {
+        let sub = sub.to_string();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            let applied = apply(&tx, &sub, &record)?;
+            let stored = tx
+                .query_row(
+                    &format!("SELECT {COLUMNS} FROM conversations WHERE sub = ?1 AND id = ?2"),
+                    params![sub, record.id],
+                    from_row,
+                )
+                .map_err(anyhow::Error::from)
+                .and_then(decode)?;
+            tx.commit()?;
+            Ok((applied, stored))
+        })
+        .await
+    }
+
+    /// Apply the client's pending changes, then return the new cursor, the
+    /// ids of the changes the server now holds at the pushed version, and
+    /// everything that changed after revision `since`.
+    pub async fn sync_conversations(
+        &self,
+        sub: &str,
+        since: i64,
+        changes: Vec<ConversationRecord>,
+    ) -> Result<(i64, Vec<String>, Vec<ConversationRecord>)> {
+        let sub = sub.to_string();
+        self.call(move |conn| {
+            let tx = conn.transaction()?;
+            // A cursor from the future means the database was replaced; start over.
+            let since = if since > current_rev(&tx)? { 0 } else { since };
+            let mut acked = Vec::new();
+            for record in &changes {
+                // Already holding this exact version (a retried push whose
+                // response was lost) counts too; losing to a newer copy doesn't.
+                if apply(&tx, &sub, record)? || holds(&tx, &sub, record)? {
+                    acked.push(record.id.clone());
+                }
+            }
+            let updates = {
+                let mut stmt = tx.prepare(&format!(
+                    "SELECT {COLUMNS} FROM conversations WHERE sub = ?1 AND rev > ?2 ORDER BY rev"
+                ))?;
+                let rows = stmt.query_map(params![sub, since], from_row)?;
+                rows.map(|row| decode(row?)).collect::<Result<Vec<_>>>()?
+            };
+            let cursor = current_rev(&tx)?;
+            tx.commit()?;
+            Ok((cursor, acked, updates))
+        })
+        .await
+    }
+}
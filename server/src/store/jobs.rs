@@ -0,0 +1,419 @@
+use anyhow::{anyhow, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+use super::Store;
+use crate::clock::now_secs;
+use crate::prompts::PromptOptions;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Created, waiting for its first run.
+    Queued,
+    Running,
+    /// A scheduled job between runs.
+    Scheduled,
+    Done,
+    Cancelled,
+    /// The run couldn't start or stopped on an error, in `error`.
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Scheduled => "scheduled",
+            Self::Done => "done",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn parse(s: &str) -> Self {
+        match s {
+            "queued" => Self::Queued,
+            "running" => Self::Running,
+            "scheduled" => Self::Scheduled,
+            "done" => Self::Done,
+            "cancelled" => Self::Cancelled,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// What each row is sent: the persona and prompt template, rendered with
+/// the row's columns, to one model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobSpec {
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// The persona's system prompt template, as picked.
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    pub template: String,
+    #[serde(default)]
+    pub options: PromptOptions,
+    pub concurrency: usize,
+    /// Input columns in file order; empty for a scheduled prompt without rows.
+    #[serde(default)]
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    #[serde(skip)]
+    pub sub: String,
+    #[serde(skip)]
+    pub username: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    /// Cron expression for scheduled jobs.
+    pub schedule: Option<String>,
+    pub status: JobStatus,
+    /// Number of the current or last run; 0 before the first.
+    pub run: i64,
+    /// Rows in a run, and how many of them have answered or failed so far.
+    pub total: i64,
+    pub done: i64,
+    pub failed: i64,
+    /// Highest result `seq`, so clients can tell when there is more to fetch.
+    pub last_seq: i64,
+    pub error: Option<String>,
+    pub next_run_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// One row's answer in one run. `seq` increases across all runs of all
+/// jobs.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobResult {
+    pub seq: i64,
+    pub run: i64,
+    pub index: i64,
+    pub input: Map<String, Value>,
+    pub prompt: String,
+    pub reply: Option<String>,
+    pub error: Option<String>,
+    pub finished_at: i64,
+}
+
+pub struct NewJob {
+    pub name: String,
+    pub spec: JobSpec,
+    pub schedule: Option<String>,
+    pub rows: Vec<Map<String, Value>>,
+    pub next_run_at: Option<i64>,
+}
+
+const COLUMNS: &str = "id, sub, username, name, spec, schedule, status, run, total, done, failed,
+    (SELECT COALESCE(MAX(seq), 0) FROM job_results WHERE job_id = jobs.id),
+    error, next_run_at, created_at, updated_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<Job> {
+    let spec: String = row.get(4)?;
+    let status: String = row.get(6)?;
+    Ok(Job {
+        id: row.get(0)?,
+        sub: row.get(1)?,
+        username: row.get(2)?,
+        name: row.get(3)?,
+        spec: serde_json::from_str(&spec).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e))
+        })?,
+        schedule: row.get(5)?,
+        status: JobStatus::parse(&status),
+        run: row.get(7)?,
+        total: row.get(8)?,
+        done: row.get(9)?,
+        failed: row.get(10)?,
+        last_seq: row.get(11)?,
+        error: row.get(12)?,
+        next_run_at: row.get(13)?,
+        created_at: row.get(14)?,
+        updated_at: row.get(15)?,
+    })
+}
+
+fn get(conn: &Connection, id: &str) -> Result<Option<Job>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {COLUMNS} FROM jobs WHERE id = ?1"),
+            params![id],
+            from_row,
+        )
+        .optional()?)
+}
+
+fn select(conn: &Connection, filter: &str, args: impl rusqlite::Params) -> Result<Vec<Job>> {
+    let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM jobs WHERE {filter}"))?;
+    let rows = stmt.query_map(args, from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+// Batch and scheduled prompt jobs, owned per OIDC subject.
+impl Store {
+    pub async fn create_job(&self, sub: &str, username: &str, job: NewJob) -> Result<Job> {
+        let (sub, username) = (sub.to_string(), username.to_string());
+        self.call(move |conn| {
+            let id = uuid::Uuid::new_v4().to_string();
+            let now = now_secs();
+            let status = if job.schedule.is_some() {
+                JobStatus::Scheduled
+            } else {
+                JobStatus::Queued
+            };
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO jobs
+                    (id, sub, username, name, spec, schedule, status, total, next_run_at, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)",
+                params![
+                    id,
+                    sub,
+                    username,
+                    job.name,
+                    serde_json::to_string(&job.spec)?,
+                    job.schedule,
+                    status.as_str(),
+                    job.rows.len().max(1) as i64,
+                    job.next_run_at,
+                    now,
+                ],
+            )?;
+            {
+                let mut insert =
+                    tx.prepare("INSERT INTO job_inputs (job_id, idx, data) VALUES (?1, ?2, ?3)")?;
+                for (i, row) in job.rows.iter().enumerate() {
+                    insert.execute(params![id, i as i64, serde_json::to_string(row)?])?;
+                }
+            }
+            tx.commit()?;
+            get(conn, &id)?.ok_or_else(|| anyhow!("job {id} vanished after insert"))
+        })
+        .await
+    }
+
+    /// Newest first.
+    pub async fn list_jobs(&self, sub: &str) -> Result<Vec<Job>> {
+        let sub = sub.to_string();
+        self.call(move |conn| select(conn, "sub = ?1 ORDER BY created_at DESC", params![sub]))
+            .await
+    }
+
+    pub async fn get_job(&self, sub: &str, id: &str) -> Result<Option<Job>> {
+        let (sub, id) = (sub.to_string(), id.to_string());
+        self.call(move |conn| Ok(get(conn, &id)?.filter(|job| job.sub == sub)))
+            .await
+    }
+
+    /// Jobs whose run was interrupted by a restart, or that never started.
+    pub async fn unfinished_jobs(&self) -> Result<Vec<Job>> {
+        self.call(|conn| select(conn, "status IN ('queued', 'running')", []))
+            .await
+    }
+
+    /// Scheduled jobs whose next run is due.
+    pub async fn due_jobs(&self) -> Result<Vec<Job>> {
+        self.call(|conn| {
+            select(
+                conn,
+                "status = 'scheduled' AND next_run_at <= ?1",
+                params![now_secs()],
+            )
+        })
+        .await
+    }
+
+    /// Mark the job running, starting a fresh run (counters reset) unless
+    /// `resume`. `None` if the job has been deleted.
+    pub async fn start_job_run(&self, id: &str, resume: bool) -> Result<Option<Job>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            let sql = if resume {
+                "UPDATE jobs SET status = 'running', error = NULL, updated_at = ?2 WHERE id = ?1"
+            } else {
+                "UPDATE jobs SET status = 'running', error = NULL, updated_at = ?2,
+                    run = run + 1, done = 0, failed = 0,
+                    total = MAX(1, (SELECT COUNT(*) FROM job_inputs WHERE job_id = ?1))
+                 WHERE id = ?1"
+            };
+            conn.execute(sql, params![id, now_secs()])?;
+            get(conn, &id)
+        })
+        .await
+    }
+
+    /// Rows of `run` that have no result yet, in file order. A job without
+    /// rows has a single empty one.
+    pub async fn pending_job_inputs(
+        &self,
+        id: &str,
+        run: i64,
+    ) -> Result<Vec<(i64, Map<String, Value>)>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            let inputs: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM job_inputs WHERE job_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )?;
+            if inputs == 0 {
+                let answered: bool = conn.query_row(
+                    "SELECT EXISTS (SELECT 1 FROM job_results WHERE job_id = ?1 AND run = ?2)",
+                    params![id, run],
+                    |row| row.get(0),
+                )?;
+                return Ok(if answered {
+                    Vec::new()
+                } else {
+                    vec![(0, Map::new())]
+                });
+            }
+            let mut stmt = conn.prepare(
+                "SELECT idx, data FROM job_inputs i
+                 WHERE job_id = ?1 AND NOT EXISTS (
+                     SELECT 1 FROM job_results r
+                     WHERE r.job_id = i.job_id AND r.run = ?2 AND r.idx = i.idx)
+                 ORDER BY idx",
+            )?;
+            let rows = stmt.query_map(params![id, run], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?;
+            let mut pending = Vec::new();
+            for row in rows {
+                let (idx, data) = row?;
+                pending.push((idx, serde_json::from_str(&data)?));
+            }
+            Ok(pending)
+        })
+        .await
+    }
+
+    /// Store one row's outcome and count it. `None` if the job has been
+    /// deleted meanwhile.
+    pub async fn record_job_result(
+        &self,
+        id: &str,
+        run: i64,
+        index: i64,
+        prompt: String,
+        outcome: Result<String, String>,
+    ) -> Result<Option<Job>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            let now = now_secs();
+            let counter = if outcome.is_ok() { "done" } else { "failed" };
+            // Together, so a crash can't leave a counted row without its
+            // result for a resumed run to count again.
+            let tx = conn.transaction()?;
+            let updated = tx.execute(
+                &format!(
+                    "UPDATE jobs SET {counter} = {counter} + 1, updated_at = ?2 WHERE id = ?1"
+                ),
+                params![id, now],
+            )?;
+            if updated == 0 {
+                return Ok(None);
+            }
+            let (reply, error) = match outcome {
+                Ok(reply) => (Some(reply), None),
+                Err(error) => (None, Some(error)),
+            };
+            tx.execute(
+                "INSERT OR REPLACE INTO job_results
+                    (job_id, run, idx, prompt, reply, error, finished_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![id, run, index, prompt, reply, error, now],
+            )?;
+            let job = get(&tx, &id)?;
+            tx.commit()?;
+            Ok(job)
+        })
+        .await
+    }
+
+    /// End the current run in `status`. `None` if the job has been deleted.
+    pub async fn finish_job_run(
+        &self,
+        id: &str,
+        status: JobStatus,
+        next_run_at: Option<i64>,
+        error: Option<String>,
+    ) -> Result<Option<Job>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            conn.execute(
+                "UPDATE jobs SET status = ?2, next_run_at = ?3, error = ?4, updated_at = ?5
+                 WHERE id = ?1",
+                params![id, status.as_str(), next_run_at, error, now_secs()],
+            )?;
+            get(conn, &id)
+        })
+        .await
+    }
+
+    /// Results after `after` in `seq` order, at most `limit` of them.
+    pub async fn job_results(&self, id: &str, after: i64, limit: i64) -> Result<Vec<JobResult>> {
+        let id = id.to_string();
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT r.seq, r.run, r.idx, i.data, r.prompt, r.reply, r.error, r.finished_at
+                 FROM job_results r
+                 LEFT JOIN job_inputs i ON i.job_id = r.job_id AND i.idx = r.idx
+                 WHERE r.job_id = ?1 AND r.seq > ?2
+                 ORDER BY r.seq
+                 LIMIT ?3",
+            )?;
+            let rows = stmt.query_map(params![id, after, limit], |row| {
+                Ok((
+                    JobResult {
+                        seq: row.get(0)?,
+                        run: row.get(1)?,
+                        index: row.get(2)?,
+                        input: Map::new(),
+                        prompt: row.get(4)?,
+                        reply: row.get(5)?,
+                        error: row.get(6)?,
+                        finished_at: row.get(7)?,
+                    },
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?;
+            let mut results = Vec::new();
+            for row in rows {
+                let (mut result, data) = row?;
+                if let Some(data) = data {
+                    result.input = serde_json::from_str(&data)?;
+                }
+                results.push(result);
+            }
+            Ok(results)
+        })
+        .await
+    }
+
+    /// Returns whether a job was deleted; its rows and results go with it.
+    pub async fn delete_job(&self, sub: &str, id: &str) -> Result<bool> {
+        let (sub, id) = (sub.to_string(), id.to_string());
+        self.call(move |conn| {
+            let n = conn.execute(
+                "DELETE FROM jobs WHERE id = ?1 AND sub = ?2",
+                params![id, sub],
+            )?;
+            Ok(n > 0)
+        })
+        .await
+    }
+}
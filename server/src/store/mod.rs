@@ -0,0 +1,149 @@
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+pub mod conversations;
+pub mod jobs;
+pub mod personas;
+pub mod shares;
+
+/// Schema steps, applied in order and tracked with `PRAGMA user_version`.
+/// Append only: never edit a step that has shipped.
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE personas (
+        id               TEXT PRIMARY KEY,
+        sub              TEXT NOT NULL,
+        name             TEXT NOT NULL,
+        prompt           TEXT NOT NULL,
+        suggested_models TEXT NOT NULL,
+        options          TEXT NOT NULL,
+        variables        TEXT NOT NULL,
+        created_at       INTEGER NOT NULL,
+        updated_at       INTEGER NOT NULL,
+        UNIQUE (sub, name)
+    );
+    "#,
+    r#"
+    CREATE TABLE conversations (
+        sub         TEXT NOT NULL,
+        id          TEXT NOT NULL,
+        modified_at INTEGER NOT NULL,
+        deleted     INTEGER NOT NULL DEFAULT 0,
+        data        TEXT,
+        rev         INTEGER NOT NULL,
+        PRIMARY KEY (sub, id)
+    );
+    CREATE INDEX conversations_rev ON conversations (sub, rev);
+    "#,
+    r#"
+    CREATE TABLE shares (
+        token           TEXT PRIMARY KEY,
+        sub             TEXT NOT NULL,
+        owner           TEXT NOT NULL,
+        conversation_id TEXT NOT NULL,
+        title           TEXT NOT NULL,
+        snapshot        TEXT NOT NULL,
+        require_auth    INTEGER NOT NULL,
+        created_at      INTEGER NOT NULL,
+        expires_at      INTEGER
+    );
+    CREATE INDEX shares_sub ON shares (sub, created_at);
+    "#,
+    r#"
+    CREATE TABLE jobs (
+        id          TEXT PRIMARY KEY,
+        sub         TEXT NOT NULL,
+        username    TEXT NOT NULL,
+        name        TEXT NOT NULL,
+        spec        TEXT NOT NULL,
+        schedule    TEXT,
+        status      TEXT NOT NULL,
+        run         INTEGER NOT NULL DEFAULT 0,
+        total       INTEGER NOT NULL DEFAULT 0,
+        done        INTEGER NOT NULL DEFAULT 0,
+        failed      INTEGER NOT NULL DEFAULT 0,
+        error       TEXT,
+        next_run_at INTEGER,
+        created_at  INTEGER NOT NULL,
+        updated_at  INTEGER NOT NULL
+    );
+    CREATE INDEX jobs_sub ON jobs (sub, created_at);
+    CREATE TABLE job_inputs (
+        job_id TEXT NOT NULL REFERENCES jobs (id) ON DELETE CASCADE,
+        idx    INTEGER NOT NULL,
+        data   TEXT NOT NULL,
+        PRIMARY KEY (job_id, idx)
+    );
+    CREATE TABLE job_results (
+        seq         INTEGER PRIMARY KEY AUTOINCREMENT,
+        job_id      TEXT NOT NULL REFERENCES jobs (id) ON DELETE CASCADE,
+        run         INTEGER NOT NULL,
+        idx         INTEGER NOT NULL,
+        prompt      TEXT NOT NULL,
+        reply       TEXT,
+        error       TEXT,
+        finished_at INTEGER NOT NULL,
+        UNIQUE (job_id, run, idx)
+    );
+    "#,
+];
+
+/// Per-user data in a single SQLite file. Every query runs on the blocking
+/// pool behind one connection; a personal deployment sees a handful of small
+/// queries per request, so a pool would buy nothing.
+#[derive(Clone)]
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Store {
+    pub fn open(path: &str) -> Result<Self> {
+        if let Some(dir) = Path::new(path).parent() {
+            if !dir.as_os_str().is_empty() {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("create database directory {}", dir.display()))?;
+            }
+        }
+        let mut conn =
+            Connection::open(path).with_context(|| format!("open database at {path}"))?;
+        // journal_mode answers with a row, so it can't go through pragma_update.
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run `f` against the connection on the blocking pool.
+    pub async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut conn)
+        })
+        .await
+        .context("database task panicked")?
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(step)
+            .with_context(|| format!("apply migration {}", i + 1))?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+        tracing::info!("Applied database migration {}", i + 1);
+    }
+    Ok(())
+}
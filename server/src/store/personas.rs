@@ -0,0 +1,129 @@
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension, Row};
+
+use super::Store;
+use crate::clock::now_secs;
+use crate::prompts::SystemPrompt;
+
+const COLUMNS: &str = "id, name, prompt, suggested_models, options, variables";
+
+/// A `COLUMNS` row; the last three are JSON.
+type PersonaRow = (String, String, String, String, String, String);
+
+fn from_row(row: &Row) -> rusqlite::Result<PersonaRow> {
+    Ok((
+        row.get(0)?,
+        row.get(1)?,
+        row.get(2)?,
+        row.get(3)?,
+        row.get(4)?,
+        row.get(5)?,
+    ))
+}
+
+fn decode((id, name, prompt, models, options, variables): PersonaRow) -> Result<SystemPrompt> {
+    Ok(SystemPrompt {
+        id: Some(id),
+        name,
+        prompt,
+        suggested_models: serde_json::from_str(&models)?,
+        options: serde_json::from_str(&options)?,
+        variables: serde_json::from_str(&variables)?,
+    })
+}
+
+// Personas a user created in the UI, stored per OIDC subject.
+impl Store {
+    pub async fn list_personas(&self, sub: &str) -> Result<Vec<SystemPrompt>> {
+        let sub = sub.to_string();
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM personas WHERE sub = ?1 ORDER BY created_at, name"
+            ))?;
+            let rows = stmt.query_map(params![sub], from_row)?;
+            rows.map(|row| decode(row?)).collect()
+        })
+        .await
+    }
+
+    pub async fn create_persona(&self, sub: &str, persona: SystemPrompt) -> Result<SystemPrompt> {
+        let sub = sub.to_string();
+        self.call(move |conn| {
+            let id = uuid::Uuid::new_v4().to_string();
+            let now = now_secs();
+            conn.execute(
+                "INSERT INTO personas
+                    (id, sub, name, prompt, suggested_models, options, variables, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+                params![
+                    id,
+                    sub,
+                    persona.name,
+                    persona.prompt,
+                    serde_json::to_string(&persona.suggested_models)?,
+                    serde_json::to_string(&persona.options)?,
+                    serde_json::to_string(&persona.variables)?,
+                    now,
+                ],
+            )?;
+            Ok(SystemPrompt {
+                id: Some(id),
+                ..persona
+            })
+        })
+        .await
+    }
+
+    /// Returns `None` when `id` doesn't exist or belongs to someone else.
+    pub async fn update_persona(
+        &self,
+        sub: &str,
+        id: &str,
+        persona: SystemPrompt,
+    ) -> Result<Option<SystemPrompt>> {
+        let (sub, id) = (sub.to_string(), id.to_string());
+        self.call(move |conn| {
+            let changed = conn.execute(
+                "UPDATE personas
+                 SET name = ?3, prompt = ?4, suggested_models = ?5, options = ?6,
+                     variables = ?7, updated_at = ?8
+                 WHERE id = ?1 AND sub = ?2",
+                params![
+                    id,
+                    sub,
+                    persona.name,
+                    persona.prompt,
+                    serde_json::to_string(&persona.suggested_models)?,
+                    serde_json::to_string(&persona.options)?,
+                    serde_json::to_string(&persona.variables)?,
+                    now_secs(),
+                ],
+            )?;
+            if changed == 0 {
+                return Ok(None);
+            }
+            conn.query_row(
+                &format!("SELECT {COLUMNS} FROM personas WHERE id = ?1"),
+                params![id],
+                from_row,
+            )
+            .optional()?
+            .map(decode)
+            .transpose()
+        })
+        .await
+    }
+
+    /// Returns `false` when there was nothing of this user's to delete.
+    pub async fn delete_persona(&self, sub: &str, id: &str) -> Result<bool> {
+        let (sub, id) = (sub.to_string(), id.to_string());
+        self.call(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM personas WHERE id = ?1 AND sub = ?2",
+                params![id, sub],
+            )?;
+            Ok(deleted > 0)
+        })
+        .await
+    }
+}
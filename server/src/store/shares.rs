@@ -0,0 +1,151 @@
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+
+use super::Store;
+use crate::auth::oidc::random_b64_url;
+use crate::clock::now_secs;
+
+/// A read-only link to a snapshot of one conversation. The token is the
+/// whole capability, so it is long and random rather than a UUID.
+#[derive(Debug, Clone, Serialize)]
+pub struct Share {
+    pub token: String,
+    pub conversation_id: String,
+    pub title: String,
+    pub require_auth: bool,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+}
+
+/// What the public endpoint serves: the snapshot plus who shared it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedConversation {
+    pub title: String,
+    pub owner: String,
+    pub require_auth: bool,
+    pub created_at: i64,
+    pub expires_at: Option<i64>,
+    pub snapshot: serde_json::Value,
+}
+
+pub struct NewShare {
+    pub conversation_id: String,
+    pub title: String,
+    pub snapshot: serde_json::Value,
+    pub require_auth: bool,
+    pub expires_at: Option<i64>,
+}
+
+const COLUMNS: &str = "token, conversation_id, title, require_auth, created_at, expires_at";
+
+fn from_row(row: &Row) -> rusqlite::Result<Share> {
+    Ok(Share {
+        token: row.get(0)?,
+        conversation_id: row.get(1)?,
+        title: row.get(2)?,
+        require_auth: row.get(3)?,
+        created_at: row.get(4)?,
+        expires_at: row.get(5)?,
+    })
+}
+
+// Shared conversation snapshots, owned per OIDC subject.
+impl Store {
+    pub async fn create_share(&self, sub: &str, owner: &str, share: NewShare) -> Result<Share> {
+        let (sub, owner) = (sub.to_string(), owner.to_string());
+        self.call(move |conn| {
+            let token = random_b64_url(24);
+            let now = now_secs();
+            conn.execute(
+                "INSERT INTO shares
+                    (token, sub, owner, conversation_id, title, snapshot, require_auth, created_at, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    token,
+                    sub,
+                    owner,
+                    share.conversation_id,
+                    share.title,
+                    serde_json::to_string(&share.snapshot)?,
+                    share.require_auth,
+                    now,
+                    share.expires_at,
+                ],
+            )?;
+            Ok(Share {
+                token,
+                conversation_id: share.conversation_id,
+                title: share.title,
+                require_auth: share.require_auth,
+                created_at: now,
+                expires_at: share.expires_at,
+            })
+        })
+        .await
+    }
+
+    /// Newest first, expired links included so their owner can clean up.
+    pub async fn list_shares(&self, sub: &str) -> Result<Vec<Share>> {
+        let sub = sub.to_string();
+        self.call(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {COLUMNS} FROM shares WHERE sub = ?1 ORDER BY created_at DESC"
+            ))?;
+            let rows = stmt.query_map(params![sub], from_row)?;
+            Ok(rows.collect::<rusqlite::Result<_>>()?)
+        })
+        .await
+    }
+
+    /// Returns `None` for unknown and expired tokens alike.
+    pub async fn get_share(&self, token: &str) -> Result<Option<SharedConversation>> {
+        let token = token.to_string();
+        self.call(move |conn| {
+            let row = conn
+                .query_row(
+                    "SELECT title, owner, require_auth, created_at, expires_at, snapshot
+                     FROM shares WHERE token = ?1 AND (expires_at IS NULL OR expires_at > ?2)",
+                    params![token, now_secs()],
+                    |row| {
+                        Ok((
+                            row.get::<_, String>(0)?,
+                            row.get::<_, String>(1)?,
+                            row.get::<_, bool>(2)?,
+                            row.get::<_, i64>(3)?,
+                            row.get::<_, Option<i64>>(4)?,
+                            row.get::<_, String>(5)?,
+                        ))
+                    },
+                )
+                .optional()?;
+            row.map(
+                |(title, owner, require_auth, created_at, expires_at, snapshot)| {
+                    Ok(SharedConversation {
+                        title,
+                        owner,
+                        require_auth,
+                        created_at,
+                        expires_at,
+                        snapshot: serde_json::from_str(&snapshot)?,
+                    })
+                },
+            )
+            .transpose()
+        })
+        .await
+    }
+
+    /// Returns `false` when there was nothing of this user's to revoke.
+    pub async fn delete_share(&self, sub: &str, token: &str) -> Result<bool> {
+        let (sub, token) = (sub.to_string(), token.to_string());
+        self.call(move |conn| {
+            let deleted = conn.execute(
+                "DELETE FROM shares WHERE token = ?1 AND sub = ?2",
+                params![token, sub],
+            )?;
+            Ok(deleted > 0)
+        })
+        .await
+    }
+}
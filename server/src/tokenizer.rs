@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tokenizers::Tokenizer;
+
+use crate::config::TokenizerConfig;
+
+/// Counts tokens with a model's own tokenizer, read from
+/// `<dir>/<model>.json` (a Hugging Face `tokenizer.json`). For a tag like
+/// `llama3.1:8b` the file may also be named after the part before the
+/// colon, `llama3.1.json`.
+pub struct TokenizerStore {
+    dir: Option<PathBuf>,
+    /// Loaded on first use; `None` records that no file exists.
+    loaded: Mutex<HashMap<String, Option<Arc<Tokenizer>>>>,
+}
+
+impl TokenizerStore {
+    pub fn new(config: TokenizerConfig) -> Self {
+        Self {
+            dir: config.dir.map(PathBuf::from),
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.dir.is_some()
+    }
+
+    /// Token count of each text, or `None` when there is no tokenizer for
+    /// `model`.
+    pub fn count(&self, model: &str, texts: &[String]) -> Result<Option<Vec<usize>>> {
+        let Some(tokenizer) = self.get(model)? else {
+            return Ok(None);
+        };
+        texts
+            .iter()
+            .map(|text| {
+                tokenizer
+                    .encode(text.as_str(), false)
+                    .map(|encoding| encoding.len())
+                    .map_err(|e| anyhow!("tokenize with {}: {}", model, e))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(Some)
+    }
+
+    fn get(&self, model: &str) -> Result<Option<Arc<Tokenizer>>> {
+        let Some(dir) = &self.dir else {
+            return Ok(None);
+        };
+        if let Some(cached) = self.loaded.lock().unwrap().get(model) {
+            return Ok(cached.clone());
+        }
+
+        // Model names come from the client; keep them inside `dir`.
+        let name = model.replace(['/', '\\'], "_");
+        if name.is_empty() || name.starts_with('.') {
+            return Ok(None);
+        }
+        let mut candidates = vec![name.clone()];
+        if let Some((base, _tag)) = name.split_once(':') {
+            candidates.push(base.to_string());
+        }
+        let path = candidates
+            .into_iter()
+            .map(|c| dir.join(format!("{}.json", c)))
+            .find(|p| p.is_file());
+
+        let tokenizer = match path {
+            Some(path) => {
+                let tokenizer = Tokenizer::from_file(&path)
+                    .map_err(|e| anyhow!("load {}: {}", path.display(), e))?;
+                tracing::info!("Loaded tokenizer for {} from {}", model, path.display());
+                Some(Arc::new(tokenizer))
+            }
+            None => None,
+        };
+        self.loaded
+            .lock()
+            .unwrap()
+            .insert(model.to_string(), tokenizer.clone());
+        Ok(tokenizer)
+    }
+}